@@ -0,0 +1,521 @@
+// Optional authentication of the clients by API key.
+//
+// Each key restricts the datasets the client can query and the number of requests
+// it can make per second. The middleware is a no-op if no keys are configured.
+
+use crate::model::ApiError;
+use crate::routes::is_valid_dataset;
+use actix_service::{Service, Transform};
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    Error, HttpResponse,
+};
+use futures::future::{ok, Either, FutureResult};
+use futures::Poll;
+use serde::Deserialize;
+use slog_scope::{info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Routes that can be called without key
+const PUBLIC_PATHS: &[&str] = &[
+    "/",
+    "/status",
+    "/metrics",
+    "/openapi.json",
+    "/health/live",
+    "/health/ready",
+];
+
+/// Routes that can only be called with a key allowing it, even without keys configured
+const EXPORT_PREFIX: &str = "/export/";
+
+const API_KEY_HEADER: &str = "X-Api-Key";
+
+lazy_static::lazy_static! {
+    static ref API_KEY_COUNTER: prometheus::CounterVec = prometheus::register_counter_vec!(
+        "bragi_api_key_requests_total",
+        "Total number of HTTP requests made by client, with the outcome of the key check.",
+        &["client", "status"]
+    )
+    .unwrap();
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    /// Name of the client, used in the logs and the metrics (instead of the key)
+    pub name: String,
+    /// Maximum number of requests per second, no limit if not set
+    pub rate_limit: Option<f64>,
+    /// The `pt_dataset` the client can query
+    #[serde(default)]
+    pub pt_datasets: Vec<String>,
+    /// The `poi_dataset` the client can query
+    #[serde(default)]
+    pub poi_datasets: Vec<String>,
+    /// Can the client use `_all_data`
+    #[serde(default)]
+    pub all_data: bool,
+    /// Can the client use `_debug`
+    #[serde(default)]
+    pub debug: bool,
+    /// Can the client use `/export`, restricted to its datasets for the stops and the POIs
+    #[serde(default)]
+    pub export: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKeysConfig {
+    pub keys: Vec<ApiKey>,
+}
+
+impl ApiKeysConfig {
+    pub fn new(settings: &str) -> Result<ApiKeysConfig, String> {
+        toml::from_str(settings).map_err(|e| e.to_string())
+    }
+}
+
+/// The parameters of a request that need a permission
+#[derive(Deserialize, Debug, Default)]
+struct RequestedData {
+    #[serde(default)]
+    pt_dataset: Vec<String>,
+    #[serde(default)]
+    poi_dataset: Vec<String>,
+    #[serde(rename = "_all_data", default)]
+    all_data: bool,
+    #[serde(rename = "_debug", default)]
+    debug: Option<bool>,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Rejection {
+    InvalidParam(String),
+    MissingKey,
+    UnknownKey,
+    Forbidden(String),
+    RateLimited,
+}
+
+impl Rejection {
+    fn status(&self) -> &'static str {
+        match *self {
+            Rejection::InvalidParam(_) => "invalid_param",
+            Rejection::MissingKey | Rejection::UnknownKey => "unauthorized",
+            Rejection::Forbidden(_) => "forbidden",
+            Rejection::RateLimited => "rate_limited",
+        }
+    }
+
+    fn response(&self) -> HttpResponse {
+        let (status, short, long) = match self {
+            Rejection::InvalidParam(msg) => (
+                StatusCode::BAD_REQUEST,
+                "validation error",
+                format!("invalid argument: {}", msg),
+            ),
+            Rejection::MissingKey => (
+                StatusCode::UNAUTHORIZED,
+                "authentication error",
+                format!(
+                    "an api key is required, in the '{}' header or the 'api_key' parameter",
+                    API_KEY_HEADER
+                ),
+            ),
+            Rejection::UnknownKey => (
+                StatusCode::UNAUTHORIZED,
+                "authentication error",
+                "invalid api key".to_owned(),
+            ),
+            Rejection::Forbidden(msg) => {
+                (StatusCode::FORBIDDEN, "authorization error", msg.clone())
+            }
+            Rejection::RateLimited => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate limit error",
+                "too many requests".to_owned(),
+            ),
+        };
+        HttpResponse::build(status).json(ApiError {
+            short: short.to_owned(),
+            long,
+        })
+    }
+}
+
+fn check_datasets(param: &str, requested: &[String], allowed: &[String]) -> Result<(), Rejection> {
+    match requested.iter().find(|d| !allowed.contains(d)) {
+        Some(d) => Err(Rejection::Forbidden(format!(
+            "{} '{}' is not allowed",
+            param, d
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl ApiKey {
+    fn check_permissions(&self, requested: &RequestedData) -> Result<(), Rejection> {
+        if requested.all_data && !self.all_data {
+            return Err(Rejection::Forbidden(
+                "'_all_data' is not allowed".to_owned(),
+            ));
+        }
+        if requested.debug.unwrap_or(false) && !self.debug {
+            return Err(Rejection::Forbidden("'_debug' is not allowed".to_owned()));
+        }
+        check_datasets("pt_dataset", &requested.pt_dataset, &self.pt_datasets)?;
+        check_datasets("poi_dataset", &requested.poi_dataset, &self.poi_datasets)
+    }
+
+    // the path is `/export/{type}/{dataset}`
+    fn check_export(&self, path: &str) -> Result<(), Rejection> {
+        if !self.export {
+            return Err(Rejection::Forbidden("'/export' is not allowed".to_owned()));
+        }
+        let mut parts = path[EXPORT_PREFIX.len()..].splitn(2, '/');
+        let doc_type = parts.next().unwrap_or_default();
+        let dataset = parts.next().unwrap_or_default();
+        // checked before the permissions, as `fr,munin_poi_x` would not match a dataset name
+        if !is_valid_dataset(dataset) {
+            return Err(Rejection::InvalidParam(format!(
+                "invalid dataset '{}'",
+                dataset
+            )));
+        }
+        let dataset = vec![dataset.to_owned()];
+        match doc_type {
+            "stop" => check_datasets("pt_dataset", &dataset, &self.pt_datasets),
+            "poi" => check_datasets("poi_dataset", &dataset, &self.poi_datasets),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        TokenBucket {
+            tokens: rate.max(1.),
+            last_refill: now,
+        }
+    }
+
+    // the bucket holds at most one second of requests, so short bursts are allowed
+    fn try_take(&mut self, rate: f64, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(rate.max(1.));
+        self.last_refill = now;
+        if self.tokens >= 1. {
+            self.tokens -= 1.;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+struct Keys {
+    keys: HashMap<String, ApiKey>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Keys {
+    // returns the name of the client and the outcome of the check
+    fn check(
+        &self,
+        path: &str,
+        query_string: &str,
+        header: Option<&str>,
+    ) -> (String, Result<(), Rejection>) {
+        let requested: RequestedData =
+            match serde_qs::Config::new(5, false).deserialize_str(query_string) {
+                Ok(requested) => requested,
+                Err(e) => {
+                    return (
+                        "unknown".to_owned(),
+                        Err(Rejection::InvalidParam(e.to_string())),
+                    )
+                }
+            };
+        let key = match header.or_else(|| requested.api_key.as_deref()) {
+            Some(key) => key,
+            None => return ("anonymous".to_owned(), Err(Rejection::MissingKey)),
+        };
+        let api_key = match self.keys.get(key) {
+            Some(api_key) => api_key,
+            None => return ("unknown".to_owned(), Err(Rejection::UnknownKey)),
+        };
+        let res = api_key
+            .check_permissions(&requested)
+            .and_then(|_| {
+                if path.starts_with(EXPORT_PREFIX) {
+                    api_key.check_export(path)
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|_| match api_key.rate_limit {
+                Some(rate) => {
+                    let now = Instant::now();
+                    let mut buckets = self.buckets.lock().unwrap();
+                    let bucket = buckets
+                        .entry(api_key.key.clone())
+                        .or_insert_with(|| TokenBucket::new(rate, now));
+                    if bucket.try_take(rate, now) {
+                        Ok(())
+                    } else {
+                        Err(Rejection::RateLimited)
+                    }
+                }
+                None => Ok(()),
+            });
+        if let Err(ref rejection) = res {
+            info!(
+                "request on {} rejected for client {}: {:?}",
+                path, api_key.name, rejection
+            );
+        }
+        (api_key.name.clone(), res)
+    }
+}
+
+#[derive(Clone)]
+#[must_use = "must be set up as middleware for actix-web"]
+/// Check the API key of each request.
+///
+/// The key is given either by the `X-Api-Key` header or by the `api_key` parameter.
+/// The number of requests is tracked by the `bragi_api_key_requests_total` counter
+/// (labels: client, status).
+pub struct ApiKeys {
+    inner: Option<Arc<Keys>>,
+}
+
+impl ApiKeys {
+    /// Create the middleware, without configuration every request is accepted
+    pub fn new(config: Option<ApiKeysConfig>) -> Self {
+        ApiKeys {
+            inner: config.map(|config| {
+                let keys: HashMap<_, _> = config
+                    .keys
+                    .into_iter()
+                    .map(|k| (k.key.clone(), k))
+                    .collect();
+                if keys.is_empty() {
+                    warn!("no api key configured, all requests will be rejected");
+                }
+                Arc::new(Keys {
+                    keys,
+                    buckets: Mutex::new(HashMap::new()),
+                })
+            }),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for ApiKeys
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiKeysMiddleware<S>;
+    type Future = FutureResult<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ApiKeysMiddleware {
+            service,
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+#[doc(hidden)]
+/// Middleware service for ApiKeys
+pub struct ApiKeysMiddleware<S> {
+    service: S,
+    inner: Option<Arc<Keys>>,
+}
+
+impl<S, B> Service for ApiKeysMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, FutureResult<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let keys = match self.inner {
+            Some(ref keys) if !PUBLIC_PATHS.contains(&req.path()) => keys,
+            None if req.path().starts_with(EXPORT_PREFIX) => {
+                let rejection = Rejection::Forbidden(
+                    "'/export' needs the api keys to be configured".to_owned(),
+                );
+                API_KEY_COUNTER
+                    .with_label_values(&["anonymous", rejection.status()])
+                    .inc();
+                return Either::B(ok(req.into_response(rejection.response().into_body())));
+            }
+            _ => return Either::A(self.service.call(req)),
+        };
+        let header = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|h| h.to_str().ok());
+        let (client, res) = keys.check(req.path(), req.query_string(), header);
+        let status = res
+            .as_ref()
+            .map(|_| "accepted")
+            .unwrap_or_else(Rejection::status);
+        API_KEY_COUNTER.with_label_values(&[&client, status]).inc();
+        match res {
+            Ok(()) => Either::A(self.service.call(req)),
+            Err(rejection) => Either::B(ok(req.into_response(rejection.response().into_body()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn keys() -> Keys {
+        let config = ApiKeysConfig::new(
+            r#"
+            [[keys]]
+            key = "secret"
+            name = "navitia"
+            rate_limit = 2
+            pt_datasets = ["fr-idf"]
+            poi_datasets = ["idf-pois"]
+
+            [[keys]]
+            key = "admin"
+            name = "admin"
+            all_data = true
+            debug = true
+
+            [[keys]]
+            key = "audit"
+            name = "audit"
+            pt_datasets = ["fr-idf"]
+            export = true
+            "#,
+        )
+        .unwrap();
+        Keys {
+            keys: config
+                .keys
+                .into_iter()
+                .map(|k| (k.key.clone(), k))
+                .collect(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn test_api_key_permissions() {
+        let keys = keys();
+        let check = |qs: &str, header: Option<&str>| keys.check("/autocomplete", qs, header).1;
+
+        assert_eq!(check("q=toto", None), Err(Rejection::MissingKey));
+        assert_eq!(
+            check("q=toto&api_key=plop", None),
+            Err(Rejection::UnknownKey)
+        );
+        assert_eq!(check("q=toto&_all_data=true", Some("admin")), Ok(()));
+        assert_eq!(check("q=toto&_debug=true&api_key=admin", None), Ok(()));
+        assert_eq!(
+            check("q=toto&_all_data=true&api_key=secret", None),
+            Err(Rejection::Forbidden(
+                "'_all_data' is not allowed".to_owned()
+            ))
+        );
+        assert_eq!(
+            check(
+                "q=toto&pt_dataset[]=fr-idf&poi_dataset[]=idf-pois",
+                Some("secret")
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                "q=toto&pt_dataset[]=fr-idf&pt_dataset[]=fr-ne",
+                Some("secret")
+            ),
+            Err(Rejection::Forbidden(
+                "pt_dataset 'fr-ne' is not allowed".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_api_key_export() {
+        let keys = keys();
+        let check = |path: &str, key: &str| keys.check(path, "", Some(key)).1;
+
+        assert_eq!(check("/export/stop/fr-idf", "audit"), Ok(()));
+        assert_eq!(check("/export/addr/fr", "audit"), Ok(()));
+        assert_eq!(
+            check("/export/stop/fr-ne", "audit"),
+            Err(Rejection::Forbidden(
+                "pt_dataset 'fr-ne' is not allowed".to_owned()
+            ))
+        );
+        assert_eq!(
+            check("/export/poi/idf-pois", "audit"),
+            Err(Rejection::Forbidden(
+                "poi_dataset 'idf-pois' is not allowed".to_owned()
+            ))
+        );
+        assert_eq!(
+            check("/export/stop/fr-idf", "secret"),
+            Err(Rejection::Forbidden("'/export' is not allowed".to_owned()))
+        );
+        assert_eq!(
+            check("/export/addr/fr,munin_poi_x", "audit"),
+            Err(Rejection::InvalidParam(
+                "invalid dataset 'fr,munin_poi_x'".to_owned()
+            ))
+        );
+        assert_eq!(
+            check("/export/stop/fr-*", "audit"),
+            Err(Rejection::InvalidParam("invalid dataset 'fr-*'".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2., start);
+        assert!(bucket.try_take(2., start));
+        assert!(bucket.try_take(2., start));
+        assert!(!bucket.try_take(2., start));
+        assert!(bucket.try_take(2., start + Duration::from_millis(500)));
+        assert!(!bucket.try_take(2., start + Duration::from_millis(600)));
+        // the bucket does not fill over its capacity
+        let later = start + Duration::from_secs(60);
+        assert!(bucket.try_take(2., later));
+        assert!(bucket.try_take(2., later));
+        assert!(!bucket.try_take(2., later));
+    }
+}
@@ -0,0 +1,141 @@
+//! Confidence of the autocomplete results, so the clients can tell a confident match
+//! from a weak guess with a threshold.
+//!
+//! The Elasticsearch score alone can't be compared between two queries, so the confidence
+//! mixes the token overlap between the query and the label of the place (how much of the
+//! query is found in the label), and the score relative to the best result.
+//! The results of the fuzzy pass are penalized.
+
+use crate::query::MatchType;
+
+/// Weight of the token overlap in the confidence, the rest being the relative score
+const OVERLAP_WEIGHT: f64 = 0.7;
+/// Factor applied to the confidence of the results found by the fuzzy pass
+const FUZZY_PENALTY: f64 = 0.8;
+
+// lowercase words, without the most common diacritics
+fn tokens(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.chars().flat_map(char::to_lowercase).map(fold).collect())
+        .collect()
+}
+
+fn fold(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        c => c,
+    }
+}
+
+/// Fraction of the words of `q` found in `label`.
+/// The last word of `q` can be a prefix, since it may still be typed.
+pub fn token_overlap(q: &str, label: &str) -> f64 {
+    let q_tokens = tokens(q);
+    let label_tokens = tokens(label);
+    if q_tokens.is_empty() {
+        return 0.;
+    }
+    let last = q_tokens.len() - 1;
+    let found = q_tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, q_token)| {
+            label_tokens
+                .iter()
+                .any(|l| l == *q_token || (*i == last && l.starts_with(q_token.as_str())))
+        })
+        .count();
+    found as f64 / q_tokens.len() as f64
+}
+
+/// Confidence in [0, 1] of a result with the label `label` and the score `score`,
+/// `max_score` being the score of the best result of the response
+pub fn confidence(
+    q: &str,
+    label: &str,
+    score: Option<f64>,
+    max_score: Option<f64>,
+    match_type: MatchType,
+) -> f64 {
+    let relative_score = match (score, max_score) {
+        (Some(score), Some(max_score)) if max_score > 0. => (score / max_score).min(1.).max(0.),
+        _ => 0.,
+    };
+    let confidence =
+        OVERLAP_WEIGHT * token_overlap(q, label) + (1. - OVERLAP_WEIGHT) * relative_score;
+    match match_type {
+        MatchType::Prefix => confidence,
+        MatchType::Fuzzy => confidence * FUZZY_PENALTY,
+    }
+}
+
+/// Confidence of each place of an autocomplete response
+pub fn confidences(q: &str, places: &[mimir::Place], match_type: MatchType) -> Vec<f64> {
+    let max_score = places
+        .iter()
+        .filter_map(mimir::Place::score)
+        .fold(None, |max: Option<f64>, score| {
+            Some(max.map_or(score, |max| max.max(score)))
+        });
+    places
+        .iter()
+        .map(|place| confidence(q, place.label(), place.score(), max_score, match_type))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{} is not {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_token_overlap() {
+        assert_close(
+            token_overlap("rue hector malot", "Rue Hector Malot (Paris)"),
+            1.,
+        );
+        // the last word can be a prefix, and the diacritics are ignored
+        assert_close(token_overlap("chatelet les h", "Châtelet-Les Halles"), 1.);
+        assert_close(token_overlap("rue hect malot", "Rue Hector Malot"), 2. / 3.);
+        assert_close(token_overlap("melun", "Livry-sur-Seine"), 0.);
+        assert_close(token_overlap("", "Melun"), 0.);
+    }
+
+    #[test]
+    fn test_confidence() {
+        let best = confidence("melun", "Melun", Some(10.), Some(10.), MatchType::Prefix);
+        assert_close(best, 1.);
+
+        let second = confidence("melun", "Melun", Some(5.), Some(10.), MatchType::Prefix);
+        assert!(second < best);
+
+        let fuzzy = confidence("melun", "Melun", Some(10.), Some(10.), MatchType::Fuzzy);
+        assert!(fuzzy < best);
+
+        let unrelated = confidence(
+            "melun",
+            "Livry-sur-Seine",
+            Some(1.),
+            Some(10.),
+            MatchType::Fuzzy,
+        );
+        assert!(unrelated < 0.1);
+        assert!(unrelated >= 0.);
+    }
+}
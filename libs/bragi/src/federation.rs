@@ -0,0 +1,458 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Federated search over several Elasticsearch clusters.
+//!
+//! Each cluster hosts some of the datasets (typically one cluster per region),
+//! the queries are sent in parallel to the clusters hosting the requested datasets
+//! and the results are merged.
+
+use crate::clone_or_create;
+use crate::model::BragiError;
+use crate::query::{AutocompleteResult, MatchType};
+use mimir::rubber::{DocumentModel, Rubber};
+use serde::Deserialize;
+use slog_scope::{error, warn};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ClusterConfig {
+    /// Elasticsearch connection string of the cluster
+    pub url: String,
+    /// Document model of the cluster, `typeless` for Elasticsearch 7+
+    #[serde(default)]
+    pub document_model: DocumentModel,
+    /// Does the cluster hosts the geographic data (admins, streets, addresses and public POIs)
+    #[serde(default)]
+    pub geo_data: bool,
+    #[serde(default)]
+    pub pt_datasets: Vec<String>,
+    #[serde(default)]
+    pub poi_datasets: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FederationConfig {
+    pub clusters: Vec<ClusterConfig>,
+}
+
+impl FederationConfig {
+    pub fn new(settings: &str) -> Result<FederationConfig, String> {
+        toml::from_str(settings).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Cluster {
+    config: ClusterConfig,
+    autocomplete_rubber: Rubber,
+    features_rubber: Rubber,
+    reverse_rubber: Rubber,
+}
+
+/// The part of a request a cluster has to answer.
+pub struct Target {
+    pub rubber: Rubber,
+    pub pt_datasets: Vec<String>,
+    pub poi_datasets: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Federation {
+    clusters: Vec<Cluster>,
+}
+
+impl Federation {
+    pub fn new(
+        config: FederationConfig,
+        autocomplete_timeout: Option<Duration>,
+        features_timeout: Option<Duration>,
+        reverse_timeout: Option<Duration>,
+    ) -> Federation {
+        Federation {
+            clusters: config
+                .clusters
+                .into_iter()
+                .map(|config| Cluster {
+                    autocomplete_rubber: Rubber::new_with_timeout(
+                        &config.url,
+                        autocomplete_timeout,
+                    )
+                    .with_document_model(config.document_model),
+                    features_rubber: Rubber::new_with_timeout(&config.url, features_timeout)
+                        .with_document_model(config.document_model),
+                    reverse_rubber: Rubber::new_with_timeout(&config.url, reverse_timeout)
+                        .with_document_model(config.document_model),
+                    config,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn autocomplete_targets(
+        &self,
+        timeout: Option<Duration>,
+        pt_datasets: &[String],
+        poi_datasets: &[String],
+        all_data: bool,
+    ) -> Vec<Target> {
+        self.targets(
+            |c| &c.autocomplete_rubber,
+            timeout,
+            pt_datasets,
+            poi_datasets,
+            all_data,
+        )
+    }
+
+    pub fn features_targets(
+        &self,
+        timeout: Option<Duration>,
+        pt_datasets: &[String],
+        poi_datasets: &[String],
+        all_data: bool,
+    ) -> Vec<Target> {
+        self.targets(
+            |c| &c.features_rubber,
+            timeout,
+            pt_datasets,
+            poi_datasets,
+            all_data,
+        )
+    }
+
+    /// The clusters hosting the geographic data, where the addresses and streets are
+    pub fn reverse_targets(&self, timeout: Option<Duration>) -> Vec<Target> {
+        self.targets(|c| &c.reverse_rubber, timeout, &[], &[], false)
+    }
+
+    // A cluster is queried if it hosts the geographic data or one of the requested datasets.
+    // It is only given the datasets it hosts.
+    fn targets(
+        &self,
+        rubber: impl Fn(&Cluster) -> &Rubber,
+        timeout: Option<Duration>,
+        pt_datasets: &[String],
+        poi_datasets: &[String],
+        all_data: bool,
+    ) -> Vec<Target> {
+        self.clusters
+            .iter()
+            .filter_map(|cluster| {
+                let hosted = |requested: &[String], hosted: &[String]| -> Vec<String> {
+                    requested
+                        .iter()
+                        .filter(|d| hosted.contains(d))
+                        .cloned()
+                        .collect()
+                };
+                let pt_datasets = hosted(pt_datasets, &cluster.config.pt_datasets);
+                let poi_datasets = hosted(poi_datasets, &cluster.config.poi_datasets);
+                if all_data
+                    || cluster.config.geo_data
+                    || !pt_datasets.is_empty()
+                    || !poi_datasets.is_empty()
+                {
+                    Some(Target {
+                        rubber: clone_or_create(rubber(cluster), timeout),
+                        pt_datasets,
+                        poi_datasets,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Run `query` on all the targets in parallel
+pub fn fan_out<F, R>(targets: Vec<Target>, query: F) -> Vec<R>
+where
+    F: Fn(Target) -> R + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    let query = Arc::new(query);
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let query = query.clone();
+            thread::spawn(move || query(target))
+        })
+        .collect();
+    handles
+        .into_iter()
+        .filter_map(|handle| {
+            handle
+                .join()
+                .map_err(|_| error!("a federated query has panicked"))
+                .ok()
+        })
+        .collect()
+}
+
+/// Merge the results of all the clusters.
+///
+/// The Elasticsearch scores of two clusters are not comparable (they depend on the
+/// statistics of each index), so the results are interleaved by rank: the best result
+/// of each cluster comes first, then their second results, and so on.
+///
+/// A failing cluster is ignored as long as another cluster answered.
+pub fn merge(
+    results: Vec<Result<Vec<mimir::Place>, BragiError>>,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<mimir::Place>, BragiError> {
+    let mut first_error = None;
+    let mut ranked = vec![];
+    for result in results {
+        match result {
+            Ok(places) => ranked.push(places),
+            Err(err) => {
+                warn!("federated query failed: {}", err);
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    if let (true, Some(err)) = (ranked.is_empty(), first_error) {
+        return Err(err);
+    }
+    Ok(interleave(ranked)
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect())
+}
+
+/// Merge the autocomplete results of all the clusters (cf `merge`)
+///
+/// The Elasticsearch time is the one of the slowest cluster, and the fuzzy pass is
+/// reported if one of the cluster needed it.
+pub fn merge_autocomplete(
+    results: Vec<Result<AutocompleteResult, BragiError>>,
+    offset: u64,
+    limit: u64,
+) -> Result<AutocompleteResult, BragiError> {
+    let mut took = 0;
+    let mut match_type = MatchType::Prefix;
+    let results = results
+        .into_iter()
+        .map(|res| {
+            res.map(|r| {
+                took = took.max(r.took);
+                if r.match_type == MatchType::Fuzzy {
+                    match_type = MatchType::Fuzzy;
+                }
+                r.places
+            })
+        })
+        .collect();
+    merge(results, offset, limit).map(|places| AutocompleteResult {
+        places,
+        match_type,
+        took,
+    })
+}
+
+/// Return the place nearest to the requested coordinate among the results of all the clusters
+///
+/// A failing cluster is ignored as long as another cluster answered.
+pub fn nearest(
+    results: Vec<Result<Vec<mimir::Place>, BragiError>>,
+) -> Result<Vec<mimir::Place>, BragiError> {
+    let places = merge(results, 0, u64::max_value())?;
+    Ok(places
+        .into_iter()
+        .min_by_key(|place| place.distance().unwrap_or_else(u32::max_value))
+        .into_iter()
+        .collect())
+}
+
+/// Return the places found by the first cluster that knows the object
+pub fn first_found(
+    results: Vec<Result<Vec<mimir::Place>, BragiError>>,
+) -> Result<Vec<mimir::Place>, BragiError> {
+    let mut error = BragiError::ObjectNotFound;
+    for result in results {
+        match result {
+            Ok(places) if !places.is_empty() => return Ok(places),
+            Ok(_) | Err(BragiError::ObjectNotFound) => {}
+            Err(err) => {
+                warn!("federated query failed: {}", err);
+                error = err;
+            }
+        }
+    }
+    Err(error)
+}
+
+fn interleave(ranked: Vec<Vec<mimir::Place>>) -> Vec<mimir::Place> {
+    let mut seen = HashSet::new();
+    let mut merged = vec![];
+    let mut iters: Vec<_> = ranked.into_iter().map(Vec::into_iter).collect();
+    loop {
+        let mut exhausted = true;
+        for iter in iters.iter_mut() {
+            if let Some(place) = iter.next() {
+                exhausted = false;
+                // the same object can be hosted by several clusters
+                if seen.insert(place.id().to_string()) {
+                    merged.push(place);
+                }
+            }
+        }
+        if exhausted {
+            return merged;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn street(id: &str) -> mimir::Place {
+        mimir::Place::Street(mimir::Street {
+            id: id.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn ids(places: &[mimir::Place]) -> Vec<&str> {
+        places.iter().map(|p| p.id()).collect()
+    }
+
+    #[test]
+    fn test_merge_interleaves_by_rank() {
+        let merged = merge(
+            vec![
+                Ok(vec![street("a1"), street("a2"), street("a3")]),
+                Ok(vec![street("b1")]),
+                Err(BragiError::ObjectNotFound),
+                Ok(vec![street("c1"), street("a2")]),
+            ],
+            0,
+            10,
+        )
+        .unwrap();
+        assert_eq!(ids(&merged), vec!["a1", "b1", "c1", "a2", "a3"]);
+
+        let merged = merge(
+            vec![
+                Ok(vec![street("a1"), street("a2")]),
+                Ok(vec![street("b1"), street("b2")]),
+            ],
+            1,
+            2,
+        )
+        .unwrap();
+        assert_eq!(ids(&merged), vec!["b1", "a2"]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let street_at = |id: &str, distance: u32| {
+            let mut place = street(id);
+            place.set_distance(distance);
+            place
+        };
+        let nearest = nearest(vec![
+            Ok(vec![street_at("a", 120)]),
+            Err(BragiError::ObjectNotFound),
+            Ok(vec![street_at("b", 30)]),
+            Ok(vec![]),
+        ])
+        .unwrap();
+        assert_eq!(ids(&nearest), vec!["b"]);
+    }
+
+    #[test]
+    fn test_merge_fails_only_if_all_clusters_fail() {
+        assert!(merge(vec![Err(BragiError::ObjectNotFound)], 0, 10).is_err());
+        assert!(merge(vec![], 0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_targets() {
+        let config = FederationConfig::new(
+            r#"
+            [[clusters]]
+            url = "http://geo:9200/munin"
+            geo_data = true
+            poi_datasets = ["shared"]
+
+            [[clusters]]
+            url = "http://north:9200/munin"
+            pt_datasets = ["lille", "amiens"]
+
+            [[clusters]]
+            url = "http://south:9200/munin"
+            pt_datasets = ["marseille"]
+            poi_datasets = ["private"]
+            "#,
+        )
+        .unwrap();
+        let federation = Federation::new(config, None, None, None);
+        let datasets = |targets: Vec<Target>| -> Vec<(Vec<String>, Vec<String>)> {
+            targets
+                .into_iter()
+                .map(|t| (t.pt_datasets, t.poi_datasets))
+                .collect()
+        };
+
+        // without dataset, only the geographic data are queried
+        assert_eq!(
+            datasets(federation.autocomplete_targets(None, &[], &[], false)),
+            vec![(vec![], vec![])]
+        );
+
+        let pt = vec!["lille".to_string(), "marseille".to_string()];
+        let poi = vec!["private".to_string()];
+        assert_eq!(
+            datasets(federation.autocomplete_targets(None, &pt, &poi, false)),
+            vec![
+                (vec![], vec![]),
+                (vec!["lille".to_string()], vec![]),
+                (vec!["marseille".to_string()], vec!["private".to_string()]),
+            ]
+        );
+
+        assert_eq!(federation.features_targets(None, &[], &[], true).len(), 3);
+
+        // the reverse geocoding only queries the geographic data
+        assert_eq!(
+            datasets(federation.reverse_targets(None)),
+            vec![(vec![], vec![])]
+        );
+    }
+}
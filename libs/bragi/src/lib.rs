@@ -31,34 +31,59 @@
 #[macro_use]
 extern crate prometheus;
 
-use mimir::rubber::Rubber;
+use crate::federation::Federation;
+use crate::query_log::{QueryLogConfig, QueryLogger};
+use crate::routes::{incompatible_indexes, IndexTemplateVersion};
+use mimir::rubber::{DocumentModel, Rubber};
 use slog_scope::debug;
 use std::convert::TryFrom;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
 use std::fs::read_to_string;
 
+pub mod api_key_middleware;
+mod confidence;
 mod extractors;
+pub mod federation;
 mod model;
+mod mvt;
+pub mod openapi;
 pub mod prometheus_middleware;
 pub(crate) mod query;
+mod query_log;
 mod query_settings;
 mod routes;
 pub mod server;
 
+pub use federation::FederationConfig;
 pub use query::make_place as query_make_place;
+pub use query::{autocomplete as query_autocomplete, AutocompleteResult, MatchType};
 pub use query_settings::QuerySettings;
 
+/// Timeout of the readiness probe when no `max_es_timeout` is given
+const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the indexes with another template version given by `/status` are kept
+const INCOMPATIBLE_INDEXES_TTL: Duration = Duration::from_secs(60);
+
 lazy_static::lazy_static! {
     static ref BRAGI_NB_THREADS: String = (8 * ::num_cpus::get()).to_string();
 }
 
 #[derive(StructOpt, Debug, Clone, Default)]
 pub struct Args {
-    /// Address to bind.
-    #[structopt(short = "b", long = "bind", default_value = "127.0.0.1:4000")]
-    pub bind: String,
+    /// Addresses to bind, the option can be repeated to listen on several addresses.
+    /// A unix socket is given by `unix:` followed by the path of the socket.
+    #[structopt(
+        short = "b",
+        long = "bind",
+        default_value = "127.0.0.1:4000",
+        number_of_values = 1
+    )]
+    pub bind: Vec<String>,
     /// Elasticsearch parameters, override BRAGI_ES environment variable.
     #[structopt(
         short = "c",
@@ -67,6 +92,15 @@ pub struct Args {
         env = "BRAGI_ES"
     )]
     pub connection_string: String,
+    /// Document model of Elasticsearch: `typed` (Elasticsearch 2 to 6)
+    /// or `typeless` (Elasticsearch 7+), override BRAGI_DOCUMENT_MODEL environment variable.
+    #[structopt(
+        long = "document-model",
+        default_value = "typed",
+        possible_values = &["typed", "typeless"],
+        env = "BRAGI_DOCUMENT_MODEL"
+    )]
+    pub document_model: DocumentModel,
     /// Number of threads used to serve http requests, override BRAGI_NB_THREADS environment variable.
     #[structopt(
         short = "t",
@@ -110,6 +144,79 @@ pub struct Args {
     pub http_cache_duration: u32,
     #[structopt(long = "weight-config-file")]
     pub weight_config_file: Option<String>,
+    /// Federation configuration file, listing the Elasticsearch clusters and the datasets
+    /// they host. If set, the queries are sent to all the relevant clusters
+    /// instead of the cluster given by `connection_string`.
+    #[structopt(long = "federation-config", env = "BRAGI_FEDERATION_CONFIG")]
+    pub federation_config: Option<String>,
+    /// API keys configuration file. If set, each request (except on `/`, `/status` and `/metrics`)
+    /// needs a valid key, and is restricted to the datasets and the rate limit of this key.
+    #[structopt(long = "api-keys-file", env = "BRAGI_API_KEYS_FILE")]
+    pub api_keys_file: Option<String>,
+
+    /// File where the autocomplete queries are logged (one JSON line per query),
+    /// no query log if not set.
+    #[structopt(long = "query-log-file", env = "BRAGI_QUERY_LOG_FILE")]
+    pub query_log_file: Option<String>,
+    /// Maximum size of the query log file in MB, before it is rotated
+    #[structopt(long = "query-log-max-size", default_value = "100")]
+    pub query_log_max_size: u64,
+    /// Number of rotated query log files kept
+    #[structopt(long = "query-log-max-files", default_value = "5")]
+    pub query_log_max_files: usize,
+    /// Fraction of the queries logged, between 0 and 1
+    #[structopt(long = "query-log-sampling", default_value = "1")]
+    pub query_log_sampling: f64,
+    /// Number of results logged for each query
+    #[structopt(long = "query-log-nb-results", default_value = "5")]
+    pub query_log_nb_results: usize,
+
+    /// On SIGTERM, duration in seconds during which bragi keeps serving the requests
+    /// but reports not ready (on `/health/ready`), for the load balancers to stop sending requests.
+    #[structopt(
+        long = "shutdown-delay",
+        env = "BRAGI_SHUTDOWN_DELAY",
+        default_value = "5"
+    )]
+    pub shutdown_delay: u64,
+    /// After the shutdown delay, maximum duration in seconds given to the in-flight requests to finish.
+    #[structopt(
+        long = "shutdown-grace-period",
+        env = "BRAGI_SHUTDOWN_GRACE_PERIOD",
+        default_value = "30"
+    )]
+    pub shutdown_grace_period: u64,
+
+    /// Origins allowed by the CORS policy (comma separated), all origins are allowed if not set.
+    #[structopt(
+        long = "cors-allowed-origins",
+        env = "BRAGI_CORS_ALLOWED_ORIGINS",
+        use_delimiter = true
+    )]
+    pub cors_allowed_origins: Vec<String>,
+    /// HTTP methods allowed by the CORS policy (comma separated)
+    #[structopt(
+        long = "cors-allowed-methods",
+        env = "BRAGI_CORS_ALLOWED_METHODS",
+        default_value = "GET,POST",
+        use_delimiter = true
+    )]
+    pub cors_allowed_methods: Vec<String>,
+    /// Request headers allowed by the CORS policy (comma separated), all headers are allowed if not set.
+    #[structopt(
+        long = "cors-allowed-headers",
+        env = "BRAGI_CORS_ALLOWED_HEADERS",
+        use_delimiter = true
+    )]
+    pub cors_allowed_headers: Vec<String>,
+    /// Compression of the responses: `gzip`, `br` (brotli), or `auto` to use the best
+    /// encoding accepted by the client. The responses are not compressed if not set.
+    #[structopt(
+        long = "compression",
+        env = "BRAGI_COMPRESSION",
+        possible_values = &["auto", "gzip", "br"]
+    )]
+    pub compression: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -117,10 +224,17 @@ pub struct Context {
     reverse_rubber: Rubber,
     features_rubber: Rubber,
     autocomplete_rubber: Rubber,
+    health_rubber: Rubber,
     pub cnx_string: String,
     pub http_cache_duration: u32,
     // pub rubber: Rubber,
     query_settings: QuerySettings,
+    federation: Option<Federation>,
+    query_logger: Option<Arc<QueryLogger>>,
+    // set when the server is shutting down
+    draining: Arc<AtomicBool>,
+    // the indexes with another template version and when they were fetched
+    incompatible_indexes: Arc<Mutex<Option<(Instant, Vec<IndexTemplateVersion>)>>>,
 }
 
 impl TryFrom<&Args> for Context {
@@ -145,19 +259,53 @@ impl TryFrom<&Args> for Context {
                 .map_err(|e| format!("Failed to read `{}`: {}", file_path, e))?,
             None => include_str!("../../../config/bragi-settings.toml").to_owned(),
         };
+        let federation = match args.federation_config {
+            Some(ref file_path) => {
+                let content = read_to_string(&file_path)
+                    .map_err(|e| format!("Failed to read `{}`: {}", file_path, e))?;
+                let config = FederationConfig::new(&content)
+                    .map_err(|err| format!("failed to parse `{}`: {}", file_path, err))?;
+                Some(Federation::new(
+                    config,
+                    bounded_timeout(args.max_es_autocomplete_timeout),
+                    bounded_timeout(args.max_es_features_timeout),
+                    bounded_timeout(args.max_es_reverse_timeout),
+                ))
+            }
+            None => None,
+        };
+        let query_logger = match args.query_log_file {
+            Some(ref path) => Some(Arc::new(QueryLogger::new(&QueryLogConfig {
+                path: path.into(),
+                max_size: args.query_log_max_size * 1024 * 1024,
+                max_files: args.query_log_max_files,
+                sampling: args.query_log_sampling,
+                nb_results: args.query_log_nb_results,
+            })?)),
+            None => None,
+        };
         Ok(Self {
             reverse_rubber: Rubber::new_with_timeout(
                 &args.connection_string,
                 bounded_timeout(args.max_es_reverse_timeout),
-            ),
+            )
+            .with_document_model(args.document_model),
             features_rubber: Rubber::new_with_timeout(
                 &args.connection_string,
                 bounded_timeout(args.max_es_features_timeout),
-            ),
+            )
+            .with_document_model(args.document_model),
             autocomplete_rubber: Rubber::new_with_timeout(
                 &args.connection_string,
                 bounded_timeout(args.max_es_autocomplete_timeout),
-            ),
+            )
+            .with_document_model(args.document_model),
+            // a hung Elasticsearch must fail the readiness probe, not block it
+            health_rubber: Rubber::new_with_timeout(
+                &args.connection_string,
+                max_es_timeout.unwrap_or(DEFAULT_HEALTH_TIMEOUT),
+            )
+            .with_document_model(args.document_model),
             cnx_string: args.connection_string.clone(),
             http_cache_duration: args.http_cache_duration,
             query_settings: QuerySettings::new(&content).map_err(|err| {
@@ -169,6 +317,10 @@ impl TryFrom<&Args> for Context {
                     err
                 )
             })?,
+            federation,
+            query_logger,
+            draining: Arc::new(AtomicBool::new(false)),
+            incompatible_indexes: Arc::new(Mutex::new(None)),
         })
     }
 }
@@ -183,12 +335,43 @@ impl Context {
     pub fn get_rubber_for_autocomplete(&self, timeout: Option<Duration>) -> Rubber {
         clone_or_create(&self.autocomplete_rubber, timeout)
     }
+    pub fn get_rubber_for_health(&self) -> Rubber {
+        self.health_rubber.clone()
+    }
     pub fn get_query_settings(&self) -> &QuerySettings {
         &self.query_settings
     }
+    pub fn get_federation(&self) -> Option<&Federation> {
+        self.federation.as_ref()
+    }
+    pub(crate) fn get_query_logger(&self) -> Option<&QueryLogger> {
+        self.query_logger.as_deref()
+    }
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst)
+    }
+    /// The searched indexes built with another template version, fetched again from
+    /// Elasticsearch at most every `INCOMPATIBLE_INDEXES_TTL`
+    pub fn get_incompatible_indexes(&self) -> Vec<IndexTemplateVersion> {
+        let mut cache = self
+            .incompatible_indexes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some((fetched, indexes)) = &*cache {
+            if fetched.elapsed() < INCOMPATIBLE_INDEXES_TTL {
+                return indexes.clone();
+            }
+        }
+        let indexes = incompatible_indexes(&self.get_rubber_for_features(None));
+        *cache = Some((Instant::now(), indexes.clone()));
+        indexes
+    }
 }
 
-fn clone_or_create(rubber: &Rubber, timeout: Option<Duration>) -> Rubber {
+pub(crate) fn clone_or_create(rubber: &Rubber, timeout: Option<Duration>) -> Rubber {
     if rubber.timeout == timeout {
         // we clone the rs_es_client, reusing the reqwest connection pool
         rubber.clone()
@@ -197,5 +380,6 @@ fn clone_or_create(rubber: &Rubber, timeout: Option<Duration>) -> Rubber {
         // we build a new Rubber (and thus a new connection)
         debug!("creating a new rubber for timeout {:?}", &timeout);
         Rubber::new_with_timeout(&rubber.cnx_string, timeout)
+            .with_document_model(rubber.document_model)
     }
 }
@@ -28,6 +28,7 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
+use crate::query::MatchType;
 use failure::Fail;
 use heck::SnakeCase;
 use rs_es::error::EsError;
@@ -109,6 +110,15 @@ pub struct Feature {
     pub properties: Properties,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub distance: Option<u32>,
+    /// Elasticsearch score, only comparable within a response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Autocomplete pass that found the place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_type: Option<MatchType>,
+    /// Confidence of the autocomplete in [0, 1], see the `confidence` module
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<mimir::Context>,
 }
@@ -248,19 +258,17 @@ impl FromWithLang<mimir::Place> for Feature {
     fn from_with_lang(other: mimir::Place, lang: Option<&str>) -> Feature {
         let geom = other.to_geom();
         let distance = other.distance();
+        let score = other.score();
         let context = other.context();
-        let geocoding = match other {
-            mimir::Place::Admin(admin) => GeocodingResponse::from_with_lang(admin, lang),
-            mimir::Place::Street(street) => GeocodingResponse::from_with_lang(street, lang),
-            mimir::Place::Addr(addr) => GeocodingResponse::from_with_lang(addr, lang),
-            mimir::Place::Poi(poi) => GeocodingResponse::from_with_lang(poi, lang),
-            mimir::Place::Stop(poi) => GeocodingResponse::from_with_lang(poi, lang),
-        };
+        let geocoding = GeocodingResponse::from_with_lang(other, lang);
         Feature {
             feature_type: "Feature".to_string(),
             geometry: geom,
             properties: Properties { geocoding },
             distance,
+            score,
+            match_type: None,
+            confidence: None,
             context,
         }
     }
@@ -270,6 +278,18 @@ pub trait FromWithLang<T> {
     fn from_with_lang(_: T, lang: Option<&str>) -> Self;
 }
 
+impl FromWithLang<mimir::Place> for GeocodingResponse {
+    fn from_with_lang(other: mimir::Place, lang: Option<&str>) -> GeocodingResponse {
+        match other {
+            mimir::Place::Admin(admin) => GeocodingResponse::from_with_lang(admin, lang),
+            mimir::Place::Street(street) => GeocodingResponse::from_with_lang(street, lang),
+            mimir::Place::Addr(addr) => GeocodingResponse::from_with_lang(addr, lang),
+            mimir::Place::Poi(poi) => GeocodingResponse::from_with_lang(poi, lang),
+            mimir::Place::Stop(poi) => GeocodingResponse::from_with_lang(poi, lang),
+        }
+    }
+}
+
 impl FromWithLang<mimir::Admin> for GeocodingResponse {
     fn from_with_lang(other: mimir::Admin, lang: Option<&str>) -> GeocodingResponse {
         let (name, label) = if let Some(code) = lang {
@@ -316,6 +336,7 @@ impl FromWithLang<mimir::Admin> for GeocodingResponse {
             bbox: other.bbox,
             codes: other.codes,
             country_codes: other.country_codes,
+            timezone: other.timezone,
             administrative_regions: associated_admins,
             ..Default::default()
         }
@@ -349,6 +370,8 @@ impl FromWithLang<mimir::Street> for GeocodingResponse {
             Some(other.zip_codes.join(";"))
         };
         let citycode = get_citycode(&admins);
+        // the places imported without timezone can take it from their admins
+        let timezone = other.timezone.or_else(|| mimir::find_timezone(&admins));
 
         let associated_admins = admins
             .iter()
@@ -366,6 +389,7 @@ impl FromWithLang<mimir::Street> for GeocodingResponse {
             city,
             administrative_regions: associated_admins,
             country_codes: other.country_codes,
+            timezone,
             ..Default::default()
         }
     }
@@ -386,6 +410,10 @@ impl FromWithLang<mimir::Addr> for GeocodingResponse {
             Some(other.zip_codes.join(";"))
         };
         let citycode = get_citycode(&admins);
+        let timezone = other
+            .street
+            .timezone
+            .or_else(|| mimir::find_timezone(&admins));
 
         let associated_admins = admins
             .iter()
@@ -404,6 +432,7 @@ impl FromWithLang<mimir::Addr> for GeocodingResponse {
             city,
             administrative_regions: associated_admins,
             country_codes: other.country_codes,
+            timezone,
             ..Default::default()
         }
     }
@@ -430,6 +459,7 @@ impl FromWithLang<mimir::Poi> for GeocodingResponse {
             Some(other.zip_codes.join(";"))
         };
         let citycode = get_citycode(&admins);
+        let timezone = other.timezone.or_else(|| mimir::find_timezone(&admins));
 
         let associated_admins = admins
             .iter()
@@ -457,6 +487,7 @@ impl FromWithLang<mimir::Poi> for GeocodingResponse {
                 _ => None,
             },
             country_codes: other.country_codes,
+            timezone,
             ..Default::default()
         }
     }
@@ -494,7 +525,7 @@ impl FromWithLang<mimir::Stop> for GeocodingResponse {
             physical_modes: other.physical_modes,
             lines: other.lines,
             comments: other.comments,
-            timezone: Some(other.timezone),
+            timezone: Some(other.timezone).filter(|tz| !tz.is_empty()),
             codes: other.codes,
             properties: other.properties,
             feed_publishers: other.feed_publishers,
@@ -0,0 +1,442 @@
+// Encoding of Mapbox Vector Tiles (https://github.com/mapbox/vector-tile-spec/tree/master/2.1)
+//
+// Only the few protobuf messages needed by the tiles are written, by hand,
+// and the properties of the features are strings.
+
+use geo_types::{Coordinate, MultiPolygon, Rect};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+/// Size of a tile in the tile coordinates
+pub const EXTENT: u32 = 4096;
+// the polygons are clipped a bit outside of the tile, for the renderers to not draw the clip
+const BUFFER: f64 = 64.;
+const MAX_ZOOM: u32 = 22;
+
+// protobuf wire types
+const VARINT: u32 = 0;
+const LENGTH_DELIMITED: u32 = 2;
+
+// geometry types of the features
+const POINT: u32 = 1;
+const POLYGON: u32 = 3;
+
+// geometry commands
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_key(buf: &mut Vec<u8>, field: u32, wire_type: u32) {
+    write_varint(buf, u64::from(field << 3 | wire_type));
+}
+
+fn write_uint(buf: &mut Vec<u8>, field: u32, value: u64) {
+    write_key(buf, field, VARINT);
+    write_varint(buf, value);
+}
+
+fn write_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_key(buf, field, LENGTH_DELIMITED);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = vec![];
+    for v in values {
+        write_varint(&mut packed, u64::from(*v));
+    }
+    write_bytes(buf, field, &packed);
+}
+
+fn zigzag(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+fn command(id: u32, count: usize) -> u32 {
+    id | (count as u32) << 3
+}
+
+/// Coordinates of a tile, in the XYZ scheme used by the web maps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileId {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    pub fn new(z: u32, x: u32, y: u32) -> Result<TileId, &'static str> {
+        if z > MAX_ZOOM {
+            return Err("the zoom level must be at most 22");
+        }
+        if x >= 1 << z || y >= 1 << z {
+            return Err("the tile is out of the zoom level bounds");
+        }
+        Ok(TileId { z, x, y })
+    }
+
+    fn nb_tiles(&self) -> f64 {
+        f64::from(1u32 << self.z)
+    }
+
+    /// Bounding box of the tile in WGS84
+    pub fn bbox(&self) -> Rect<f64> {
+        let lon = |x: u32| f64::from(x) / self.nb_tiles() * 360. - 180.;
+        let lat = |y: u32| {
+            (PI * (1. - 2. * f64::from(y) / self.nb_tiles()))
+                .sinh()
+                .atan()
+                .to_degrees()
+        };
+        Rect::new(
+            Coordinate {
+                x: lon(self.x),
+                y: lat(self.y + 1),
+            },
+            Coordinate {
+                x: lon(self.x + 1),
+                y: lat(self.y),
+            },
+        )
+    }
+
+    /// Size in degrees of a unit of the tile coordinates (at the equator)
+    pub fn resolution(&self) -> f64 {
+        360. / self.nb_tiles() / f64::from(EXTENT)
+    }
+
+    /// Projection of a WGS84 coordinate in the tile coordinates (web mercator)
+    fn project(&self, coord: Coordinate<f64>) -> (f64, f64) {
+        let lat = coord.y.max(-85.0511).min(85.0511).to_radians();
+        let x = (coord.x + 180.) / 360. * self.nb_tiles();
+        let y = (1. - (lat.tan() + 1. / lat.cos()).ln() / PI) / 2. * self.nb_tiles();
+        let to_tile = |v: f64, origin: u32| (v - f64::from(origin)) * f64::from(EXTENT);
+        (to_tile(x, self.x), to_tile(y, self.y))
+    }
+}
+
+// Sutherland-Hodgman clipping of a ring by the tile (and its buffer)
+fn clip_ring(ring: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let (min, max) = (-BUFFER, f64::from(EXTENT) + BUFFER);
+    let ring = clip_side(ring, |p| p.0 >= min, |a, b| intersect_x(a, b, min));
+    let ring = clip_side(ring, |p| p.0 <= max, |a, b| intersect_x(a, b, max));
+    let ring = clip_side(ring, |p| p.1 >= min, |a, b| intersect_y(a, b, min));
+    clip_side(ring, |p| p.1 <= max, |a, b| intersect_y(a, b, max))
+}
+
+fn clip_side(
+    ring: Vec<(f64, f64)>,
+    inside: impl Fn((f64, f64)) -> bool,
+    intersection: impl Fn((f64, f64), (f64, f64)) -> (f64, f64),
+) -> Vec<(f64, f64)> {
+    let mut clipped = vec![];
+    let mut previous = match ring.last() {
+        Some(p) => *p,
+        None => return clipped,
+    };
+    for point in ring {
+        match (inside(previous), inside(point)) {
+            (true, true) => clipped.push(point),
+            (true, false) => clipped.push(intersection(previous, point)),
+            (false, true) => {
+                clipped.push(intersection(previous, point));
+                clipped.push(point);
+            }
+            (false, false) => {}
+        }
+        previous = point;
+    }
+    clipped
+}
+
+fn intersect_x(a: (f64, f64), b: (f64, f64), x: f64) -> (f64, f64) {
+    (x, a.1 + (b.1 - a.1) * (x - a.0) / (b.0 - a.0))
+}
+
+fn intersect_y(a: (f64, f64), b: (f64, f64), y: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * (y - a.1) / (b.1 - a.1), y)
+}
+
+fn round((x, y): (f64, f64)) -> (i32, i32) {
+    (x.round() as i32, y.round() as i32)
+}
+
+pub enum Geometry<'a> {
+    Point(Coordinate<f64>),
+    MultiPolygon(&'a MultiPolygon<f64>),
+}
+
+/// A layer of a tile, the features are encoded when they are added
+pub struct Layer {
+    name: String,
+    tile: TileId,
+    keys: Vec<String>,
+    values: Vec<String>,
+    key_indexes: HashMap<String, u32>,
+    value_indexes: HashMap<String, u32>,
+    features: Vec<Vec<u8>>,
+}
+
+impl Layer {
+    pub fn new(name: &str, tile: TileId) -> Layer {
+        Layer {
+            name: name.to_string(),
+            tile,
+            keys: vec![],
+            values: vec![],
+            key_indexes: HashMap::new(),
+            value_indexes: HashMap::new(),
+            features: vec![],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    fn index(indexes: &mut HashMap<String, u32>, table: &mut Vec<String>, s: &str) -> u32 {
+        if let Some(i) = indexes.get(s) {
+            return *i;
+        }
+        let i = table.len() as u32;
+        table.push(s.to_string());
+        indexes.insert(s.to_string(), i);
+        i
+    }
+
+    /// Add a feature, a polygon too small to be visible in the tile is skipped
+    pub fn add_feature(&mut self, properties: &[(&str, &str)], geometry: Geometry<'_>) {
+        let (geometry_type, commands) = match geometry {
+            Geometry::Point(coord) => {
+                let (x, y) = round(self.tile.project(coord));
+                (POINT, vec![command(MOVE_TO, 1), zigzag(x), zigzag(y)])
+            }
+            Geometry::MultiPolygon(multi_polygon) => {
+                (POLYGON, self.encode_multi_polygon(multi_polygon))
+            }
+        };
+        if commands.is_empty() {
+            return;
+        }
+        let mut tags = vec![];
+        for (key, value) in properties {
+            tags.push(Self::index(&mut self.key_indexes, &mut self.keys, key));
+            tags.push(Self::index(
+                &mut self.value_indexes,
+                &mut self.values,
+                value,
+            ));
+        }
+        let mut feature = vec![];
+        write_packed(&mut feature, 2, &tags);
+        write_uint(&mut feature, 3, u64::from(geometry_type));
+        write_packed(&mut feature, 4, &commands);
+        self.features.push(feature);
+    }
+
+    // The exterior rings are clockwise in the tile coordinates (y pointing down),
+    // and the interior rings counter clockwise.
+    fn encode_multi_polygon(&self, multi_polygon: &MultiPolygon<f64>) -> Vec<u32> {
+        let mut commands = vec![];
+        // the coordinates are relative to the previous point, even across the rings
+        let mut cursor = (0, 0);
+        for polygon in multi_polygon.0.iter() {
+            let exterior = self.ring(polygon.exterior().0.iter().cloned());
+            if exterior.len() < 3 {
+                continue;
+            }
+            let interiors = polygon
+                .interiors()
+                .iter()
+                .map(|ring| self.ring(ring.0.iter().cloned()))
+                .filter(|ring| ring.len() >= 3);
+            for (mut ring, is_exterior) in
+                std::iter::once((exterior, true)).chain(interiors.map(|ring| (ring, false)))
+            {
+                if (area(&ring) > 0) != is_exterior {
+                    ring.reverse();
+                }
+                commands.push(command(MOVE_TO, 1));
+                commands.push(zigzag(ring[0].0 - cursor.0));
+                commands.push(zigzag(ring[0].1 - cursor.1));
+                commands.push(command(LINE_TO, ring.len() - 1));
+                for w in ring.windows(2) {
+                    commands.push(zigzag(w[1].0 - w[0].0));
+                    commands.push(zigzag(w[1].1 - w[0].1));
+                }
+                commands.push(command(CLOSE_PATH, 1));
+                cursor = ring[ring.len() - 1];
+            }
+        }
+        commands
+    }
+
+    // Projected and clipped ring, without the closing point nor the repeated points
+    fn ring(&self, coords: impl Iterator<Item = Coordinate<f64>>) -> Vec<(i32, i32)> {
+        let mut ring: Vec<(i32, i32)> = vec![];
+        let projected = coords.map(|c| self.tile.project(c)).collect();
+        for point in clip_ring(projected).into_iter().map(round) {
+            if ring.last() != Some(&point) {
+                ring.push(point);
+            }
+        }
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+        ring
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        write_uint(&mut buf, 15, 2);
+        write_bytes(&mut buf, 1, self.name.as_bytes());
+        for feature in &self.features {
+            write_bytes(&mut buf, 2, feature);
+        }
+        for key in &self.keys {
+            write_bytes(&mut buf, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            let mut encoded_value = vec![];
+            write_bytes(&mut encoded_value, 1, value.as_bytes());
+            write_bytes(&mut buf, 4, &encoded_value);
+        }
+        write_uint(&mut buf, 5, u64::from(EXTENT));
+        buf
+    }
+}
+
+// twice the signed area (surveyor's formula)
+fn area(ring: &[(i32, i32)]) -> i64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| i64::from(a.0) * i64::from(b.1) - i64::from(b.0) * i64::from(a.1))
+        .sum()
+}
+
+/// Encode a tile, the empty layers are skipped
+pub fn encode_tile(layers: &[Layer]) -> Vec<u8> {
+    let mut buf = vec![];
+    for layer in layers.iter().filter(|l| !l.is_empty()) {
+        write_bytes(&mut buf, 3, &layer.encode());
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::{LineString, Polygon};
+
+    #[test]
+    fn test_varint() {
+        let encode = |v| {
+            let mut buf = vec![];
+            write_varint(&mut buf, v);
+            buf
+        };
+        assert_eq!(encode(1), vec![0x01]);
+        assert_eq!(encode(300), vec![0xac, 0x02]);
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-2), 3);
+    }
+
+    #[test]
+    fn test_tile_bbox() {
+        assert!(TileId::new(1, 2, 0).is_err());
+        assert!(TileId::new(23, 0, 0).is_err());
+
+        let bbox = TileId::new(1, 1, 0).unwrap().bbox();
+        assert_eq!(bbox.min().x, 0.);
+        assert_eq!(bbox.max().x, 180.);
+        assert!(bbox.min().y.abs() < 1e-9);
+        assert!((bbox.max().y - 85.0511).abs() < 1e-4);
+
+        let tile = TileId::new(1, 1, 0).unwrap();
+        assert_eq!(round(tile.project(Coordinate { x: 0., y: 0. })), (0, 4096));
+        assert_eq!(
+            round(tile.project(Coordinate { x: 90., y: 0. })),
+            (2048, 4096)
+        );
+    }
+
+    #[test]
+    fn test_point_feature() {
+        // example of the specification: a point at (25, 17)
+        let tile = TileId::new(0, 0, 0).unwrap();
+        let mut layer = Layer::new("stop", tile);
+        let coord = Coordinate {
+            x: 25. * tile.resolution() - 180.,
+            y: 0.,
+        };
+        layer.add_feature(&[("id", "stop:1")], Geometry::Point(coord));
+        let mut expected = vec![];
+        write_packed(&mut expected, 2, &[0, 0]);
+        write_uint(&mut expected, 3, 1);
+        write_packed(&mut expected, 4, &[9, 50, 4096]);
+        assert_eq!(layer.features, vec![expected]);
+        assert_eq!(layer.keys, vec!["id"]);
+        assert_eq!(layer.values, vec!["stop:1"]);
+    }
+
+    #[test]
+    fn test_polygon_orientation() {
+        let tile = TileId::new(0, 0, 0).unwrap();
+        let layer = Layer::new("admin", tile);
+        // counter clockwise in WGS84, as in GeoJSON
+        let polygon = Polygon::new(
+            LineString::from(vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.), (0., 0.)]),
+            vec![],
+        );
+        let commands = layer.encode_multi_polygon(&MultiPolygon(vec![polygon]));
+        assert_eq!(commands[0], command(MOVE_TO, 1));
+        assert_eq!(commands[3], command(LINE_TO, 3));
+        assert_eq!(*commands.last().unwrap(), command(CLOSE_PATH, 1));
+
+        // the ring is clockwise in the tile coordinates
+        let mut ring = vec![];
+        let mut cursor = (0, 0);
+        let decode = |v: u32| ((v >> 1) as i32) ^ -((v & 1) as i32);
+        for (i, pair) in commands[1..3]
+            .chunks(2)
+            .chain(commands[4..commands.len() - 1].chunks(2))
+            .enumerate()
+        {
+            cursor = (cursor.0 + decode(pair[0]), cursor.1 + decode(pair[1]));
+            ring.push(cursor);
+            assert!(i < 4);
+        }
+        assert!(area(&ring) > 0);
+
+        // a polygon smaller than a unit of the tile is skipped
+        let tiny = Polygon::new(
+            LineString::from(vec![(0., 0.), (1e-6, 0.), (0., 1e-6), (0., 0.)]),
+            vec![],
+        );
+        assert!(layer
+            .encode_multi_polygon(&MultiPolygon(vec![tiny]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_clip_ring() {
+        let ring = vec![(-1000., 100.), (100., 100.), (100., 200.), (-1000., 200.)];
+        assert_eq!(
+            clip_ring(ring),
+            vec![(-64., 100.), (100., 100.), (100., 200.), (-64., 200.)]
+        );
+        let outside = vec![(-1000., 100.), (-900., 100.), (-900., 200.)];
+        assert!(clip_ring(outside).is_empty());
+    }
+}
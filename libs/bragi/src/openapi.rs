@@ -0,0 +1,717 @@
+//! OpenAPI 3 description of the Bragi API, served at `/openapi.json`.
+//!
+//! The parameters are declared next to each route's `Params` structure, and the tests
+//! check that both agree (names, types and examples).
+//! The response schemas are inferred from samples built with exhaustive struct literals,
+//! so adding a field to a response structure does not compile until the sample is updated.
+
+use crate::model::{
+    ApiError, AssociatedAdmin, Autocomplete, BragiError, Feature, GeocodingResponse, Properties,
+};
+use crate::query::MatchType;
+use crate::routes::{
+    Admins, AdminsListParams, AdminsParams, AutocompleteParams, ExportParams, FeaturesParams,
+    Health, IndexTemplateVersion, JsonParams, ReverseParams, Status, TilesParams, Timezone,
+    TimezoneParams,
+};
+use actix_web::ResponseError;
+use mimir::rubber::TEMPLATE_VERSION;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+lazy_static::lazy_static! {
+    pub static ref DOCUMENT: Value = document();
+}
+
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub location: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+    pub schema: Value,
+    /// Value given in the documentation, also used by the tests to check that the parameter is accepted
+    pub example: Value,
+}
+
+impl Parameter {
+    pub fn query(
+        name: &'static str,
+        description: &'static str,
+        schema: Value,
+        example: Value,
+    ) -> Self {
+        Parameter {
+            name,
+            location: "query",
+            description,
+            required: false,
+            schema,
+            example,
+        }
+    }
+
+    pub fn path(name: &'static str, description: &'static str, example: Value) -> Self {
+        Parameter {
+            name,
+            location: "path",
+            description,
+            required: true,
+            schema: schema("string"),
+            example,
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    fn is_array(&self) -> bool {
+        self.schema["type"] == "array"
+    }
+
+    /// Name of the parameter in the query string, the arrays are given as `name[]=a&name[]=b`
+    pub fn query_name(&self) -> String {
+        if self.is_array() {
+            format!("{}[]", self.name)
+        } else {
+            self.name.to_owned()
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut param = json!({
+            "name": self.query_name(),
+            "in": self.location,
+            "description": self.description,
+            "required": self.required,
+            "schema": self.schema,
+            "example": self.example,
+        });
+        if self.is_array() {
+            param["style"] = json!("form");
+            param["explode"] = json!(true);
+        }
+        param
+    }
+}
+
+pub fn schema(schema_type: &str) -> Value {
+    json!({ "type": schema_type })
+}
+
+pub fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+pub fn enumeration<'a>(values: impl IntoIterator<Item = &'a str>) -> Value {
+    json!({ "type": "string", "enum": values.into_iter().collect::<Vec<_>>() })
+}
+
+/// Infer the schema of a serialized sample
+fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "nullable": true }),
+        Value::Bool(_) => schema("boolean"),
+        Value::Number(n) if n.is_f64() => schema("number"),
+        Value::Number(_) => schema("integer"),
+        Value::String(_) => schema("string"),
+        Value::Array(items) => {
+            array_of(items.first().map(infer_schema).unwrap_or_else(|| json!({})))
+        }
+        Value::Object(fields) => json!({
+            "type": "object",
+            "properties": fields
+                .iter()
+                .map(|(k, v)| (k.clone(), infer_schema(v)))
+                .collect::<Map<_, _>>(),
+        }),
+    }
+}
+
+fn sample_admin() -> AssociatedAdmin {
+    AssociatedAdmin {
+        id: "admin:fr:75056".to_owned(),
+        insee: "75056".to_owned(),
+        level: 8,
+        label: "Paris (75000-75116)".to_owned(),
+        name: "Paris".to_owned(),
+        zip_codes: vec!["75000".to_owned()],
+        coord: mimir::Coord::new(2.3522, 48.8566),
+        bbox: Some(geo_types::Rect::new(
+            geo_types::Coordinate {
+                x: 2.224,
+                y: 48.815,
+            },
+            geo_types::Coordinate {
+                x: 2.469,
+                y: 48.902,
+            },
+        )),
+        zone_type: Some(cosmogony::ZoneType::City),
+        parent_id: Some("admin:fr:11".to_owned()),
+        codes: vec![mimir::Code {
+            name: "ref:INSEE".to_owned(),
+            value: "75056".to_owned(),
+        }],
+    }
+}
+
+fn sample_geocoding() -> GeocodingResponse {
+    GeocodingResponse {
+        id: "stop_area:SA:1".to_owned(),
+        place_type: "public_transport:stop_area".to_owned(),
+        zone_type: Some("city".to_owned()),
+        label: Some("Châtelet (Paris)".to_owned()),
+        name: Some("Châtelet".to_owned()),
+        housenumber: Some("1".to_owned()),
+        street: Some("Place du Châtelet".to_owned()),
+        postcode: Some("75001".to_owned()),
+        city: Some("Paris".to_owned()),
+        citycode: Some("75056".to_owned()),
+        level: Some(8),
+        administrative_regions: vec![sample_admin()],
+        poi_types: vec![mimir::PoiType {
+            id: "poi_type:amenity:bicycle_rental".to_owned(),
+            name: "Station VLS".to_owned(),
+        }],
+        properties: vec![mimir::Property {
+            key: "wheelchair".to_owned(),
+            value: "yes".to_owned(),
+        }],
+        address: Some(Box::new(GeocodingResponse {
+            id: "addr:2.347;48.858:1".to_owned(),
+            place_type: "house".to_owned(),
+            ..Default::default()
+        })),
+        commercial_modes: vec![mimir::CommercialMode {
+            id: "commercial_mode:Metro".to_owned(),
+            name: "Metro".to_owned(),
+        }],
+        comments: vec![mimir::Comment {
+            name: "a comment".to_owned(),
+        }],
+        physical_modes: vec![mimir::PhysicalMode {
+            id: "physical_mode:Metro".to_owned(),
+            name: "Metro".to_owned(),
+        }],
+        lines: vec![mimir::Line {
+            id: "line:M1".to_owned(),
+            name: "Métro 1".to_owned(),
+            code: Some("1".to_owned()),
+            commercial_mode: Some(mimir::CommercialMode {
+                id: "commercial_mode:Metro".to_owned(),
+                name: "Metro".to_owned(),
+            }),
+            network: Some(mimir::Network {
+                id: "network:RATP".to_owned(),
+                name: "RATP".to_owned(),
+            }),
+            physical_modes: vec![mimir::PhysicalMode {
+                id: "physical_mode:Metro".to_owned(),
+                name: "Metro".to_owned(),
+            }],
+            ..Default::default()
+        }],
+        timezone: Some("Europe/Paris".to_owned()),
+        codes: vec![mimir::Code {
+            name: "source".to_owned(),
+            value: "1".to_owned(),
+        }],
+        feed_publishers: vec![mimir::FeedPublisher {
+            id: "ratp".to_owned(),
+            license: "ODbL".to_owned(),
+            name: "RATP".to_owned(),
+            url: "https://data.ratp.fr".to_owned(),
+        }],
+        bbox: Some(geo_types::Rect::new(
+            geo_types::Coordinate {
+                x: 2.346,
+                y: 48.857,
+            },
+            geo_types::Coordinate {
+                x: 2.348,
+                y: 48.859,
+            },
+        )),
+        country_codes: vec!["FR".to_owned()],
+    }
+}
+
+fn sample_feature() -> Feature {
+    Feature {
+        feature_type: "Feature".to_owned(),
+        geometry: geojson::Geometry::new(geojson::Value::Point(vec![2.347, 48.858])),
+        properties: Properties {
+            geocoding: sample_geocoding(),
+        },
+        distance: Some(42),
+        score: Some(12.5),
+        match_type: Some(MatchType::Prefix),
+        confidence: Some(0.93),
+        context: Some(mimir::Context {
+            explanation: Some(mimir::Explanation {
+                value: 1.5,
+                description: "sum of:".to_owned(),
+                details: vec![],
+            }),
+        }),
+    }
+}
+
+fn sample_errors() -> Vec<BragiError> {
+    vec![
+        BragiError::ObjectNotFound,
+        BragiError::InvalidParam("you should provide a 'lon' AND a 'lat' parameter"),
+        BragiError::InvalidShape("only 'feature' is supported"),
+    ]
+}
+
+fn error_responses() -> Map<String, Value> {
+    let mut descriptions = BTreeMap::<String, Vec<String>>::new();
+    for err in sample_errors() {
+        descriptions
+            .entry(err.render_response().status().as_u16().to_string())
+            .or_default()
+            .push(err.to_string());
+    }
+    // the Elasticsearch errors (cf BragiError::Es)
+    descriptions
+        .entry("503".to_owned())
+        .or_default()
+        .push("Elasticsearch is not reachable".to_owned());
+    descriptions
+        .entry("500".to_owned())
+        .or_default()
+        .push("Elasticsearch query error".to_owned());
+    descriptions
+        .into_iter()
+        .map(|(status, descriptions)| {
+            (
+                status,
+                json!({
+                    "description": descriptions.join(", "),
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ApiError" }
+                        }
+                    }
+                }),
+            )
+        })
+        .collect()
+}
+
+fn json_response(description: &str, schema_name: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": { "$ref": format!("#/components/schemas/{}", schema_name) }
+            }
+        }
+    })
+}
+
+fn operation(summary: &str, parameters: &[Parameter], response_schema: &str) -> Value {
+    let mut responses = error_responses();
+    responses.insert("200".to_owned(), json_response("OK", response_schema));
+    json!({
+        "summary": summary,
+        "parameters": parameters.iter().map(Parameter::to_json).collect::<Vec<_>>(),
+        "responses": responses,
+    })
+}
+
+fn monitoring_operation(summary: &str, response_schema: &str, unavailable: bool) -> Value {
+    let mut responses = Map::new();
+    responses.insert("200".to_owned(), json_response("OK", response_schema));
+    if unavailable {
+        responses.insert(
+            "503".to_owned(),
+            json_response("Not available", response_schema),
+        );
+    }
+    json!({
+        "summary": summary,
+        "parameters": [],
+        "responses": responses,
+    })
+}
+
+fn sample_schema<T: serde::Serialize>(sample: T) -> Value {
+    infer_schema(&serde_json::to_value(sample).expect("failed to serialize sample"))
+}
+
+pub fn document() -> Value {
+    let autocomplete_params = AutocompleteParams::openapi_parameters();
+    let mut post_autocomplete = operation(
+        "Search places by their name, restricted to a shape",
+        &autocomplete_params,
+        "Autocomplete",
+    );
+    post_autocomplete["requestBody"] = json!({
+        "required": true,
+        "content": {
+            "application/json": {
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "shape": {
+                            "description": "GeoJSON Feature, with a Polygon or MultiPolygon geometry",
+                            "type": "object",
+                        }
+                    },
+                    "required": ["shape"],
+                },
+                "example": JsonParams::openapi_example(),
+            }
+        }
+    });
+
+    let mut paths = Map::new();
+    paths.insert(
+        "/autocomplete".to_owned(),
+        json!({
+            "get": operation("Search places by their name", &autocomplete_params, "Autocomplete"),
+            "post": post_autocomplete,
+        }),
+    );
+    paths.insert(
+        "/features/{id}".to_owned(),
+        json!({
+            "get": operation(
+                "Get a place by its id",
+                &FeaturesParams::openapi_parameters(),
+                "Autocomplete",
+            )
+        }),
+    );
+    paths.insert(
+        "/reverse".to_owned(),
+        json!({
+            "get": operation(
+                "Find the nearest address of a coordinate",
+                &ReverseParams::openapi_parameters(),
+                "Autocomplete",
+            )
+        }),
+    );
+    paths.insert(
+        "/timezone".to_owned(),
+        json!({
+            "get": operation(
+                "Timezone of a coordinate, from the admins containing it",
+                &TimezoneParams::openapi_parameters(),
+                "Timezone",
+            )
+        }),
+    );
+    paths.insert(
+        "/admins".to_owned(),
+        json!({
+            "get": operation(
+                "List the admins of a zone type",
+                &AdminsListParams::openapi_parameters(),
+                "Admins",
+            )
+        }),
+    );
+    paths.insert(
+        "/admins/{id}".to_owned(),
+        json!({
+            "get": operation(
+                "Get an admin followed by its ancestors, up to the root of the hierarchy",
+                &AdminsParams::openapi_parameters(),
+                "Admins",
+            )
+        }),
+    );
+    paths.insert(
+        "/admins/{id}/children".to_owned(),
+        json!({
+            "get": operation(
+                "List the children of an admin",
+                &AdminsListParams::openapi_children_parameters(),
+                "Admins",
+            )
+        }),
+    );
+    let mut export = operation(
+        "All the places of a dataset, as newline delimited JSON (needs an API key allowed to export)",
+        &ExportParams::openapi_parameters(),
+        "Autocomplete",
+    );
+    export["responses"]["200"] = json!({
+        "description": "OK",
+        "content": {
+            "application/x-ndjson": {
+                "schema": { "$ref": "#/components/schemas/Geocoding" }
+            }
+        }
+    });
+    paths.insert(
+        "/export/{type}/{dataset}".to_owned(),
+        json!({ "get": export }),
+    );
+    let mut tiles = operation(
+        "Mapbox Vector Tile of the admin boundaries, the stops or the POIs",
+        &TilesParams::openapi_parameters(),
+        "Autocomplete",
+    );
+    tiles["responses"]["200"] = json!({
+        "description": "OK",
+        "content": {
+            "application/vnd.mapbox-vector-tile": {
+                "schema": { "type": "string", "format": "binary" }
+            }
+        }
+    });
+    paths.insert(
+        "/tiles/{layer}/{z}/{x}/{y}.mvt".to_owned(),
+        json!({ "get": tiles }),
+    );
+    paths.insert(
+        "/status".to_owned(),
+        json!({
+            "get": monitoring_operation(
+                "Bragi's version, Elasticsearch connection and indexes built with another template version",
+                "Status",
+                false,
+            )
+        }),
+    );
+    paths.insert(
+        "/health/live".to_owned(),
+        json!({ "get": monitoring_operation("Liveness of the server", "Health", false) }),
+    );
+    paths.insert(
+        "/health/ready".to_owned(),
+        json!({
+            "get": monitoring_operation(
+                "Readiness of the server: Elasticsearch is reachable with the required aliases, and the server is not shutting down",
+                "Health",
+                true,
+            )
+        }),
+    );
+
+    let mut schemas = Map::new();
+    schemas.insert(
+        "Autocomplete".to_owned(),
+        sample_schema(Autocomplete::new(
+            "châtelet".to_owned(),
+            vec![sample_feature()],
+        )),
+    );
+    schemas.insert(
+        "Timezone".to_owned(),
+        sample_schema(Timezone {
+            timezone: "Europe/Paris".to_owned(),
+            admin_id: "admin:fr:75056".to_owned(),
+        }),
+    );
+    schemas.insert("Geocoding".to_owned(), sample_schema(sample_geocoding()));
+    schemas.insert(
+        "Admins".to_owned(),
+        sample_schema(Admins {
+            admins: vec![sample_admin()],
+        }),
+    );
+    schemas.insert(
+        "ApiError".to_owned(),
+        sample_schema(ApiError {
+            short: "validation error".to_owned(),
+            long: "invalid argument: missing field `q`".to_owned(),
+        }),
+    );
+    schemas.insert(
+        "Status".to_owned(),
+        sample_schema(Status {
+            version: VERSION.to_owned(),
+            es: "http://localhost:9200/munin".to_owned(),
+            status: "good".to_owned(),
+            template_version: TEMPLATE_VERSION,
+            incompatible_indexes: vec![IndexTemplateVersion {
+                index: "munin_poi_fr_20201203_101010_000000".to_owned(),
+                template_version: None,
+            }],
+        }),
+    );
+    schemas.insert(
+        "Health".to_owned(),
+        sample_schema(Health {
+            status: "not ready".to_owned(),
+            reason: Some("missing aliases: munin_geo_data".to_owned()),
+        }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Bragi",
+            "description": "Geocoding API built around Elasticsearch",
+            "version": VERSION,
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::collections::BTreeSet;
+
+    fn to_query_value(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        }
+    }
+
+    // Build a query string with the example of each documented query parameter,
+    // parse it like the route would, and check that the parsed structure has exactly
+    // the documented fields.
+    fn check_parameters<T: DeserializeOwned + Serialize>(parameters: &[Parameter]) {
+        let parameters: Vec<_> = parameters
+            .iter()
+            .filter(|p| p.location == "query")
+            .collect();
+        let query = parameters
+            .iter()
+            .flat_map(|p| match p.example {
+                Value::Array(ref values) => values
+                    .iter()
+                    .map(|v| format!("{}={}", p.query_name(), to_query_value(v)))
+                    .collect(),
+                ref v => vec![format!("{}={}", p.query_name(), to_query_value(v))],
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let params: T = serde_qs::Config::new(5, false)
+            .deserialize_str(&query)
+            .unwrap_or_else(|e| panic!("documented parameters '{}' are rejected: {}", query, e));
+
+        let fields: BTreeSet<String> = match serde_json::to_value(&params).unwrap() {
+            Value::Object(fields) => {
+                for (name, value) in fields.iter() {
+                    assert!(
+                        !value.is_null(),
+                        "the example of '{}' has not been parsed",
+                        name
+                    );
+                }
+                fields.keys().cloned().collect()
+            }
+            v => panic!("{} is not an object", v),
+        };
+        let documented: BTreeSet<String> = parameters.iter().map(|p| p.name.to_owned()).collect();
+        assert_eq!(
+            fields, documented,
+            "the documentation and the parameters differ"
+        );
+
+        // a parameter that is not optional in the structure must be documented as required
+        for p in parameters.iter().filter(|p| !p.required) {
+            let query = query
+                .split('&')
+                .filter(|kv| !kv.starts_with(&format!("{}=", p.query_name())))
+                .collect::<Vec<_>>()
+                .join("&");
+            assert!(
+                serde_qs::Config::new(5, false)
+                    .deserialize_str::<T>(&query)
+                    .is_ok(),
+                "'{}' is documented as optional but is required",
+                p.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_autocomplete_parameters() {
+        check_parameters::<AutocompleteParams>(&AutocompleteParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_features_parameters() {
+        check_parameters::<FeaturesParams>(&FeaturesParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_reverse_parameters() {
+        check_parameters::<ReverseParams>(&ReverseParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_tiles_parameters() {
+        check_parameters::<TilesParams>(&TilesParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_timezone_parameters() {
+        check_parameters::<TimezoneParams>(&TimezoneParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_admins_parameters() {
+        check_parameters::<AdminsParams>(&AdminsParams::openapi_parameters());
+        check_parameters::<AdminsListParams>(&AdminsListParams::openapi_parameters());
+        check_parameters::<AdminsListParams>(&AdminsListParams::openapi_children_parameters());
+    }
+
+    #[test]
+    fn test_export_parameters() {
+        check_parameters::<ExportParams>(&ExportParams::openapi_parameters());
+    }
+
+    #[test]
+    fn test_autocomplete_body() {
+        let body: Result<JsonParams, _> = serde_json::from_value(JsonParams::openapi_example());
+        assert!(body.is_ok(), "the documented body is rejected: {:?}", body);
+    }
+
+    #[test]
+    fn test_served_document() {
+        let doc = &*DOCUMENT;
+        assert_eq!(doc["openapi"], "3.0.3");
+
+        // all the referenced schemas are defined
+        let refs = doc.to_string();
+        for schema in refs
+            .split("#/components/schemas/")
+            .skip(1)
+            .map(|s| s.split('"').next().unwrap())
+        {
+            assert!(
+                doc["components"]["schemas"][schema].is_object(),
+                "schema {} is not defined",
+                schema
+            );
+        }
+
+        let geocoding = &doc["components"]["schemas"]["Autocomplete"]["properties"]["features"]
+            ["items"]["properties"]["properties"]["properties"]["geocoding"]["properties"];
+        for field in &["id", "type", "label", "administrative_regions", "timezone"] {
+            assert!(geocoding[field].is_object(), "{} is not documented", field);
+        }
+        let feature =
+            &doc["components"]["schemas"]["Autocomplete"]["properties"]["features"]["items"];
+        for field in &["score", "match_type", "confidence"] {
+            assert!(
+                feature["properties"][field].is_object(),
+                "{} is not documented",
+                field
+            );
+        }
+        for status in &["200", "400", "404"] {
+            assert!(doc["paths"]["/autocomplete"]["get"]["responses"][status].is_object());
+        }
+    }
+}
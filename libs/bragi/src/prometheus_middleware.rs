@@ -25,10 +25,19 @@ lazy_static::lazy_static! {
         map.insert("/status", "status");
         map.insert("/reverse", "reverse");
         map.insert("/autocomplete", "autocomplete");
+        map.insert("/timezone", "timezone");
+        map.insert("/admins", "admins");
+        map.insert("/openapi.json", "openapi");
+        map.insert("/health/live", "live");
+        map.insert("/health/ready", "ready");
         map
     };
 
     static ref FEATURES_ROUTE: &'static str = "features";
+    static ref TILES_ROUTE: &'static str = "tiles";
+    static ref EXPORT_ROUTE: &'static str = "export";
+    static ref ADMIN_ROUTE: &'static str = "admin";
+    static ref ADMIN_CHILDREN_ROUTE: &'static str = "admin_children";
 }
 
 fn get_ressource_name(path: &str) -> String {
@@ -40,6 +49,14 @@ fn get_ressource_name(path: &str) -> String {
         .unwrap_or_else(|| {
             if path.starts_with("/features") {
                 &FEATURES_ROUTE
+            } else if path.starts_with("/tiles") {
+                &TILES_ROUTE
+            } else if path.starts_with("/export/") {
+                &EXPORT_ROUTE
+            } else if path.starts_with("/admins/") && path.ends_with("/children") {
+                &ADMIN_CHILDREN_ROUTE
+            } else if path.starts_with("/admins/") {
+                &ADMIN_ROUTE
             } else {
                 ""
             }
@@ -31,10 +31,12 @@ use super::model::{self, BragiError};
 use crate::query_settings::{BuildWeight, Proximity, QuerySettings, Types};
 use geojson::Geometry;
 use mimir::objects::{Addr, Admin, Coord, MimirObject, PlaceDocType, Poi, Stop, Street};
-use mimir::rubber::{get_indexes, read_places, Rubber};
+use mimir::rubber::{
+    document_type, get_indexes, get_main_type_index, read_places, Rubber, SearchRequest,
+};
 use prometheus::{self, exponential_buckets, histogram_opts, register_histogram_vec, HistogramVec};
 use rs_es::error::EsError;
-use rs_es::operations::search::Source;
+use rs_es::operations::search::{Order, Sort};
 use rs_es::query::compound::BoostMode;
 use rs_es::query::functions::{DecayOptions, FilteredFunction, Function, Modifier};
 use rs_es::query::Query;
@@ -53,8 +55,9 @@ lazy_static::lazy_static! {
 }
 
 /// takes a ES json blob and build a Place from it
-/// it uses the _type field of ES to know which type of the Place enum to fill
+/// it uses the `type` field (or the _type field of ES) to know which type of the Place enum to fill
 pub fn make_place(doc_type: String, value: Option<Box<serde_json::Value>>) -> Option<mimir::Place> {
+    let doc_type = document_type(doc_type, value.as_deref());
     value.and_then(|v| {
         fn convert<T>(v: serde_json::Value, f: fn(T) -> mimir::Place) -> Option<mimir::Place>
         where
@@ -79,8 +82,9 @@ pub fn make_place(doc_type: String, value: Option<Box<serde_json::Value>>) -> Op
     })
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum MatchType {
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchType {
     Prefix,
     Fuzzy,
 }
@@ -95,6 +99,16 @@ impl fmt::Display for MatchType {
     }
 }
 
+/// The places found by an autocomplete, with some details on how they have been found
+#[derive(Debug)]
+pub struct AutocompleteResult {
+    pub places: Vec<mimir::Place>,
+    /// the pass that produced the places
+    pub match_type: MatchType,
+    /// time spent by Elasticsearch in ms, for all the passes
+    pub took: u64,
+}
+
 // filter to handle PT coverages
 // we either want:
 // * to get objects with no coverage at all (non-PT objects)
@@ -147,10 +161,11 @@ fn build_proximity_with_boost(coord: &Coord, infos: &Proximity, is_fuzzy: bool)
         .build()
 }
 
-fn build_with_weight(build_weight: &BuildWeight, types: &Types) -> Query {
+/// `type_field` is the field holding the type of the documents, see `mimir::rubber::DocumentModel`
+fn build_with_weight(build_weight: &BuildWeight, types: &Types, type_field: &str) -> Query {
     let weighted = |doc_type, weight| {
         FilteredFunction::build_filtered_function(
-            Query::build_term("_type", doc_type).build(),
+            Query::build_term(type_field, doc_type).build(),
             Function::build_field_value_factor("weight")
                 .with_factor(build_weight.factor)
                 .with_missing(build_weight.missing)
@@ -184,20 +199,22 @@ fn build_query<'a>(
     zone_types: &[&str],
     poi_types: &[&str],
     query_settings: &QuerySettings,
+    type_field: &str,
 ) -> Query {
     // Priorization by type
-    fn match_type_with_boost<T: MimirObject>(boost: f64) -> Query {
-        Query::build_term("_type", T::doc_type())
+    fn match_type_with_boost<T: MimirObject>(type_field: &str, boost: f64) -> Query {
+        Query::build_term(type_field, T::doc_type())
             .with_boost(boost)
             .build()
     }
+    let boosts = &query_settings.type_query.boosts;
     let type_query = Query::build_bool()
         .with_should(vec![
-            match_type_with_boost::<Addr>(query_settings.type_query.boosts.address),
-            match_type_with_boost::<Admin>(query_settings.type_query.boosts.admin),
-            match_type_with_boost::<Stop>(query_settings.type_query.boosts.stop),
-            match_type_with_boost::<Poi>(query_settings.type_query.boosts.poi),
-            match_type_with_boost::<Street>(query_settings.type_query.boosts.street),
+            match_type_with_boost::<Addr>(type_field, boosts.address),
+            match_type_with_boost::<Admin>(type_field, boosts.admin),
+            match_type_with_boost::<Stop>(type_field, boosts.stop),
+            match_type_with_boost::<Poi>(type_field, boosts.poi),
+            match_type_with_boost::<Street>(type_field, boosts.street),
         ])
         .with_boost(query_settings.type_query.global)
         .build();
@@ -284,7 +301,7 @@ fn build_query<'a>(
     };
 
     // Priorization by importance
-    let mut importance_queries = vec![build_with_weight(&weights, &settings.types)];
+    let mut importance_queries = vec![build_with_weight(&weights, &settings.types, type_field)];
 
     if let Some(ref coord) = coord {
         importance_queries.push(build_proximity_with_boost(
@@ -297,7 +314,7 @@ fn build_query<'a>(
     match match_type {
         MatchType::Prefix => {
             let admin_importance_query = Query::build_function_score()
-                .with_query(Query::build_term("_type", Admin::doc_type()).build())
+                .with_query(Query::build_term(type_field, Admin::doc_type()).build())
                 .with_functions(vec![
                     FilteredFunction::build_filtered_function(
                         None,
@@ -405,7 +422,7 @@ fn build_query<'a>(
                 shape_scope
                     .iter()
                     .map(PlaceDocType::as_str)
-                    .map(|x| Query::build_term("_type", x).build())
+                    .map(|x| Query::build_term(type_field, x).build())
                     .collect::<Vec<_>>(),
             )
             .build();
@@ -418,7 +435,7 @@ fn build_query<'a>(
         let filter_wo_shape = shape_scope
             .iter()
             .map(PlaceDocType::as_str)
-            .map(|x| Query::build_term("_type", x).build())
+            .map(|x| Query::build_term(type_field, x).build())
             .collect::<Vec<_>>();
         let filter_wo_shape = Query::build_bool().with_must_not(filter_wo_shape).build();
 
@@ -481,7 +498,7 @@ fn query(
     debug: bool,
     query_settings: &QuerySettings,
     request_id: Option<&str>,
-) -> Result<Vec<mimir::Place>, EsError> {
+) -> Result<(Vec<mimir::Place>, u64), EsError> {
     if let Some(id) = request_id {
         info!("query::autocomplete - enter - {} - ({})", id, q);
     }
@@ -499,9 +516,16 @@ fn query(
         zone_types,
         poi_types,
         query_settings,
+        rubber.document_model.type_field(),
     );
 
-    let indexes = get_indexes(all_data, &pt_datasets, &poi_datasets, types);
+    let indexes = get_indexes(
+        &rubber.namespace,
+        all_data,
+        &pt_datasets,
+        &poi_datasets,
+        types,
+    );
     let indexes = indexes
         .iter()
         .map(|index| index.as_str())
@@ -511,7 +535,7 @@ fn query(
     if indexes.is_empty() {
         // if there is no indexes, rs_es search with index "_all"
         // but we want to return empty response in this case.
-        return Ok(vec![]);
+        return Ok((vec![], 0));
     }
     let timer = ES_REQ_HISTOGRAM
         .get_metric_with_label_values(&[query_type.as_str()])
@@ -521,35 +545,19 @@ fn query(
         )
         .ok();
 
-    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
-    let mut search_query = rubber.es_client.search_query();
-
-    let search_query = search_query
-        .with_ignore_unavailable(true)
-        .with_indexes(&indexes)
-        .with_query(&query)
+    let search_request = SearchRequest::new(&indexes, &query)
         .with_from(offset)
         .with_size(limit)
         // No need to fetch "boundary" as it's not used in the geocoding response
         // and is very large in some documents (countries...)
-        .with_source(Source::exclude(&["boundary"]));
-
-    // We don't want to clutter the Query URL, so we only add an explanation if the option is used
-    let search_query = if debug {
-        search_query.with_explain(true)
-    } else {
-        search_query
-    };
-
-    if let Some(timeout) = &timeout {
-        search_query.with_timeout(timeout.as_str());
-    }
+        .with_boundary(false)
+        .with_explain(debug);
 
     if let Some(id) = request_id {
         info!("query::autocomplete - es - enter - {} - ({})", id, q);
     }
 
-    let result = search_query.send()?;
+    let result = rubber.search(&search_request)?;
 
     if let Some(id) = request_id {
         info!("query::autocomplete - es - exit - {} - ({})", id, q);
@@ -559,13 +567,14 @@ fn query(
         t.observe_duration();
     }
 
+    let took = result.took;
     let res = read_places(result, coord.as_ref());
 
     if let Some(id) = request_id {
         info!("query::autocomplete - exit - {} - ({})", id, q);
     }
 
-    res
+    res.map(|places| (places, took))
 }
 
 pub fn features(
@@ -573,6 +582,7 @@ pub fn features(
     poi_datasets: &[&str],
     all_data: bool,
     id: &str,
+    with_boundary: bool,
     mut rubber: Rubber,
 ) -> Result<Vec<mimir::Place>, BragiError> {
     let val = rs_es::units::JsonVal::String(id.into());
@@ -585,7 +595,13 @@ pub fn features(
     let filter = Query::build_bool().with_must(filters).build();
     let query = Query::build_bool().with_filter(filter).build();
 
-    let indexes = get_indexes(all_data, &pt_datasets, &poi_datasets, &[]);
+    let indexes = get_indexes(
+        &rubber.namespace,
+        all_data,
+        &pt_datasets,
+        &poi_datasets,
+        &[],
+    );
     let indexes = indexes
         .iter()
         .map(|index| index.as_str())
@@ -607,29 +623,157 @@ pub fn features(
         )
         .ok();
 
-    let timeout = rubber.timeout.map(|t| format!("{:?}", t));
-    let mut search_query = rubber.es_client.search_query();
+    // the boundary is very large in some documents (countries...),
+    // so it's only fetched when it is returned
+    let result =
+        rubber.search(&SearchRequest::new(&indexes, &query).with_boundary(with_boundary))?;
 
-    let search_query = search_query
-        .with_ignore_unavailable(true)
-        .with_indexes(&indexes)
-        .with_query(&query);
+    if let Some(t) = timer {
+        t.observe_duration()
+    }
 
-    if let Some(timeout) = &timeout {
-        search_query.with_timeout(timeout.as_str());
+    if result.total == 0 {
+        Err(BragiError::ObjectNotFound)
+    } else {
+        read_places(result, None).map_err(model::BragiError::from)
     }
+}
+
+/// Search the documents whose `field` (a `geo_shape`) intersects `shape`
+/// (the bounding box of a tile, a point...)
+#[allow(clippy::too_many_arguments)]
+pub fn intersecting(
+    search_type: &str,
+    indexes: &[&str],
+    field: &str,
+    shape: Geometry,
+    zone_types: &[&str],
+    with_boundary: bool,
+    limit: u64,
+    mut rubber: Rubber,
+) -> Result<Vec<mimir::Place>, BragiError> {
+    let mut filters = vec![Query::build_geo_shape(field).with_geojson(shape).build()];
+    if !zone_types.is_empty() {
+        filters.push(
+            Query::build_bool()
+                .with_should(
+                    zone_types
+                        .iter()
+                        .map(|x| Query::build_match("zone_type", *x).build())
+                        .collect::<Vec<_>>(),
+                )
+                .build(),
+        );
+    }
+    let query = Query::build_bool()
+        .with_filter(Query::build_bool().with_must(filters).build())
+        .build();
 
-    let result = search_query.send()?;
+    debug!("ES indexes: {:?}", indexes);
+
+    if indexes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let timer = ES_REQ_HISTOGRAM
+        .get_metric_with_label_values(&[search_type])
+        .map(|h| h.start_timer())
+        .map_err(
+            |err| error!("impossible to get ES_REQ_HISTOGRAM metrics"; "err" => err.to_string()),
+        )
+        .ok();
+
+    // the most important places are kept when there are more than `limit`
+    let sort = Sort::field_order("weight", Order::Desc);
+    let result = rubber.search(
+        &SearchRequest::new(indexes, &query)
+            .with_sort(&sort)
+            .with_size(limit)
+            .with_boundary(with_boundary),
+    )?;
 
     if let Some(t) = timer {
         t.observe_duration()
     }
 
-    if result.hits.total == 0 {
-        Err(BragiError::ObjectNotFound)
-    } else {
-        read_places(result, None).map_err(model::BragiError::from)
+    read_places(result, None).map_err(model::BragiError::from)
+}
+
+/// Search the admins having the exact values `terms` (like `id` or `parent_id`)
+/// and, if given, the zone type `zone_type`, sorted by name
+pub fn admins(
+    terms: &[(&str, &str)],
+    zone_type: Option<&str>,
+    limit: u64,
+    rubber: Rubber,
+) -> Result<Vec<mimir::Admin>, BragiError> {
+    let mut filters: Vec<Query> = terms
+        .iter()
+        .map(|(field, value)| Query::build_term(*field, *value).build())
+        .collect();
+    if let Some(zone_type) = zone_type {
+        filters.push(Query::build_match("zone_type", zone_type).build());
+    }
+    let query = Query::build_bool()
+        .with_filter(Query::build_bool().with_must(filters).build())
+        .build();
+    let sort = Sort::field_order("name.sort", Order::Asc);
+    search_admins(&query, Some(&sort), limit, rubber)
+}
+
+/// The admins of ids `ids`, in the order of `ids` (the unknown ids are skipped)
+pub fn admins_by_ids(ids: &[String], rubber: Rubber) -> Result<Vec<mimir::Admin>, BragiError> {
+    if ids.is_empty() {
+        return Ok(vec![]);
     }
+    let values = ids
+        .iter()
+        .map(|id| rs_es::units::JsonVal::String(id.clone()))
+        .collect();
+    let query = Query::build_bool()
+        .with_filter(Query::build_ids(values).build())
+        .build();
+    let mut admins = search_admins(&query, None, ids.len() as u64, rubber)?;
+    admins.sort_by_key(|admin| ids.iter().position(|id| *id == admin.id));
+    Ok(admins)
+}
+
+fn search_admins(
+    query: &Query,
+    sort: Option<&Sort>,
+    limit: u64,
+    mut rubber: Rubber,
+) -> Result<Vec<mimir::Admin>, BragiError> {
+    let timer = ES_REQ_HISTOGRAM
+        .get_metric_with_label_values(&["admins"])
+        .map(|h| h.start_timer())
+        .map_err(
+            |err| error!("impossible to get ES_REQ_HISTOGRAM metrics"; "err" => err.to_string()),
+        )
+        .ok();
+
+    let admins_index = get_main_type_index::<Admin>(&rubber.namespace);
+    let indexes = [admins_index.as_str()];
+    let mut search_request = SearchRequest::new(&indexes, query)
+        .with_size(limit)
+        .with_boundary(false);
+    if let Some(sort) = sort {
+        search_request = search_request.with_sort(sort);
+    }
+
+    let result = rubber.search(&search_request)?;
+
+    if let Some(t) = timer {
+        t.observe_duration()
+    }
+
+    Ok(read_places(result, None)?
+        .into_iter()
+        .filter_map(|place| match place {
+            mimir::Place::Admin(admin) => Some(admin),
+            _ => None,
+        })
+        .collect())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -651,7 +795,7 @@ pub fn autocomplete(
     debug: bool,
     query_settings: &QuerySettings,
     request_id: Option<&str>,
-) -> Result<Vec<mimir::Place>, BragiError> {
+) -> Result<AutocompleteResult, BragiError> {
     // Perform parameters validation.
     if !zone_types.is_empty() && !types.iter().any(|s| *s == "zone") {
         return Err(BragiError::InvalidParam(
@@ -666,7 +810,7 @@ pub fn autocomplete(
 
     // First we try a pretty exact match on the prefix.
     // If there are no results then we do a new fuzzy search (matching ngrams)
-    let results = query(
+    let (places, prefix_took) = query(
         &q,
         &pt_datasets,
         &poi_datasets,
@@ -687,7 +831,7 @@ pub fn autocomplete(
         request_id,
     )
     .map_err(model::BragiError::from)?;
-    if results.is_empty() {
+    if places.is_empty() {
         query(
             &q,
             &pt_datasets,
@@ -708,8 +852,17 @@ pub fn autocomplete(
             query_settings,
             request_id,
         )
+        .map(|(places, took)| AutocompleteResult {
+            places,
+            match_type: MatchType::Fuzzy,
+            took: prefix_took + took,
+        })
         .map_err(model::BragiError::from)
     } else {
-        Ok(results)
+        Ok(AutocompleteResult {
+            places,
+            match_type: MatchType::Prefix,
+            took: prefix_took,
+        })
     }
 }
@@ -0,0 +1,271 @@
+// Structured log of the autocomplete queries, for offline relevance analysis.
+//
+// Each logged query is written as one JSON line in a dedicated file (separated from the
+// application logs), rotated when it gets too big.
+
+use crate::model::BragiError;
+use crate::query::AutocompleteResult;
+use serde::Serialize;
+use slog_scope::error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct QueryLogConfig {
+    pub path: PathBuf,
+    /// maximum size of a file in bytes, before rotation
+    pub max_size: u64,
+    /// number of rotated files kept (`file.1` being the most recent)
+    pub max_files: usize,
+    /// fraction of the queries logged, between 0 and 1
+    pub sampling: f64,
+    /// number of results logged for each query
+    pub nb_results: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct LoggedResult<'a> {
+    id: &'a str,
+    #[serde(rename = "type")]
+    place_type: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct QueryLogEntry<'a, P: Serialize> {
+    timestamp: String,
+    params: &'a P,
+    with_shape: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nb_results: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<LoggedResult<'a>>>,
+    /// time spent by Elasticsearch in ms
+    #[serde(skip_serializing_if = "Option::is_none")]
+    took: Option<u64>,
+    /// the pass that produced the results (prefix or fuzzy)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+fn open_append(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let (file, size) = open_append(&path)?;
+        Ok(RotatingFile {
+            path,
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        // the line is written in one call to not mix it with another line
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    // file.{n-1} -> file.{n}, ..., file -> file.1
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        let (file, size) = open_append(&self.path)?;
+        self.file = file;
+        self.size = size;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct QueryLogger {
+    file: Mutex<RotatingFile>,
+    sampling: f64,
+    nb_results: usize,
+    nb_queries: AtomicU64,
+}
+
+fn place_type(place: &mimir::Place) -> &'static str {
+    match place {
+        mimir::Place::Admin(_) => "admin",
+        mimir::Place::Street(_) => "street",
+        mimir::Place::Addr(_) => "addr",
+        mimir::Place::Poi(_) => "poi",
+        mimir::Place::Stop(_) => "stop",
+    }
+}
+
+impl QueryLogger {
+    pub fn new(config: &QueryLogConfig) -> Result<Self, String> {
+        if !(0. ..=1.).contains(&config.sampling) {
+            return Err(format!(
+                "the query log sampling must be between 0 and 1, not {}",
+                config.sampling
+            ));
+        }
+        let file = RotatingFile::new(config.path.clone(), config.max_size, config.max_files)
+            .map_err(|e| format!("Failed to open `{}`: {}", config.path.display(), e))?;
+        Ok(QueryLogger {
+            file: Mutex::new(file),
+            sampling: config.sampling,
+            nb_results: config.nb_results,
+            nb_queries: AtomicU64::new(0),
+        })
+    }
+
+    // The sampling is deterministic: a query is logged each time the number of queries
+    // times the sampling reaches a new integer.
+    fn is_sampled(&self) -> bool {
+        let n = self.nb_queries.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.) * self.sampling).floor() > (n * self.sampling).floor()
+    }
+
+    pub fn log<P: Serialize>(
+        &self,
+        params: &P,
+        with_shape: bool,
+        result: &Result<AutocompleteResult, BragiError>,
+    ) {
+        if !self.is_sampled() {
+            return;
+        }
+        let mut entry = QueryLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            params,
+            with_shape,
+            nb_results: None,
+            results: None,
+            took: None,
+            match_type: None,
+            error: None,
+        };
+        match result {
+            Ok(res) => {
+                entry.nb_results = Some(res.places.len());
+                entry.results = Some(
+                    res.places
+                        .iter()
+                        .take(self.nb_results)
+                        .map(|p| LoggedResult {
+                            id: p.id(),
+                            place_type: place_type(p),
+                        })
+                        .collect(),
+                );
+                entry.took = Some(res.took);
+                entry.match_type = Some(res.match_type.to_string());
+            }
+            Err(err) => entry.error = Some(err.to_string()),
+        }
+        let res = serde_json::to_vec(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|mut line| {
+                line.push(b'\n');
+                self.file
+                    .lock()
+                    .unwrap()
+                    .write_line(&line)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = res {
+            error!("impossible to write the query log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger(dir: &Path, sampling: f64) -> QueryLogger {
+        QueryLogger::new(&QueryLogConfig {
+            path: dir.join(format!("queries_{}.log", sampling)),
+            max_size: 1024,
+            max_files: 2,
+            sampling,
+            nb_results: 1,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sampling() {
+        let dir =
+            std::env::temp_dir().join(format!("bragi_query_log_sampling_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let count = |logger: QueryLogger| (0..100).filter(|_| logger.is_sampled()).count();
+        assert_eq!(count(logger(&dir, 1.)), 100);
+        assert_eq!(count(logger(&dir, 0.25)), 25);
+        assert_eq!(count(logger(&dir, 0.)), 0);
+        assert!(QueryLogger::new(&QueryLogConfig {
+            path: dir.join("queries.log"),
+            max_size: 1,
+            max_files: 1,
+            sampling: 2.,
+            nb_results: 1,
+        })
+        .is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir =
+            std::env::temp_dir().join(format!("bragi_query_log_rotation_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queries.log");
+        let mut file = RotatingFile::new(path.clone(), 10, 2).unwrap();
+        for line in &["aaaaaa\n", "bbbbbb\n", "cccccc\n", "dddddd\n"] {
+            file.write_line(line.as_bytes()).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddd\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "cccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "bbbbbb\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use crate::extractors::BragiQuery;
+use crate::model::{self, AssociatedAdmin, FromWithLang};
+use crate::openapi::{schema, Parameter};
+use crate::{query, Context};
+use actix_http::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, HttpResponse, Path};
+use mimir::rubber::Rubber;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+/// Maximum number of ancestors of an admin
+const MAX_DEPTH: usize = 20;
+
+/// Maximum `limit`, the default `max_result_window` of Elasticsearch
+const MAX_LIMIT: u64 = 10_000;
+
+fn default_limit() -> u64 {
+    1000u64
+}
+
+/// Parameters of `/admins/{id}`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Params {
+    lang: Option<String>,
+    /// timeout in milliseconds
+    timeout: Option<u64>,
+}
+
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::path("id", "Id of the admin", json!("admin:osm:relation:7444")),
+            Parameter::query(
+                "lang",
+                "Language of the names and labels",
+                schema("string"),
+                json!("fr"),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+        ]
+    }
+}
+
+/// Parameters of `/admins` and `/admins/{id}/children`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListParams {
+    zone_type: Option<cosmogony::ZoneType>,
+    #[serde(default = "default_limit")]
+    limit: u64,
+    lang: Option<String>,
+    /// timeout in milliseconds
+    timeout: Option<u64>,
+}
+
+impl ListParams {
+    fn limit(&self) -> Result<u64, model::BragiError> {
+        if self.limit > MAX_LIMIT {
+            Err(model::BragiError::InvalidParam(
+                "limit must be at most 10000",
+            ))
+        } else {
+            Ok(self.limit)
+        }
+    }
+
+    /// Documentation of the parameters of `/admins`, where the zone type is required
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::query(
+                "zone_type",
+                "Type of the admins",
+                schema("string"),
+                json!("country"),
+            )
+            .required(),
+            Parameter::query(
+                "limit",
+                "Maximum number of admins (at most 10000)",
+                schema("integer"),
+                json!(100),
+            ),
+            Parameter::query(
+                "lang",
+                "Language of the names and labels",
+                schema("string"),
+                json!("fr"),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+        ]
+    }
+
+    /// Documentation of the parameters of `/admins/{id}/children`
+    pub(crate) fn openapi_children_parameters() -> Vec<Parameter> {
+        let mut parameters = vec![Parameter::path(
+            "id",
+            "Id of the parent admin",
+            json!("admin:osm:relation:7444"),
+        )];
+        parameters.extend(Self::openapi_parameters().into_iter().map(|p| Parameter {
+            required: false,
+            ..p
+        }));
+        parameters
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Admins {
+    pub admins: Vec<AssociatedAdmin>,
+}
+
+fn find_admin(id: &str, rubber: &Rubber) -> Result<Option<mimir::Admin>, model::BragiError> {
+    Ok(query::admins(&[("id", id)], None, 1, rubber.clone())?
+        .into_iter()
+        .next())
+}
+
+fn response(admins: &[mimir::Admin], lang: Option<&str>, cache_duration: u32) -> HttpResponse {
+    HttpResponse::Ok()
+        .set(CacheControl(vec![CacheDirective::MaxAge(cache_duration)]))
+        .json(Admins {
+            admins: admins
+                .iter()
+                .map(|admin| AssociatedAdmin::from_with_lang(admin, lang))
+                .collect(),
+        })
+}
+
+/// The admin followed by its ancestors, from its parent up to the root of the hierarchy
+pub fn admin(
+    params: BragiQuery<Params>,
+    state: Data<Context>,
+    id: Path<String>,
+) -> Result<HttpResponse, model::BragiError> {
+    let rubber = state.get_rubber_for_features(params.timeout.map(Duration::from_millis));
+    let admin = find_admin(&id, &rubber)?.ok_or(model::BragiError::ObjectNotFound)?;
+    // the stored `administrative_regions` are the ancestors, from the parent up
+    let mut ancestor_ids: Vec<String> = if admin.administrative_regions.is_empty() {
+        admin.parent_id.iter().cloned().collect()
+    } else {
+        admin
+            .administrative_regions
+            .iter()
+            .map(|a| a.id.clone())
+            .collect()
+    };
+    ancestor_ids.truncate(MAX_DEPTH);
+    let mut hierarchy = vec![admin];
+    hierarchy.extend(query::admins_by_ids(&ancestor_ids, rubber)?);
+    Ok(response(
+        &hierarchy,
+        params.lang.as_ref().map(String::as_str),
+        state.http_cache_duration,
+    ))
+}
+
+/// The admins whose parent is the admin `id`
+pub fn admin_children(
+    params: BragiQuery<ListParams>,
+    state: Data<Context>,
+    id: Path<String>,
+) -> Result<HttpResponse, model::BragiError> {
+    let rubber = state.get_rubber_for_features(params.timeout.map(Duration::from_millis));
+    find_admin(&id, &rubber)?.ok_or(model::BragiError::ObjectNotFound)?;
+    let children = query::admins(
+        &[("parent_id", id.as_str())],
+        params.zone_type.as_ref().map(|z| z.as_str()),
+        params.limit()?,
+        rubber,
+    )?;
+    Ok(response(
+        &children,
+        params.lang.as_ref().map(String::as_str),
+        state.http_cache_duration,
+    ))
+}
+
+/// The admins of a zone type, like all the countries
+pub fn admins(
+    params: BragiQuery<ListParams>,
+    state: Data<Context>,
+) -> Result<HttpResponse, model::BragiError> {
+    let zone_type = params
+        .zone_type
+        .as_ref()
+        .ok_or(model::BragiError::InvalidParam("zone_type is required"))?;
+    let admins = query::admins(
+        &[],
+        Some(zone_type.as_str()),
+        params.limit()?,
+        state.get_rubber_for_features(params.timeout.map(Duration::from_millis)),
+    )?;
+    Ok(response(
+        &admins,
+        params.lang.as_ref().map(String::as_str),
+        state.http_cache_duration,
+    ))
+}
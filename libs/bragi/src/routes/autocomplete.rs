@@ -1,12 +1,15 @@
 use crate::extractors::BragiQuery;
 use crate::model::{Autocomplete, BragiError, FromWithLang};
+use crate::openapi::{array_of, enumeration, schema, Parameter};
 use crate::routes::params;
-use crate::{model, query, Context};
+use crate::{confidence, federation, model, query, Context, QuerySettings};
 use actix_http::http::header::{CacheControl, CacheDirective};
 use actix_web::web::{Data, HttpResponse, Json};
 use geojson::{GeoJson, Geometry};
 use mimir::objects::{Coord, PlaceDocType};
+use mimir::rubber::Rubber;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use slog_scope::trace;
 use std::time::Duration;
 
@@ -27,6 +30,15 @@ enum Type {
 }
 
 impl Type {
+    const ALL: [Type; 6] = [
+        Type::City,
+        Type::House,
+        Type::Poi,
+        Type::StopArea,
+        Type::Street,
+        Type::Zone,
+    ];
+
     fn as_str(&self) -> &'static str {
         match self {
             Type::City => "city",
@@ -121,6 +133,137 @@ impl Params {
     fn timeout(&self) -> Option<Duration> {
         self.timeout.map(Duration::from_millis)
     }
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        let datasets = array_of(schema("string"));
+        vec![
+            Parameter::query(
+                "q",
+                "Text to search",
+                schema("string"),
+                json!("rue hector malot"),
+            )
+            .required(),
+            Parameter::query(
+                "pt_dataset",
+                "Public transport datasets to search in",
+                datasets.clone(),
+                json!(["fr-idf"]),
+            ),
+            Parameter::query(
+                "poi_dataset",
+                "Private POI datasets to search in",
+                datasets,
+                json!(["idf-pois"]),
+            ),
+            Parameter::query(
+                "_all_data",
+                "Search in all the datasets (not a public parameter)",
+                schema("boolean"),
+                json!(false),
+            ),
+            Parameter::query(
+                "limit",
+                "Maximum number of results",
+                json!({ "type": "integer", "default": default_limit() }),
+                json!(10),
+            ),
+            Parameter::query(
+                "offset",
+                "Number of results to skip",
+                json!({ "type": "integer", "default": 0 }),
+                json!(0),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+            Parameter::query(
+                "lat",
+                "Latitude of the position of the user, the places nearby are favored",
+                schema("number"),
+                json!(48.85),
+            ),
+            Parameter::query(
+                "lon",
+                "Longitude of the position of the user, the places nearby are favored",
+                schema("number"),
+                json!(2.35),
+            ),
+            Parameter::query(
+                "proximity_scale",
+                "Override the scale (in km) of the decay around the position",
+                schema("number"),
+                json!(130.0),
+            ),
+            Parameter::query(
+                "proximity_offset",
+                "Override the offset (in km) of the decay around the position",
+                schema("number"),
+                json!(20.0),
+            ),
+            Parameter::query(
+                "proximity_decay",
+                "Override the decay around the position",
+                schema("number"),
+                json!(0.4),
+            ),
+            Parameter::query(
+                "type",
+                "Types of the places to search",
+                array_of(enumeration(Type::ALL.iter().map(Type::as_str))),
+                json!(["street", "zone"]),
+            ),
+            Parameter::query(
+                "zone_type",
+                "Types of the zones to search, requires type[]=zone",
+                array_of(schema("string")),
+                json!(["city"]),
+            ),
+            Parameter::query(
+                "poi_type",
+                "Types of the POIs to search, requires type[]=poi",
+                array_of(schema("string")),
+                json!(["poi_type:amenity:bicycle_rental"]),
+            ),
+            Parameter::query(
+                "lang",
+                "Language of the names and labels",
+                schema("string"),
+                json!("fr"),
+            ),
+            Parameter::query(
+                "shape_scope",
+                "Types of the places restricted to the shape (POST only)",
+                array_of(enumeration(
+                    [
+                        PlaceDocType::Admin,
+                        PlaceDocType::Street,
+                        PlaceDocType::Addr,
+                        PlaceDocType::Poi,
+                        PlaceDocType::Stop,
+                    ]
+                    .iter()
+                    .map(PlaceDocType::as_str),
+                )),
+                json!(["poi"]),
+            ),
+            Parameter::query(
+                "_debug",
+                "Add the Elasticsearch explanation of the score (not a public parameter)",
+                schema("boolean"),
+                json!(false),
+            ),
+            Parameter::query(
+                "request_id",
+                "Id of the request, used for tracing",
+                schema("string"),
+                json!("a1b2c3"),
+            ),
+        ]
+    }
     fn build_coord(lon: Option<f64>, lat: Option<f64>) -> Result<Option<Coord>, BragiError> {
         match (lon, lat) {
             (Some(lon), Some(lat)) => Ok(Some(params::make_coord(lon, lat)?)),
@@ -138,6 +281,19 @@ pub struct JsonParams {
 }
 
 impl JsonParams {
+    pub(crate) fn openapi_example() -> serde_json::Value {
+        json!({
+            "shape": {
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[2.2, 48.8], [2.5, 48.8], [2.5, 48.9], [2.2, 48.9], [2.2, 48.8]]]
+                }
+            }
+        })
+    }
+
     fn get_geometry(self) -> Result<Geometry, model::BragiError> {
         match self.shape {
             GeoJson::Feature(f) => f.geometry.ok_or(BragiError::InvalidShape("no geometry")),
@@ -152,7 +308,6 @@ pub fn call_autocomplete(
     shape: Option<Geometry>,
 ) -> Result<HttpResponse, model::BragiError> {
     let langs = params.langs();
-    let rubber = state.get_rubber_for_autocomplete(params.timeout());
     let mut query_settings = state.get_query_settings().clone();
 
     if let Some(scale) = params.proximity_scale {
@@ -171,41 +326,94 @@ pub fn call_autocomplete(
         trace!("routes::autocomplete by {} ({})", id, params.q);
     }
 
-    let res = query::autocomplete(
+    let with_shape = shape.is_some();
+    let res = match state.get_federation() {
+        Some(federation) => {
+            let targets = federation.autocomplete_targets(
+                params.timeout(),
+                &params.pt_dataset,
+                &params.poi_dataset,
+                params.all_data,
+            );
+            let (offset, limit) = (params.offset, params.limit);
+            let federated_params = params.clone();
+            let results = federation::fan_out(targets, move |target| {
+                search(
+                    &federated_params,
+                    &target.pt_datasets,
+                    &target.poi_datasets,
+                    // each cluster has to return enough results for the merged page
+                    0,
+                    offset + limit,
+                    shape.clone(),
+                    target.rubber,
+                    &query_settings,
+                )
+            });
+            federation::merge_autocomplete(results, offset, limit)
+        }
+        None => search(
+            params,
+            &params.pt_dataset,
+            &params.poi_dataset,
+            params.offset,
+            params.limit,
+            shape,
+            state.get_rubber_for_autocomplete(params.timeout()),
+            &query_settings,
+        ),
+    };
+    if let Some(logger) = state.get_query_logger() {
+        logger.log(params, with_shape, &res);
+    }
+    res.map(|r| {
+        let confidences = confidence::confidences(&params.q, &r.places, r.match_type);
+        let mut autocomplete = Autocomplete::from_with_lang(r.places, langs.into_iter().next());
+        for (feature, confidence) in autocomplete.features.iter_mut().zip(confidences) {
+            feature.match_type = Some(r.match_type);
+            feature.confidence = Some(confidence);
+        }
+        autocomplete
+    })
+    .map(|v| {
+        HttpResponse::Ok()
+            .set(CacheControl(vec![CacheDirective::MaxAge(
+                state.http_cache_duration,
+            )]))
+            .json(v)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search(
+    params: &Params,
+    pt_datasets: &[String],
+    poi_datasets: &[String],
+    offset: u64,
+    limit: u64,
+    shape: Option<Geometry>,
+    rubber: Rubber,
+    query_settings: &QuerySettings,
+) -> Result<query::AutocompleteResult, model::BragiError> {
+    query::autocomplete(
         &params.q,
-        &params
-            .pt_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
-        &params
-            .poi_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
+        &pt_datasets.iter().map(String::as_str).collect::<Vec<_>>(),
+        &poi_datasets.iter().map(String::as_str).collect::<Vec<_>>(),
         params.all_data,
-        params.offset,
-        params.limit,
+        offset,
+        limit,
         params.coord()?,
         shape,
         &params.shape_scope,
         &params.types_as_str(),
         &params.zone_types_as_str(),
         &params.poi_types_as_str(),
-        &langs,
+        &params.langs(),
         rubber,
         params.debug.unwrap_or(false),
-        &query_settings,
+        query_settings,
         params.request_id.as_deref(),
-    );
-    res.map(|r| Autocomplete::from_with_lang(r, langs.into_iter().next()))
-        .map(|v| {
-            HttpResponse::Ok()
-                .set(CacheControl(vec![CacheDirective::MaxAge(
-                    state.http_cache_duration,
-                )]))
-                .json(v)
-        })
+    )
 }
 
 pub fn autocomplete(
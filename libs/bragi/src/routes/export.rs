@@ -0,0 +1,138 @@
+use crate::extractors::BragiQuery;
+use crate::model::{self, FromWithLang, GeocodingResponse};
+use crate::openapi::{schema, Parameter};
+use crate::Context;
+use actix_web::error::ErrorInternalServerError;
+use actix_web::web::{Bytes, Data, HttpResponse, Path};
+use futures::sync::mpsc;
+use futures::{Sink, Stream};
+use mimir::objects::{Addr, Admin, MimirObject, Poi, Stop, Street};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use slog_scope::{error, info};
+use std::thread;
+use std::time::Duration;
+
+/// Number of places buffered between Elasticsearch and the client
+const BUFFER_SIZE: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Params {
+    lang: Option<String>,
+    /// timeout of each scroll request in milliseconds
+    timeout: Option<u64>,
+}
+
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::path(
+                "type",
+                "Type of the places: `admin`, `street`, `addr`, `poi` or `stop`",
+                json!("stop"),
+            ),
+            Parameter::path("dataset", "Dataset of the places", json!("fr-idf")),
+            Parameter::query(
+                "lang",
+                "Language of the names and labels",
+                schema("string"),
+                json!("fr"),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of each Elasticsearch scroll request in milliseconds",
+                schema("integer"),
+                json!(10000),
+            ),
+        ]
+    }
+}
+
+/// Is `dataset` a dataset name, without the `,`, `*` or `?` of the index patterns of
+/// Elasticsearch that would give access to other indexes
+pub(crate) fn is_valid_dataset(dataset: &str) -> bool {
+    !dataset.is_empty()
+        && dataset
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// All the places of the index `{namespace}_{type}_{dataset}`, as newline delimited JSON.
+///
+/// The index is read with the scroll API in a dedicated thread, and the places are
+/// streamed to the client as they come, so the export is not loaded in memory.
+pub fn export(
+    params: BragiQuery<Params>,
+    state: Data<Context>,
+    path: Path<(String, String)>,
+) -> Result<HttpResponse, model::BragiError> {
+    let (doc_type, dataset) = path.into_inner();
+    let doc_types = [
+        Admin::doc_type(),
+        Street::doc_type(),
+        Addr::doc_type(),
+        Poi::doc_type(),
+        Stop::doc_type(),
+    ];
+    if !doc_types.contains(&doc_type.as_str()) {
+        return Err(model::BragiError::InvalidParam(
+            "the type must be one of `admin`, `street`, `addr`, `poi` or `stop`",
+        ));
+    }
+    if !is_valid_dataset(&dataset) {
+        return Err(model::BragiError::InvalidParam(
+            "the dataset must only contain letters, digits, `_` and `-`",
+        ));
+    }
+    let rubber = state.get_rubber_for_features(params.timeout.map(Duration::from_millis));
+    let index = format!("{}_{}_{}", rubber.namespace, doc_type, dataset);
+    // on a connection error, the scroll gives the error
+    if !rubber.alias_exists(&index).unwrap_or(true) {
+        return Err(model::BragiError::ObjectNotFound);
+    }
+    let places = rubber.scroll_places(&index)?;
+
+    let lang = params.lang.clone();
+    let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+    thread::spawn(move || {
+        // the sender blocks when the buffer is full, until the client reads the places
+        let mut sender = sender.wait();
+        let mut nb_places = 0;
+        for place in places {
+            let place = match place {
+                Ok(place) => place,
+                Err(e) => {
+                    error!("export of {} interrupted: {}", index, e);
+                    // the response is aborted, so the client knows the export is incomplete
+                    let _ = sender.send(Err(()));
+                    return;
+                }
+            };
+            let response = GeocodingResponse::from_with_lang(place, lang.as_deref());
+            let mut line = match serde_json::to_vec(&response) {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("impossible to serialize {}: {}", response.id, e);
+                    continue;
+                }
+            };
+            line.push(b'\n');
+            if sender.send(Ok(Bytes::from(line))).is_err() {
+                info!("export of {} cancelled by the client", index);
+                return;
+            }
+            nb_places += 1;
+        }
+        let _ = sender.flush();
+        info!("{} places of {} exported", nb_places, index);
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(
+            receiver
+                .and_then(|line| line)
+                .map_err(|()| ErrorInternalServerError("export interrupted")),
+        ))
+}
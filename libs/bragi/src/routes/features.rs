@@ -1,10 +1,30 @@
 use crate::extractors::BragiQuery;
-use crate::{model, model::FromWithLang, query, Context};
+use crate::openapi::{array_of, enumeration, schema, Parameter};
+use crate::{federation, model, model::FromWithLang, query, Context};
 use actix_http::http::header::{CacheControl, CacheDirective};
 use actix_web::web::{Data, HttpResponse, Path};
+use geo::algorithm::simplify::Simplify;
+use mimir::rubber::Rubber;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::time::Duration;
 
+/// Geometry of the returned features
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeometryType {
+    /// the coordinates of the place
+    Point,
+    /// the boundary of the admins (the other places keep their coordinates)
+    Boundary,
+}
+
+impl Default for GeometryType {
+    fn default() -> Self {
+        GeometryType::Point
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Params {
     #[serde(default)]
@@ -15,6 +35,55 @@ pub struct Params {
     all_data: bool,
     /// timeout in milliseconds
     timeout: Option<u64>,
+    #[serde(default)]
+    geometry: GeometryType,
+    /// tolerance of the simplification of the boundary, in degrees
+    tolerance: Option<f64>,
+}
+
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::path("id", "Id of the place", json!("admin:fr:75056")),
+            Parameter::query(
+                "pt_dataset",
+                "Public transport datasets to search in",
+                array_of(schema("string")),
+                json!(["fr-idf"]),
+            ),
+            Parameter::query(
+                "poi_dataset",
+                "Private POI datasets to search in",
+                array_of(schema("string")),
+                json!(["idf-pois"]),
+            ),
+            Parameter::query(
+                "_all_data",
+                "Search in all the datasets (not a public parameter)",
+                schema("boolean"),
+                json!(false),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+            Parameter::query(
+                "geometry",
+                "Geometry of the features: the coordinates (`point`) or, for the admins, their boundary (`boundary`)",
+                enumeration(vec!["point", "boundary"]),
+                json!("boundary"),
+            ),
+            Parameter::query(
+                "tolerance",
+                "Tolerance in degrees of the simplification of the boundary (Douglas-Peucker), not simplified if not set",
+                schema("number"),
+                json!(0.001),
+            ),
+        ]
+    }
 }
 
 pub fn features(
@@ -22,24 +91,54 @@ pub fn features(
     state: Data<Context>,
     id: Path<String>,
 ) -> Result<HttpResponse, model::BragiError> {
-    let rubber = state.get_rubber_for_features(params.timeout.map(Duration::from_millis));
-    let features = query::features(
-        &params
-            .pt_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
-        &params
-            .poi_dataset
-            .iter()
-            .map(String::as_str)
-            .collect::<Vec<_>>(),
-        params.all_data,
-        &*id,
-        rubber,
-    );
+    if params.tolerance.map_or(false, |t| !t.is_finite() || t < 0.) {
+        return Err(model::BragiError::InvalidParam(
+            "the tolerance must be a positive number",
+        ));
+    }
+    let timeout = params.timeout.map(Duration::from_millis);
+    let with_boundary = params.geometry == GeometryType::Boundary;
+    let features = match state.get_federation() {
+        Some(federation) => {
+            let targets = federation.features_targets(
+                timeout,
+                &params.pt_dataset,
+                &params.poi_dataset,
+                params.all_data,
+            );
+            let all_data = params.all_data;
+            let id = id.into_inner();
+            let results = federation::fan_out(targets, move |target| {
+                search(
+                    &target.pt_datasets,
+                    &target.poi_datasets,
+                    all_data,
+                    &id,
+                    with_boundary,
+                    target.rubber,
+                )
+            });
+            federation::first_found(results)
+        }
+        None => search(
+            &params.pt_dataset,
+            &params.poi_dataset,
+            params.all_data,
+            &*id,
+            with_boundary,
+            state.get_rubber_for_features(timeout),
+        ),
+    };
     features
-        .map(|r| model::Autocomplete::from_with_lang(r, None))
+        .map(|places| {
+            model::Autocomplete::new(
+                "".to_string(),
+                places
+                    .into_iter()
+                    .map(|p| to_feature(p, params.geometry, params.tolerance))
+                    .collect(),
+            )
+        })
         .map(|v| {
             HttpResponse::Ok()
                 .set(CacheControl(vec![CacheDirective::MaxAge(
@@ -48,3 +147,42 @@ pub fn features(
                 .json(v)
         })
 }
+
+fn search(
+    pt_datasets: &[String],
+    poi_datasets: &[String],
+    all_data: bool,
+    id: &str,
+    with_boundary: bool,
+    rubber: Rubber,
+) -> Result<Vec<mimir::Place>, model::BragiError> {
+    query::features(
+        &pt_datasets.iter().map(String::as_str).collect::<Vec<_>>(),
+        &poi_datasets.iter().map(String::as_str).collect::<Vec<_>>(),
+        all_data,
+        id,
+        with_boundary,
+        rubber,
+    )
+}
+
+fn to_feature(
+    mut place: mimir::Place,
+    geometry: GeometryType,
+    tolerance: Option<f64>,
+) -> model::Feature {
+    let boundary = match (&mut place, geometry) {
+        (mimir::Place::Admin(admin), GeometryType::Boundary) => admin.boundary.take(),
+        _ => None,
+    };
+    let mut feature = model::Feature::from_with_lang(place, None);
+    // an admin without boundary keeps its coordinates
+    if let Some(boundary) = boundary {
+        let boundary = match tolerance {
+            Some(tolerance) => boundary.simplify(&tolerance),
+            None => boundary,
+        };
+        feature.geometry = geojson::Geometry::new(geojson::Value::from(&boundary));
+    }
+    feature
+}
@@ -0,0 +1,60 @@
+use crate::Context;
+use actix_web::web::{Data, HttpResponse};
+use mimir::rubber::get_geo_data_index;
+use serde::{Deserialize, Serialize};
+use slog_scope::warn;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Health {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reason: Option<String>,
+}
+
+pub fn live() -> HttpResponse {
+    HttpResponse::Ok().json(Health {
+        status: "alive".to_owned(),
+        reason: None,
+    })
+}
+
+fn check_ready(state: &Context) -> Result<(), String> {
+    if state.is_draining() {
+        return Err("the server is shutting down".to_owned());
+    }
+    let rubber = state.get_rubber_for_health();
+    // the aliases that must exist for bragi to answer the queries
+    let required_aliases = [
+        rubber.namespace.clone(),
+        get_geo_data_index(&rubber.namespace),
+    ];
+    let mut missing = vec![];
+    for alias in required_aliases.iter() {
+        match rubber.alias_exists(alias) {
+            Ok(true) => {}
+            Ok(false) => missing.push(alias.as_str()),
+            Err(e) => {
+                warn!("elasticsearch is not reachable: {}", e);
+                return Err("elasticsearch is not reachable".to_owned());
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("missing aliases: {}", missing.join(", ")))
+    }
+}
+
+pub fn ready(state: Data<Context>) -> HttpResponse {
+    match check_ready(&state) {
+        Ok(()) => HttpResponse::Ok().json(Health {
+            status: "ready".to_owned(),
+            reason: None,
+        }),
+        Err(reason) => HttpResponse::ServiceUnavailable().json(Health {
+            status: "not ready".to_owned(),
+            reason: Some(reason),
+        }),
+    }
+}
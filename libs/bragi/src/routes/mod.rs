@@ -1,12 +1,32 @@
+mod admins;
 mod autocomplete;
 mod entry_point;
+mod export;
 mod features;
+mod health;
+mod openapi;
 mod params;
 mod reverse;
 mod status;
+mod tiles;
+mod timezone;
 
+pub(crate) use admins::ListParams as AdminsListParams;
+pub(crate) use admins::Params as AdminsParams;
+pub use admins::{admin, admin_children, admins, Admins};
+pub(crate) use autocomplete::Params as AutocompleteParams;
 pub use autocomplete::{autocomplete, post_autocomplete, JsonParams};
 pub use entry_point::entry_point;
+pub use export::export;
+pub(crate) use export::{is_valid_dataset, Params as ExportParams};
 pub use features::features;
+pub(crate) use features::Params as FeaturesParams;
+pub use health::{live, ready, Health};
+pub use openapi::openapi;
 pub use reverse::reverse;
-pub use status::status;
+pub(crate) use reverse::Params as ReverseParams;
+pub use status::{incompatible_indexes, status, IndexTemplateVersion, Status};
+pub use tiles::tiles;
+pub(crate) use tiles::Params as TilesParams;
+pub(crate) use timezone::Params as TimezoneParams;
+pub use timezone::{timezone, Timezone};
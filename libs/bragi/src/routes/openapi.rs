@@ -0,0 +1,6 @@
+use crate::openapi::DOCUMENT;
+use actix_web::web::HttpResponse;
+
+pub fn openapi() -> HttpResponse {
+    HttpResponse::Ok().json(&*DOCUMENT)
+}
@@ -1,9 +1,11 @@
 use crate::extractors::BragiQuery;
+use crate::openapi::{schema, Parameter};
 use crate::routes::params;
-use crate::{model, model::FromWithLang, Context};
+use crate::{federation, model, model::FromWithLang, Context};
 use actix_http::http::header::{CacheControl, CacheDirective};
 use actix_web::web::{Data, HttpResponse};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::time::Duration;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -14,15 +16,46 @@ pub struct Params {
     timeout: Option<u64>,
 }
 
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::query("lat", "Latitude", schema("number"), json!(48.85)).required(),
+            Parameter::query("lon", "Longitude", schema("number"), json!(2.35)).required(),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+        ]
+    }
+}
+
 pub fn reverse(
     params: BragiQuery<Params>,
     state: Data<Context>,
 ) -> Result<HttpResponse, model::BragiError> {
-    let mut rubber = state.get_rubber_for_reverse(params.timeout.map(Duration::from_millis));
+    let timeout = params.timeout.map(Duration::from_millis);
     let coord = params::make_coord(params.lon, params.lat)?;
-    rubber
-        .get_address(&coord)
-        .map_err(model::BragiError::from)
+    let places = match state.get_federation() {
+        // the addresses and streets are on the clusters hosting the geographic data
+        Some(federation) => {
+            let targets = federation.reverse_targets(timeout);
+            let results = federation::fan_out(targets, move |mut target| {
+                target
+                    .rubber
+                    .get_address(&coord)
+                    .map_err(model::BragiError::from)
+            });
+            federation::nearest(results)
+        }
+        None => state
+            .get_rubber_for_reverse(timeout)
+            .get_address(&coord)
+            .map_err(model::BragiError::from),
+    };
+    places
         .map(|r| model::Autocomplete::from_with_lang(r, None))
         .map(|v| {
             HttpResponse::Ok()
@@ -1,6 +1,8 @@
 use crate::Context;
 use actix_web::web::{Data, Json};
+use mimir::rubber::{Rubber, TEMPLATE_VERSION};
 use serde::{Deserialize, Serialize};
+use slog_scope::warn;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -9,6 +11,39 @@ pub struct Status {
     pub version: String,
     pub es: String,
     pub status: String,
+    /// Template version expected for the indexes
+    pub template_version: u64,
+    /// Indexes built with another template version
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub incompatible_indexes: Vec<IndexTemplateVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexTemplateVersion {
+    pub index: String,
+    /// `None` for an index built before the templates were versioned
+    pub template_version: Option<u64>,
+}
+
+/// The searched indexes built with a template version other than `TEMPLATE_VERSION`
+pub fn incompatible_indexes(rubber: &Rubber) -> Vec<IndexTemplateVersion> {
+    match rubber.get_template_versions(&format!("{0},{0}_poi_*", rubber.namespace)) {
+        Ok(versions) => versions
+            .into_iter()
+            .filter(|(_, version)| *version != Some(TEMPLATE_VERSION))
+            .map(|(index, template_version)| IndexTemplateVersion {
+                index,
+                template_version,
+            })
+            .collect(),
+        Err(e) => {
+            warn!(
+                "impossible to get the template versions of the indexes: {}",
+                e
+            );
+            vec![]
+        }
+    }
 }
 
 pub fn status(state: Data<Context>) -> Json<Status> {
@@ -16,5 +51,7 @@ pub fn status(state: Data<Context>) -> Json<Status> {
         version: VERSION.to_string(),
         es: state.cnx_string.clone(),
         status: "good".to_string(),
+        template_version: TEMPLATE_VERSION,
+        incompatible_indexes: state.get_incompatible_indexes(),
     })
 }
@@ -0,0 +1,223 @@
+use crate::extractors::BragiQuery;
+use crate::mvt::{self, Geometry, Layer, TileId};
+use crate::openapi::{array_of, schema, Parameter};
+use crate::{model, query, Context};
+use actix_http::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, HttpResponse, Path};
+use geo::algorithm::simplify::Simplify;
+use mimir::rubber::get_indexes;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+/// Maximum number of places in a tile
+const MAX_FEATURES: u64 = 1000;
+
+/// Below this zoom level, the admins are points: their boundaries are much larger than a tile
+/// and are not fetched
+const MIN_BOUNDARY_ZOOM: u32 = 8;
+
+/// The minimum zoom level of each zone type, when `zone_type` is not given
+const ZONE_TYPE_MIN_ZOOMS: [(&str, u32); 7] = [
+    ("country", 0),
+    ("country_region", 4),
+    ("state", 4),
+    ("state_district", 6),
+    ("city", 8),
+    ("city_district", 11),
+    ("suburb", 12),
+];
+
+/// The zone types of the admins shown at a zoom level
+fn zone_types_at_zoom(z: u32) -> Vec<&'static str> {
+    ZONE_TYPE_MIN_ZOOMS
+        .iter()
+        .filter(|(_, min_zoom)| *min_zoom <= z)
+        .map(|(zone_type, _)| *zone_type)
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Params {
+    #[serde(default)]
+    pt_dataset: Vec<String>,
+    #[serde(default)]
+    poi_dataset: Vec<String>,
+    /// only for the `admin` layer
+    #[serde(default, rename = "zone_type")]
+    zone_types: Vec<cosmogony::ZoneType>,
+    /// timeout in milliseconds
+    timeout: Option<u64>,
+}
+
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        let integer_path = |name: &'static str, description: &'static str, example| Parameter {
+            schema: schema("integer"),
+            ..Parameter::path(name, description, example)
+        };
+        vec![
+            Parameter::path(
+                "layer",
+                "Layer of the tile: `admin`, `stop` or `poi`",
+                json!("admin"),
+            ),
+            integer_path("z", "Zoom level", json!(12)),
+            integer_path("x", "Column of the tile", json!(2074)),
+            integer_path("y", "Row of the tile", json!(1409)),
+            Parameter::query(
+                "pt_dataset",
+                "Public transport datasets of the stops",
+                array_of(schema("string")),
+                json!(["fr-idf"]),
+            ),
+            Parameter::query(
+                "poi_dataset",
+                "Private POI datasets",
+                array_of(schema("string")),
+                json!(["idf-pois"]),
+            ),
+            Parameter::query(
+                "zone_type",
+                "Types of the admins, only for the `admin` layer",
+                array_of(schema("string")),
+                json!(["city"]),
+            ),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+        ]
+    }
+}
+
+pub fn tiles(
+    params: BragiQuery<Params>,
+    state: Data<Context>,
+    path: Path<(String, u32, u32, u32)>,
+) -> Result<HttpResponse, model::BragiError> {
+    let (layer, z, x, y) = path.into_inner();
+    let tile = TileId::new(z, x, y).map_err(model::BragiError::InvalidParam)?;
+    // the admins are found by their boundary, the other places by their coordinates
+    let (doc_type, field) = match layer.as_str() {
+        "admin" => ("zone", "boundary"),
+        "stop" => ("public_transport:stop_area", "approx_coord"),
+        "poi" => ("poi", "approx_coord"),
+        _ => {
+            return Err(model::BragiError::InvalidParam(
+                "the layer must be one of `admin`, `stop` or `poi`",
+            ))
+        }
+    };
+    let pt_datasets: Vec<&str> = params.pt_dataset.iter().map(String::as_str).collect();
+    let poi_datasets: Vec<&str> = params.poi_dataset.iter().map(String::as_str).collect();
+    let timeout = params.timeout.map(Duration::from_millis);
+    let rubber = state.get_rubber_for_features(timeout);
+    let indexes = get_indexes(
+        &rubber.namespace,
+        false,
+        &pt_datasets,
+        &poi_datasets,
+        &[doc_type],
+    );
+    let zone_types: Vec<&str> = match layer.as_str() {
+        "admin" if params.zone_types.is_empty() => zone_types_at_zoom(z),
+        "admin" => params.zone_types.iter().map(|z| z.as_str()).collect(),
+        _ => vec![],
+    };
+
+    let bbox = tile.bbox();
+    let (min, max) = (bbox.min(), bbox.max());
+    let bbox = geojson::Geometry::new(geojson::Value::Polygon(vec![vec![
+        vec![min.x, min.y],
+        vec![max.x, min.y],
+        vec![max.x, max.y],
+        vec![min.x, max.y],
+        vec![min.x, min.y],
+    ]]));
+
+    let places = query::intersecting(
+        "tile",
+        &indexes.iter().map(String::as_str).collect::<Vec<_>>(),
+        field,
+        bbox,
+        &zone_types,
+        layer == "admin" && z >= MIN_BOUNDARY_ZOOM,
+        MAX_FEATURES,
+        rubber,
+    )?;
+
+    let mut tile_layer = Layer::new(&layer, tile);
+    // the boundaries are simplified to the precision of the tile
+    let tolerance = tile.resolution();
+    for place in places {
+        match place {
+            mimir::Place::Admin(admin) => {
+                let mut properties = vec![
+                    ("id", admin.id.as_str()),
+                    ("label", admin.label.as_str()),
+                    ("name", admin.name.as_str()),
+                ];
+                if let Some(zone_type) = admin.zone_type.as_ref() {
+                    properties.push(("zone_type", zone_type.as_str()));
+                }
+                match admin.boundary {
+                    Some(ref boundary) => tile_layer.add_feature(
+                        &properties,
+                        Geometry::MultiPolygon(&boundary.simplify(&tolerance)),
+                    ),
+                    None => tile_layer.add_feature(&properties, Geometry::Point(admin.coord.0)),
+                }
+            }
+            mimir::Place::Stop(stop) => tile_layer.add_feature(
+                &[
+                    ("id", stop.id.as_str()),
+                    ("label", stop.label.as_str()),
+                    ("name", stop.name.as_str()),
+                ],
+                Geometry::Point(stop.coord.0),
+            ),
+            mimir::Place::Poi(poi) => tile_layer.add_feature(
+                &[
+                    ("id", poi.id.as_str()),
+                    ("label", poi.label.as_str()),
+                    ("name", poi.name.as_str()),
+                    ("poi_type", poi.poi_type.name.as_str()),
+                ],
+                Geometry::Point(poi.coord.0),
+            ),
+            _ => {}
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/vnd.mapbox-vector-tile")
+        .set(CacheControl(vec![CacheDirective::MaxAge(
+            state.http_cache_duration,
+        )]))
+        .body(mvt::encode_tile(&[tile_layer])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_types_at_zoom() {
+        assert_eq!(zone_types_at_zoom(2), vec!["country"]);
+        assert_eq!(
+            zone_types_at_zoom(8),
+            vec![
+                "country",
+                "country_region",
+                "state",
+                "state_district",
+                "city"
+            ]
+        );
+        assert_eq!(zone_types_at_zoom(14).len(), ZONE_TYPE_MIN_ZOOMS.len());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::extractors::BragiQuery;
+use crate::openapi::{schema, Parameter};
+use crate::routes::params;
+use crate::{model, query, Context};
+use actix_http::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, HttpResponse};
+use mimir::objects::Admin;
+use mimir::rubber::get_main_type_index;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+/// Maximum number of admins containing the coordinate
+const MAX_ADMINS: u64 = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Params {
+    lat: f64,
+    lon: f64,
+    /// timeout in milliseconds
+    timeout: Option<u64>,
+}
+
+impl Params {
+    /// Documentation of the parameters, kept in sync with the structure by the `openapi` tests
+    pub(crate) fn openapi_parameters() -> Vec<Parameter> {
+        vec![
+            Parameter::query("lat", "Latitude", schema("number"), json!(48.85)).required(),
+            Parameter::query("lon", "Longitude", schema("number"), json!(2.35)).required(),
+            Parameter::query(
+                "timeout",
+                "Timeout of the query in milliseconds",
+                schema("integer"),
+                json!(500),
+            ),
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Timezone {
+    /// IANA timezone, like "Europe/Paris"
+    pub timezone: String,
+    /// id of the admin giving the timezone
+    pub admin_id: String,
+}
+
+/// Timezone of a coordinate, given by the most precise admin containing it that has a timezone
+pub fn timezone(
+    params: BragiQuery<Params>,
+    state: Data<Context>,
+) -> Result<HttpResponse, model::BragiError> {
+    let coord = params::make_coord(params.lon, params.lat)?;
+    let point = geojson::Geometry::new(geojson::Value::Point(vec![coord.lon(), coord.lat()]));
+    let rubber = state.get_rubber_for_reverse(params.timeout.map(Duration::from_millis));
+    let admins_index = get_main_type_index::<Admin>(&rubber.namespace);
+    let admin = query::intersecting(
+        "timezone",
+        &[admins_index.as_str()],
+        "boundary",
+        point,
+        &[],
+        false,
+        MAX_ADMINS,
+        rubber,
+    )?
+    .into_iter()
+    .filter_map(|place| match place {
+        mimir::Place::Admin(admin) => Some(admin),
+        _ => None,
+    })
+    .filter(|admin| admin.timezone.is_some())
+    .max_by_key(|admin| admin.level)
+    .ok_or(model::BragiError::ObjectNotFound)?;
+
+    Ok(HttpResponse::Ok()
+        .set(CacheControl(vec![CacheDirective::MaxAge(
+            state.http_cache_duration,
+        )]))
+        .json(Timezone {
+            timezone: admin.timezone.unwrap_or_default(),
+            admin_id: admin.id,
+        }))
+}
@@ -1,12 +1,23 @@
+use crate::api_key_middleware::{ApiKeys, ApiKeysConfig};
 use crate::extractors::ActixError;
 use crate::routes::{
-    autocomplete, entry_point, features, post_autocomplete, reverse, status, JsonParams,
+    admin, admin_children, admins, autocomplete, entry_point, export, features, live, openapi,
+    post_autocomplete, ready, reverse, status, tiles, timezone, JsonParams,
 };
 use crate::{Args, Context};
+use actix_web::dev::Server;
+use actix_web::http::header::ContentEncoding;
 use actix_web::FromRequest;
 use actix_web::{middleware, web, App, HttpRequest, HttpServer};
+use futures::{Future, Stream};
+use mimir::rubber::TEMPLATE_VERSION;
+use slog_scope::{error, info, warn};
 use std::convert::TryInto;
+use std::fs::read_to_string;
+use std::thread;
+use std::time::Duration;
 use structopt::StructOpt;
+use tokio_signal::unix::{Signal, SIGINT, SIGTERM};
 
 pub fn default_404(req: HttpRequest) -> Result<web::Json<()>, ActixError> {
     Err(ActixError::RouteNotFound(req.path().to_string()))
@@ -41,26 +52,166 @@ pub fn configure_server(cfg: &mut web::ServiceConfig) {
         web::resource("/reverse")
             .name("reverse")
             .route(web::get().to(reverse)),
+    )
+    .service(
+        web::resource("/health/live")
+            .name("live")
+            .route(web::get().to(live)),
+    )
+    .service(
+        web::resource("/health/ready")
+            .name("ready")
+            .route(web::get().to(ready)),
+    )
+    .service(
+        web::resource("/timezone")
+            .name("timezone")
+            .route(web::get().to(timezone)),
+    )
+    .service(
+        web::resource("/admins")
+            .name("admins")
+            .route(web::get().to(admins)),
+    )
+    .service(
+        web::resource("/admins/{id}")
+            .name("admin")
+            .route(web::get().to(admin)),
+    )
+    .service(
+        web::resource("/admins/{id}/children")
+            .name("admin_children")
+            .route(web::get().to(admin_children)),
+    )
+    .service(
+        web::resource("/export/{type}/{dataset}")
+            .name("export")
+            .route(web::get().to(export)),
+    )
+    .service(
+        web::resource("/tiles/{layer}/{z}/{x}/{y}.mvt")
+            .name("tiles")
+            .route(web::get().to(tiles)),
+    )
+    .service(
+        web::resource("/openapi.json")
+            .name("openapi")
+            .route(web::get().to(openapi)),
+    );
+}
+
+pub fn make_api_keys(args: &Args) -> Result<ApiKeys, String> {
+    let config = match args.api_keys_file {
+        Some(ref file_path) => {
+            let content = read_to_string(&file_path)
+                .map_err(|e| format!("Failed to read `{}`: {}", file_path, e))?;
+            Some(
+                ApiKeysConfig::new(&content)
+                    .map_err(|err| format!("failed to parse `{}`: {}", file_path, err))?,
+            )
+        }
+        None => None,
+    };
+    Ok(ApiKeys::new(config))
+}
+
+/// Prefix of the bind addresses that are unix sockets
+const UNIX_SOCKET_PREFIX: &str = "unix:";
+
+pub fn build_cors(args: &Args) -> actix_cors::Cors {
+    let cors = args
+        .cors_allowed_origins
+        .iter()
+        .fold(actix_cors::Cors::new(), |cors, origin| {
+            cors.allowed_origin(origin)
+        });
+    // the default of actix-cors is to allow all the methods and headers
+    let cors = if args.cors_allowed_methods.is_empty() {
+        cors
+    } else {
+        cors.allowed_methods(args.cors_allowed_methods.iter().map(String::as_str))
+    };
+    if args.cors_allowed_headers.is_empty() {
+        cors
+    } else {
+        cors.allowed_headers(args.cors_allowed_headers.iter().map(String::as_str))
+    }
+}
+
+// Without compression, the `Identity` encoding is forced whatever the client accepts.
+pub fn build_compress(args: &Args) -> middleware::Compress {
+    let encoding = match args.compression.as_ref().map(String::as_str) {
+        Some("auto") => ContentEncoding::Auto,
+        Some("gzip") => ContentEncoding::Gzip,
+        Some("br") => ContentEncoding::Br,
+        _ => ContentEncoding::Identity,
+    };
+    middleware::Compress::new(encoding)
+}
+
+// On SIGTERM (or SIGINT), the server reports not ready for `delay` while still serving
+// the requests, then it stops accepting connections and waits for the in-flight requests.
+fn handle_termination(server: Server, ctx: Context, delay: Duration) {
+    let sigterm = Signal::new(SIGTERM).flatten_stream();
+    let sigint = Signal::new(SIGINT).flatten_stream();
+    actix_rt::spawn(
+        sigterm
+            .select(sigint)
+            .into_future()
+            .map_err(|(e, _)| error!("impossible to listen to the termination signals: {}", e))
+            .map(move |_| {
+                info!("termination signal received, draining the connections");
+                ctx.start_draining();
+                thread::spawn(move || {
+                    thread::sleep(delay);
+                    info!("stopping the server");
+                    let _ = server.stop(true).wait();
+                });
+            }),
     );
 }
 
 pub fn runserver() -> Result<(), String> {
     let args = Args::from_args();
     let ctx: Context = (&args).try_into()?;
+    for index in ctx.get_incompatible_indexes() {
+        warn!(
+            "the index {} is not built with the template version {}, it must be migrated with mimir_init",
+            index.index, TEMPLATE_VERSION
+        );
+    }
+    let api_keys = make_api_keys(&args)?;
     let prometheus = crate::prometheus_middleware::PrometheusMetrics::new("bragi", "/metrics");
-    HttpServer::new(move || {
+    let sys = actix_rt::System::new("bragi");
+    let server_ctx = ctx.clone();
+    let server_args = args.clone();
+    let mut server = HttpServer::new(move || {
         App::new()
-            .data(ctx.clone())
+            .data(server_ctx.clone())
             // NOTE: if some middlewares are added, don't forget to add them in the tests too (in BragiHandler::new)
-            .wrap(actix_cors::Cors::new().allowed_methods(vec!["GET"]))
+            .wrap(api_keys.clone())
+            .wrap(build_cors(&server_args))
+            .wrap(build_compress(&server_args))
             .wrap(prometheus.clone())
             .wrap(middleware::Logger::default())
             .configure(configure_server)
             .default_service(web::resource("").route(web::get().to(default_404)))
-    })
-    .bind(&args.bind)
-    .map_err(|e| format!("Failed to bind `{}`: {}", args.bind, e))?
-    .workers(args.nb_threads)
-    .run()
-    .map_err(|e| format!("run failed: {}", e))
+    });
+    for address in &args.bind {
+        server = if address.starts_with(UNIX_SOCKET_PREFIX) {
+            server.bind_uds(&address[UNIX_SOCKET_PREFIX.len()..])
+        } else {
+            server.bind(address)
+        }
+        .map_err(|e| format!("Failed to bind `{}`: {}", address, e))?;
+    }
+    let server = server
+        .workers(args.nb_threads)
+        // the signals are handled by `handle_termination`
+        .disable_signals()
+        .shutdown_timeout(args.shutdown_grace_period)
+        .system_exit()
+        .start();
+    handle_termination(server, ctx, Duration::from_secs(args.shutdown_delay));
+    sys.run().map_err(|e| format!("run failed: {}", e))
 }
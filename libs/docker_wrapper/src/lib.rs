@@ -27,7 +27,7 @@
 // IRC #navitia on freenode
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
-use mimir::rubber::Rubber;
+use mimir::rubber::{DocumentModel, Rubber};
 use slog_scope::{info, warn};
 use std::error::Error;
 use std::process::Command;
@@ -37,6 +37,8 @@ use std::time::Duration;
 /// Allowing to setup a docker, tear it down and to provide its address and port
 pub struct DockerWrapper {
     ip: String,
+    name: &'static str,
+    document_model: DocumentModel,
 }
 
 impl DockerWrapper {
@@ -44,10 +46,16 @@ impl DockerWrapper {
         format!("http://{}:9200", self.ip)
     }
 
-    fn setup(&mut self) -> Result<(), Box<dyn Error>> {
-        info!("Launching ES docker");
+    pub fn document_model(&self) -> DocumentModel {
+        self.document_model
+    }
+
+    fn setup(&mut self, image_args: &[&str]) -> Result<(), Box<dyn Error>> {
+        info!("Launching ES docker {:?}", image_args);
+        let name = format!("--name={}", self.name);
         let status = Command::new("docker")
-            .args(&["run", "-d", "--name=mimirsbrunn_tests", "elasticsearch:2"])
+            .args(&["run", "-d", name.as_str()])
+            .args(image_args)
             .status()?;
         if !status.success() {
             return Err(format!("`docker run` failed {}", &status).into());
@@ -58,7 +66,7 @@ impl DockerWrapper {
             .args(&[
                 "inspect",
                 "--format={{.NetworkSettings.IPAddress}}",
-                "mimirsbrunn_tests",
+                self.name,
             ])
             .output()?;
 
@@ -85,16 +93,49 @@ impl DockerWrapper {
         }
     }
 
-    pub fn new() -> Result<DockerWrapper, Box<dyn Error>> {
-        let mut wrapper = DockerWrapper { ip: "".to_string() };
-        wrapper.setup()?;
-        let rubber = Rubber::new_with_timeout(&wrapper.host(), Duration::from_secs(10)); // use a long timeout
+    fn start(
+        name: &'static str,
+        document_model: DocumentModel,
+        image_args: &[&str],
+    ) -> Result<DockerWrapper, Box<dyn Error>> {
+        let mut wrapper = DockerWrapper {
+            ip: "".to_string(),
+            name,
+            document_model,
+        };
+        wrapper.setup(image_args)?;
+        let rubber = Rubber::new_with_timeout(&wrapper.host(), Duration::from_secs(10)) // use a long timeout
+            .with_document_model(document_model);
         rubber.initialize_templates().unwrap();
         Ok(wrapper)
     }
+
+    /// An Elasticsearch 2, with the `typed` document model
+    pub fn new() -> Result<DockerWrapper, Box<dyn Error>> {
+        Self::start(
+            "mimirsbrunn_tests",
+            DocumentModel::Typed,
+            &["elasticsearch:2"],
+        )
+    }
+
+    /// An Elasticsearch 7, with the `typeless` document model
+    pub fn new_es7() -> Result<DockerWrapper, Box<dyn Error>> {
+        Self::start(
+            "mimirsbrunn_tests_es7",
+            DocumentModel::Typeless,
+            &[
+                "-e",
+                "discovery.type=single-node",
+                "-e",
+                "ES_JAVA_OPTS=-Xms512m -Xmx512m",
+                "elasticsearch:7.10.1",
+            ],
+        )
+    }
 }
 
-fn docker_command(args: &[&'static str]) {
+fn docker_command(args: &[&str]) {
     info!("Running docker {:?}", args);
     let status = Command::new("docker").args(args).status();
     match status {
@@ -113,11 +154,12 @@ impl Drop for DockerWrapper {
             warn!(
                 "the docker won't be stoped at the end, you can debug it.
             Note: ES has been mapped to the port 9242 in you localhost
-            manually stop and rm the container mimirsbrunn_tests after debug"
+            manually stop and rm the container {} after debug",
+                self.name
             );
             return;
         }
-        docker_command(&["stop", "mimirsbrunn_tests"]);
-        docker_command(&["rm", "mimirsbrunn_tests"]);
+        docker_command(&["stop", self.name]);
+        docker_command(&["rm", self.name]);
     }
 }
@@ -0,0 +1,282 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use super::{MemoryBackend, SearchBackend};
+use crate::objects::{Coord, MimirObject, Place};
+use crate::rubber::{
+    get_main_type_and_dataset_index, Document, DocumentModel, IndexSettings, PublishChecks,
+    TypedIndex,
+};
+use failure::Error;
+use serde_json::json;
+use slog_scope::{debug, info};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// A backend writing the bulk requests of the indexed documents (action and document lines)
+/// to a NDJSON output instead of sending them to Elasticsearch.
+///
+/// Nothing is published: the aliases, the deletions and the closing of indexes are ignored.
+/// The searches, like the lookup of the admins, are made in a `MemoryBackend`.
+pub struct FileBackend<W: Write> {
+    output: W,
+    document_model: DocumentModel,
+    nb_documents: BTreeMap<String, u64>,
+    lookup: MemoryBackend,
+    publish_checks: PublishChecks,
+}
+
+impl<W: Write> FileBackend<W> {
+    pub fn new(output: W) -> Self {
+        FileBackend {
+            output,
+            document_model: DocumentModel::default(),
+            nb_documents: BTreeMap::new(),
+            lookup: MemoryBackend::new(),
+            publish_checks: PublishChecks::default(),
+        }
+    }
+
+    /// Change the document model, which gives the `_type` of the action lines
+    pub fn with_document_model(mut self, document_model: DocumentModel) -> Self {
+        self.document_model = document_model;
+        self
+    }
+
+    /// Change the backend used for the searches, which also gives the namespace
+    pub fn with_lookup(mut self, lookup: MemoryBackend) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.output.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn write_line(&mut self, line: &impl serde::Serialize) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.output, line)?;
+        self.output.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<W: Write> SearchBackend for FileBackend<W> {
+    fn create_index(&mut self, name: &str, _index_settings: &IndexSettings) -> Result<(), Error> {
+        debug!("creating index {}", name);
+        self.nb_documents.insert(name.to_owned(), 0);
+        Ok(())
+    }
+
+    fn delete_index(&mut self, _index: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn refresh_index(&mut self, _index: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let mapping_type = self.document_model.mapping_type(T::doc_type());
+        let mut nb = 0;
+        for object in iter {
+            let mut action = json!({ "_index": index.name(), "_type": mapping_type });
+            if let Some(id) = object.es_id() {
+                action["_id"] = id.into();
+            }
+            self.write_line(&json!({ "index": action }))?;
+            self.write_line(&Document {
+                doc_type: T::doc_type(),
+                object,
+            })?;
+            nb += 1;
+        }
+        *self
+            .nb_documents
+            .entry(index.name().to_owned())
+            .or_insert(0) += nb as u64;
+        info!("{} {} written for {}", nb, T::doc_type(), index.name());
+        Ok(nb)
+    }
+
+    fn close_index(&mut self, _index: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn open_index(&mut self, _index: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_closed_indexes(&mut self, _base_index: &str) -> Result<Vec<String>, Error> {
+        Ok(vec![])
+    }
+
+    fn nb_kept_indexes(&self) -> usize {
+        0
+    }
+
+    fn publish_checks(&self) -> &PublishChecks {
+        &self.publish_checks
+    }
+
+    fn namespace(&self) -> &str {
+        self.lookup.namespace()
+    }
+
+    fn count_documents(&mut self, index: &str) -> Result<u64, Error> {
+        match self.nb_documents.get(index) {
+            Some(nb) => Ok(*nb),
+            None => self.lookup.count_documents(index),
+        }
+    }
+
+    fn get_document_hashes(&mut self, index: &str) -> Result<HashMap<String, u64>, Error> {
+        self.lookup.get_document_hashes(index)
+    }
+
+    fn delete_documents<T: MimirObject>(
+        &mut self,
+        index: &TypedIndex<T>,
+        ids: &[String],
+    ) -> Result<usize, Error> {
+        let mapping_type = self.document_model.mapping_type(T::doc_type());
+        for id in ids {
+            self.write_line(&json!({
+                "delete": { "_index": index.name(), "_type": mapping_type, "_id": id }
+            }))?;
+        }
+        Ok(ids.len())
+    }
+
+    fn update_aliases(
+        &mut self,
+        _add: &[(String, String)],
+        _remove: &[(String, String)],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_all_aliased_index(
+        &mut self,
+        base_index: &str,
+    ) -> Result<BTreeMap<String, Vec<String>>, Error> {
+        self.lookup.get_all_aliased_index(base_index)
+    }
+
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug,
+    {
+        self.lookup.get_all_objects_from_index(index)
+    }
+
+    fn search_text(
+        &mut self,
+        indexes: &[&str],
+        text: &str,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        self.lookup.search_text(indexes, text, limit)
+    }
+
+    fn search_geo_distance(
+        &mut self,
+        indexes: &[&str],
+        coord: &Coord,
+        distance: f64,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        self.lookup
+            .search_geo_distance(indexes, coord, distance, limit)
+    }
+
+    fn search_ids(&mut self, indexes: &[&str], ids: &[&str]) -> Result<Vec<Place>, Error> {
+        self.lookup.search_ids(indexes, ids)
+    }
+
+    /// The documents are written for an index named after the dataset, without the date
+    /// of the import, so that the outputs of two imports can be compared
+    fn make_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+    ) -> Result<TypedIndex<T>, Error> {
+        let index_name = get_main_type_and_dataset_index::<T>(self.namespace(), dataset);
+        self.create_index(&index_name, index_settings)?;
+        Ok(TypedIndex::new(index_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stop;
+
+    #[test]
+    pub fn test_bulk_lines() {
+        let stop = Stop {
+            id: "stop:1".into(),
+            label: "Gare de Lyon".into(),
+            name: "Gare de Lyon".into(),
+            coord: Coord::new(2.373, 48.844),
+            ..Default::default()
+        };
+        let mut backend = FileBackend::new(vec![]).with_document_model(DocumentModel::Typeless);
+        let index_settings = IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        };
+        let nb = backend
+            .public_index("fr", &index_settings, vec![stop].into_iter())
+            .unwrap();
+        assert_eq!(nb, 1);
+
+        let output = String::from_utf8(backend.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            json!({"index": {"_index": "munin_stop_fr", "_type": "_doc", "_id": "stop:1"}})
+        );
+        assert_eq!(lines[1]["type"], "stop");
+        assert_eq!(lines[1]["id"], "stop:1");
+        assert_eq!(lines[1]["label"], "Gare de Lyon");
+    }
+}
@@ -0,0 +1,807 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use super::{content_hash, SearchBackend};
+use crate::objects::{Coord, MimirObject, Place};
+use crate::rubber::{make_place, IndexSettings, PublishChecks, TypedIndex, DEFAULT_NAMESPACE};
+use failure::{bail, format_err, Error};
+use geo::algorithm::haversine_distance::HaversineDistance;
+use slog_scope::debug;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Clone, Debug)]
+struct Document {
+    id: Option<String>,
+    doc_type: &'static str,
+    source: serde_json::Value,
+}
+
+impl Document {
+    fn place(&self) -> Option<Place> {
+        make_place(
+            self.doc_type.to_owned(),
+            Some(Box::new(self.source.clone())),
+            None,
+        )
+    }
+
+    fn weight(&self) -> f64 {
+        self.source
+            .get("weight")
+            .and_then(|w| w.as_f64())
+            .unwrap_or(0.)
+    }
+}
+
+/// A `SearchBackend` keeping the indexes and the aliases in memory.
+///
+/// It behaves like Elasticsearch for the indexes and the aliases (an alias is
+/// resolved to its indexes when it is added to another alias), but the searches
+/// only approximate the Elasticsearch queries: it is meant for the tests, not
+/// for the relevance.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    indexes: BTreeMap<String, Vec<Document>>,
+    aliases: BTreeMap<String, BTreeSet<String>>,
+    closed: BTreeSet<String>,
+    nb_kept_indexes: usize,
+    publish_checks: PublishChecks,
+    // `DEFAULT_NAMESPACE` if not given
+    namespace: Option<String>,
+}
+
+// the words of a label or of a query, to compare them
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change the number of previous indexes kept when a new index is published
+    pub fn with_nb_kept_indexes(mut self, value: usize) -> Self {
+        self.nb_kept_indexes = value;
+        self
+    }
+
+    /// Change the checks made on the new indexes before publishing them
+    pub fn with_publish_checks(mut self, value: PublishChecks) -> Self {
+        self.publish_checks = value;
+        self
+    }
+
+    /// Change the prefix of the indexes and aliases
+    pub fn with_namespace(mut self, value: &str) -> Self {
+        self.namespace = Some(value.to_owned());
+        self
+    }
+
+    /// All the indexes and aliases, with the aliases of each index
+    pub fn indexes(&self) -> BTreeMap<String, Vec<String>> {
+        self.indexes
+            .keys()
+            .map(|index| (index.clone(), self.aliases_of(index)))
+            .collect()
+    }
+
+    fn aliases_of(&self, index: &str) -> Vec<String> {
+        self.aliases
+            .iter()
+            .filter(|(_, indexes)| indexes.contains(index))
+            .map(|(alias, _)| alias.clone())
+            .collect()
+    }
+
+    // the open indexes behind a name: an index, an alias or a pattern ending with '*'
+    fn resolve(&self, name: &str) -> BTreeSet<String> {
+        let matches = |candidate: &str| match name.strip_suffix('*') {
+            Some(prefix) => candidate.starts_with(prefix),
+            None => candidate == name,
+        };
+        let indexes = self.indexes.keys().filter(|i| matches(i)).cloned();
+        let aliased = self
+            .aliases
+            .iter()
+            .filter(|(alias, _)| matches(alias))
+            .flat_map(|(_, indexes)| indexes.iter().cloned());
+        indexes
+            .chain(aliased)
+            .filter(|i| !self.closed.contains(i))
+            .collect()
+    }
+
+    fn check_index(&self, index: &str) -> Result<(), Error> {
+        if !self.indexes.contains_key(index) {
+            bail!("no such index {}", index);
+        }
+        Ok(())
+    }
+
+    fn documents<'a>(&'a self, indexes: &[&str]) -> impl Iterator<Item = &'a Document> + 'a {
+        let names: BTreeSet<String> = indexes.iter().flat_map(|i| self.resolve(i)).collect();
+        names
+            .into_iter()
+            .filter_map(move |name| self.indexes.get(&name))
+            .flat_map(|documents| documents.iter())
+    }
+}
+
+impl SearchBackend for MemoryBackend {
+    fn create_index(&mut self, name: &str, _index_settings: &IndexSettings) -> Result<(), Error> {
+        debug!("creating index {}", name);
+        if self.indexes.contains_key(name) {
+            bail!("cannot create index {}: it already exists", name);
+        }
+        self.indexes.insert(name.to_owned(), vec![]);
+        Ok(())
+    }
+
+    fn delete_index(&mut self, index: &str) -> Result<(), Error> {
+        debug!("deleting index {}", index);
+        if self.indexes.remove(index).is_none() {
+            bail!("Error deleting index {}", index);
+        }
+        self.closed.remove(index);
+        for indexes in self.aliases.values_mut() {
+            indexes.remove(index);
+        }
+        self.aliases.retain(|_, indexes| !indexes.is_empty());
+        Ok(())
+    }
+
+    fn close_index(&mut self, index: &str) -> Result<(), Error> {
+        debug!("closing index {}", index);
+        self.check_index(index)?;
+        self.closed.insert(index.to_owned());
+        Ok(())
+    }
+
+    fn open_index(&mut self, index: &str) -> Result<(), Error> {
+        debug!("opening index {}", index);
+        self.check_index(index)?;
+        self.closed.remove(index);
+        Ok(())
+    }
+
+    fn get_closed_indexes(&mut self, base_index: &str) -> Result<Vec<String>, Error> {
+        let prefix = format!("{}_", base_index);
+        Ok(self
+            .closed
+            .iter()
+            .filter(|i| i.starts_with(&prefix))
+            .cloned()
+            .collect())
+    }
+
+    fn nb_kept_indexes(&self) -> usize {
+        self.nb_kept_indexes
+    }
+
+    fn publish_checks(&self) -> &PublishChecks {
+        &self.publish_checks
+    }
+
+    fn namespace(&self) -> &str {
+        self.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE)
+    }
+
+    fn count_documents(&mut self, index: &str) -> Result<u64, Error> {
+        Ok(self.documents(&[index]).count() as u64)
+    }
+
+    fn get_document_hashes(&mut self, index: &str) -> Result<HashMap<String, u64>, Error> {
+        Ok(self
+            .documents(&[index])
+            .filter_map(|d| d.id.clone().map(|id| (id, content_hash(&d.source))))
+            .collect())
+    }
+
+    fn delete_documents<T: MimirObject>(
+        &mut self,
+        index: &TypedIndex<T>,
+        ids: &[String],
+    ) -> Result<usize, Error> {
+        let documents = self
+            .indexes
+            .get_mut(index.name())
+            .ok_or_else(|| format_err!("no such index {}", index.name()))?;
+        let nb_documents = documents.len();
+        documents.retain(|d| d.id.as_ref().map_or(true, |id| !ids.contains(id)));
+        Ok(nb_documents - documents.len())
+    }
+
+    fn refresh_index(&mut self, _index: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let documents = self
+            .indexes
+            .get_mut(index.name())
+            .ok_or_else(|| format_err!("no such index {}", index.name()))?;
+        let mut nb = 0;
+        for object in iter {
+            let mut source = serde_json::to_value(&object)?;
+            if let Some(fields) = source.as_object_mut() {
+                fields.insert("type".to_owned(), T::doc_type().into());
+            }
+            let document = Document {
+                id: object.es_id(),
+                doc_type: T::doc_type(),
+                source,
+            };
+            // like Elasticsearch, a document replaces the document with the same id
+            match documents
+                .iter_mut()
+                .find(|d| d.id.is_some() && d.id == document.id)
+            {
+                Some(existing) => *existing = document,
+                None => documents.push(document),
+            }
+            nb += 1;
+        }
+        Ok(nb)
+    }
+
+    fn update_aliases(
+        &mut self,
+        add: &[(String, String)],
+        remove: &[(String, String)],
+    ) -> Result<(), Error> {
+        debug!("adding aliases {:?}, removing {:?}", add, remove);
+        // like Elasticsearch, the names are resolved before any change
+        let mut added = vec![];
+        for (alias, name) in add {
+            let indexes = self.resolve(name);
+            if indexes.is_empty() {
+                bail!(
+                    "failed to post aliases for {}: no such index {}",
+                    alias,
+                    name
+                );
+            }
+            added.extend(indexes.into_iter().map(|i| (alias.clone(), i)));
+        }
+        let removed: Vec<(String, String)> = remove
+            .iter()
+            .flat_map(|(alias, name)| {
+                self.resolve(name)
+                    .into_iter()
+                    .map(move |i| (alias.clone(), i))
+            })
+            .collect();
+
+        for (alias, index) in added {
+            self.aliases.entry(alias).or_default().insert(index);
+        }
+        for (alias, index) in removed {
+            if let Some(indexes) = self.aliases.get_mut(&alias) {
+                indexes.remove(&index);
+            }
+        }
+        self.aliases.retain(|_, indexes| !indexes.is_empty());
+        Ok(())
+    }
+
+    fn get_all_aliased_index(
+        &mut self,
+        base_index: &str,
+    ) -> Result<BTreeMap<String, Vec<String>>, Error> {
+        Ok(self
+            .resolve(&format!("{}_*", base_index))
+            .into_iter()
+            .map(|index| {
+                let aliases = self.aliases_of(&index);
+                (index, aliases)
+            })
+            .collect())
+    }
+
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug,
+    {
+        self.documents(&[index])
+            .filter(|d| d.doc_type == T::doc_type())
+            .map(|d| Ok(serde_json::from_value(d.source.clone())?))
+            .collect()
+    }
+
+    /// The places whose label has a word starting with each word of `text`,
+    /// the places with the most exact words first, then the heaviest
+    fn search_text(
+        &mut self,
+        indexes: &[&str],
+        text: &str,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        let query = tokens(text);
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut places: Vec<(usize, f64, Place)> = self
+            .documents(indexes)
+            .filter_map(|d| {
+                let place = d.place()?;
+                let label = tokens(place.label());
+                let is_prefix = query
+                    .iter()
+                    .all(|q| label.iter().any(|l| l.starts_with(q.as_str())));
+                if !is_prefix {
+                    return None;
+                }
+                let nb_exact = query.iter().filter(|q| label.contains(q)).count();
+                Some((nb_exact, d.weight(), place))
+            })
+            .collect();
+        places.sort_by(|(exact1, weight1, _), (exact2, weight2, _)| {
+            exact2.cmp(exact1).then(
+                weight2
+                    .partial_cmp(weight1)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        Ok(places
+            .into_iter()
+            .take(limit as usize)
+            .map(|(nb_exact, weight, mut place)| {
+                place.set_score(nb_exact as f64 + weight);
+                place
+            })
+            .collect())
+    }
+
+    fn search_geo_distance(
+        &mut self,
+        indexes: &[&str],
+        coord: &Coord,
+        distance: f64,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        let point: geo_types::Point<f64> = coord.0.into();
+        let mut places: Vec<(f64, Place)> = self
+            .documents(indexes)
+            .filter_map(|d| d.place())
+            .map(|place| (point.haversine_distance(&place.coord().0.into()), place))
+            .filter(|(d, _)| *d <= distance)
+            .collect();
+        places.sort_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(places
+            .into_iter()
+            .take(limit as usize)
+            .map(|(d, mut place)| {
+                place.set_distance(d as u32);
+                place
+            })
+            .collect())
+    }
+
+    fn search_ids(&mut self, indexes: &[&str], ids: &[&str]) -> Result<Vec<Place>, Error> {
+        Ok(self
+            .documents(indexes)
+            .filter(|d| d.id.as_deref().map_or(false, |id| ids.contains(&id)))
+            .filter_map(|d| d.place())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backend::UpdateSummary;
+    use crate::rubber::{get_main_type_and_dataset_index, IndexVisibility};
+    use crate::Stop;
+
+    fn index_settings() -> IndexSettings {
+        IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        }
+    }
+
+    fn stop(id: &str, label: &str, lon: f64, lat: f64, weight: f64) -> Stop {
+        Stop {
+            id: id.to_owned(),
+            label: label.to_owned(),
+            name: label.to_owned(),
+            coord: Coord::new(lon, lat),
+            weight,
+            ..Default::default()
+        }
+    }
+
+    fn ids(places: &[Place]) -> Vec<&str> {
+        places.iter().map(|p| p.id()).collect()
+    }
+
+    #[test]
+    pub fn test_publish_replaces_the_previous_index() {
+        let mut backend = MemoryBackend::new();
+        let stops = vec![stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.)];
+        assert_eq!(
+            backend
+                .public_index("fr", &index_settings(), stops.into_iter())
+                .unwrap(),
+            1
+        );
+        let stops = vec![
+            stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.),
+            stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+        ];
+        backend
+            .public_index("fr", &index_settings(), stops.into_iter())
+            .unwrap();
+
+        // only the last index is kept, with all the aliases
+        let indexes = backend.indexes();
+        assert_eq!(indexes.len(), 1);
+        let aliases = indexes.values().next().unwrap();
+        assert_eq!(
+            aliases,
+            &vec!["munin", "munin_stop", "munin_stop_fr"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        );
+
+        let stops: Vec<Stop> = backend.get_all_objects_from_index("munin_stop").unwrap();
+        assert_eq!(stops.len(), 2);
+        let stops: Vec<Stop> = backend
+            .get_all_objects_from_index(&get_main_type_and_dataset_index::<Stop>("munin", "fr"))
+            .unwrap();
+        assert_eq!(stops.len(), 2);
+    }
+
+    #[test]
+    pub fn test_namespace() {
+        let mut backend = MemoryBackend::new().with_namespace("staging");
+        let stops = vec![stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.)];
+        backend
+            .public_index("fr", &index_settings(), stops.into_iter())
+            .unwrap();
+
+        let indexes = backend.indexes();
+        assert_eq!(indexes.len(), 1);
+        let (name, aliases) = indexes.iter().next().unwrap();
+        assert!(name.starts_with("staging_stop_fr_"));
+        assert_eq!(
+            aliases,
+            &vec!["staging", "staging_stop", "staging_stop_fr"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    pub fn test_private_index() {
+        let mut backend = MemoryBackend::new();
+        let index = backend
+            .make_index::<Stop>("private", &index_settings())
+            .unwrap();
+        backend
+            .bulk_index(
+                &index,
+                std::iter::once(stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.)),
+            )
+            .unwrap();
+        backend
+            .publish_index("private", index, IndexVisibility::Private)
+            .unwrap();
+
+        assert!(backend
+            .search_ids(&["munin"], &["stop:1"])
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            ids(&backend
+                .search_ids(&["munin_stop_private"], &["stop:1"])
+                .unwrap()),
+            vec!["stop:1"]
+        );
+    }
+
+    #[test]
+    pub fn test_search_text() {
+        let mut backend = MemoryBackend::new();
+        let stops = vec![
+            stop("stop:1", "Gare de Lyon", 2.373, 48.844, 0.5),
+            stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+            stop("stop:3", "Les Lyonnais", 4.859, 45.760, 2.),
+        ];
+        backend
+            .public_index("fr", &index_settings(), stops.into_iter())
+            .unwrap();
+
+        // the heaviest place first when the words match the same way
+        assert_eq!(
+            ids(&backend.search_text(&["munin"], "gare", 10).unwrap()),
+            vec!["stop:2", "stop:1"]
+        );
+        // the exact words first, even if lighter
+        assert_eq!(
+            ids(&backend.search_text(&["munin"], "lyon", 10).unwrap()),
+            vec!["stop:1", "stop:3"]
+        );
+        assert_eq!(
+            ids(&backend.search_text(&["munin"], "Gare Ly", 10).unwrap()),
+            vec!["stop:1"]
+        );
+        assert_eq!(
+            ids(&backend.search_text(&["munin"], "gare", 1).unwrap()),
+            vec!["stop:2"]
+        );
+        assert!(backend
+            .search_text(&["munin_addr"], "gare", 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    pub fn test_search_geo_distance() {
+        let mut backend = MemoryBackend::new();
+        let stops = vec![
+            stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.),
+            stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+            stop("stop:3", "Lyon Part-Dieu", 4.859, 45.760, 1.),
+        ];
+        backend
+            .public_index("fr", &index_settings(), stops.into_iter())
+            .unwrap();
+
+        let coord = Coord::new(2.356, 48.879);
+        let places = backend
+            .search_geo_distance(&["munin_*"], &coord, 10_000., 10)
+            .unwrap();
+        assert_eq!(ids(&places), vec!["stop:2", "stop:1"]);
+        assert!(places[0].distance().unwrap() < 200);
+    }
+
+    #[test]
+    pub fn test_rollback() {
+        let mut backend = MemoryBackend::new().with_nb_kept_indexes(1);
+        let imports = vec![
+            vec![stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.)],
+            vec![
+                stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.),
+                stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+            ],
+            vec![stop("stop:3", "Gare de l'Est", 2.359, 48.876, 1.)],
+        ];
+        for stops in imports {
+            backend
+                .public_index("fr", &index_settings(), stops.into_iter())
+                .unwrap();
+        }
+        let dataset_index = get_main_type_and_dataset_index::<Stop>("munin", "fr");
+        let indexes: Vec<String> = backend.indexes().keys().cloned().collect();
+        // the first index has been deleted, the second one is kept but not searched
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(
+            backend.get_closed_indexes(&dataset_index).unwrap(),
+            vec![indexes[0].clone()]
+        );
+        let all_stops = ["stop:1", "stop:2", "stop:3"];
+        assert_eq!(
+            ids(&backend.search_ids(&["munin"], &all_stops).unwrap()),
+            vec!["stop:3"]
+        );
+
+        assert!(backend
+            .rollback_index::<Stop>("fr", Some("munin_stop_fr_unknown"))
+            .is_err());
+        assert_eq!(
+            backend.rollback_index::<Stop>("fr", None).unwrap(),
+            indexes[0]
+        );
+        assert_eq!(
+            ids(&backend.search_ids(&["munin"], &all_stops).unwrap()),
+            vec!["stop:1", "stop:2"]
+        );
+        assert_eq!(
+            backend.indexes()[&indexes[0]],
+            vec!["munin", "munin_stop", "munin_stop_fr"]
+        );
+        // the replaced index is kept, to go back to it
+        assert_eq!(
+            backend.get_closed_indexes(&dataset_index).unwrap(),
+            vec![indexes[1].clone()]
+        );
+        backend
+            .rollback_index::<Stop>("fr", Some(&indexes[1]))
+            .unwrap();
+        assert_eq!(
+            ids(&backend.search_ids(&["munin_stop_fr"], &all_stops).unwrap()),
+            vec!["stop:3"]
+        );
+    }
+
+    #[test]
+    pub fn test_dataset_prefix() {
+        let mut backend = MemoryBackend::new().with_nb_kept_indexes(1);
+        let import = |backend: &mut MemoryBackend, dataset: &str, id: &str| {
+            let stops = vec![stop(id, "Gare de Lyon", 2.373, 48.844, 1.)];
+            backend
+                .public_index(dataset, &index_settings(), stops.into_iter())
+                .unwrap();
+        };
+        import(&mut backend, "fr_north", "stop:north:1");
+        import(&mut backend, "fr_north", "stop:north:2");
+        import(&mut backend, "fr", "stop:1");
+        import(&mut backend, "fr", "stop:2");
+
+        // publishing `fr` neither deletes nor keeps the indexes of `fr_north`
+        let north_index = get_main_type_and_dataset_index::<Stop>("munin", "fr_north");
+        let north_kept = backend.get_closed_indexes(&north_index).unwrap();
+        assert_eq!(north_kept.len(), 1);
+        assert_eq!(
+            ids(&backend
+                .search_ids(&[&north_index], &["stop:north:2"])
+                .unwrap()),
+            vec!["stop:north:2"]
+        );
+        assert_eq!(
+            ids(&backend
+                .search_ids(&["munin"], &["stop:2", "stop:north:2"])
+                .unwrap()),
+            vec!["stop:2", "stop:north:2"]
+        );
+
+        // the rollback of `fr` does not take the kept index of `fr_north`
+        let index = backend.rollback_index::<Stop>("fr", None).unwrap();
+        assert!(!north_kept.contains(&index));
+        assert_eq!(
+            ids(&backend
+                .search_ids(&["munin_stop_fr"], &["stop:1", "stop:north:1"])
+                .unwrap()),
+            vec!["stop:1"]
+        );
+        assert_eq!(
+            backend.get_closed_indexes(&north_index).unwrap(),
+            north_kept
+        );
+    }
+
+    #[test]
+    pub fn test_publish_checks() {
+        let checks = PublishChecks {
+            min_documents: 2,
+            max_drop: Some(0.5),
+            canaries: vec!["gare de lyon=stop:1".parse().unwrap()],
+        };
+        let mut backend = MemoryBackend::new().with_publish_checks(checks);
+        let stops = vec![
+            stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.),
+            stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+            stop("stop:3", "Gare de l'Est", 2.359, 48.876, 1.),
+            stop("stop:4", "Gare Saint-Lazare", 2.325, 48.876, 1.),
+        ];
+        backend
+            .public_index("fr", &index_settings(), stops.clone().into_iter())
+            .unwrap();
+
+        let err = backend
+            .public_index("fr", &index_settings(), stops[1..2].iter().cloned())
+            .unwrap_err();
+        let causes: Vec<String> = err.iter_chain().map(|c| c.to_string()).collect();
+        assert!(causes[0].starts_with("the index munin_stop_fr_"));
+        assert!(causes[1].starts_with(
+            "1 documents, less than the minimum of 2, \
+             1 documents, 75.0% less than the 4 documents of munin_stop_fr_"
+        ));
+        assert!(causes[1].ends_with(", 'gare de lyon' does not return stop:1"));
+        // the current index is still published
+        assert_eq!(
+            backend
+                .search_ids(&["munin"], &["stop:1", "stop:2", "stop:3", "stop:4"])
+                .unwrap()
+                .len(),
+            4
+        );
+
+        // a small drop is accepted
+        backend
+            .public_index("fr", &index_settings(), stops[0..3].iter().cloned())
+            .unwrap();
+        assert_eq!(
+            ids(&backend
+                .search_ids(&["munin"], &["stop:1", "stop:2", "stop:3", "stop:4"])
+                .unwrap()),
+            vec!["stop:1", "stop:2", "stop:3"]
+        );
+    }
+
+    #[test]
+    pub fn test_update_index() {
+        let mut backend = MemoryBackend::new();
+        let err = backend
+            .update_index(
+                "fr",
+                vec![stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.)].into_iter(),
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no index published for munin_stop_fr");
+
+        let stops = vec![
+            stop("stop:1", "Gare de Lyon", 2.373, 48.844, 1.),
+            stop("stop:2", "Gare du Nord", 2.355, 48.880, 1.),
+            stop("stop:3", "Gare de l'Est", 2.359, 48.876, 1.),
+        ];
+        backend
+            .public_index("fr", &index_settings(), stops.clone().into_iter())
+            .unwrap();
+        let indexes = backend.indexes();
+
+        let update = vec![
+            stops[0].clone(),
+            stop("stop:2", "Paris Nord", 2.355, 48.880, 1.),
+            stop("stop:4", "Gare Saint-Lazare", 2.325, 48.876, 1.),
+        ];
+        let summary = backend.update_index("fr", update.into_iter()).unwrap();
+        assert_eq!(
+            summary,
+            UpdateSummary {
+                nb_unchanged: 1,
+                nb_upserted: 2,
+                nb_deleted: 1,
+            }
+        );
+        // the index is updated in place
+        assert_eq!(backend.indexes(), indexes);
+        let places = backend
+            .search_ids(&["munin"], &["stop:1", "stop:2", "stop:3", "stop:4"])
+            .unwrap();
+        assert_eq!(ids(&places), vec!["stop:1", "stop:2", "stop:4"]);
+        assert_eq!(places[1].label(), "Paris Nord");
+
+        // the publish checks are made before the update
+        let mut backend = backend.with_publish_checks(PublishChecks {
+            min_documents: 2,
+            ..Default::default()
+        });
+        let err = backend
+            .update_index("fr", stops[0..1].iter().cloned())
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with(": 1 documents, less than the minimum of 2"));
+        assert_eq!(backend.count_documents("munin_stop_fr").unwrap(), 3);
+    }
+}
@@ -0,0 +1,759 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! The storage operations needed by mimir, independently of Elasticsearch.
+//!
+//! `Rubber` implements them with Elasticsearch, `MemoryBackend` keeps everything
+//! in memory so that the importers can be tested without a running Elasticsearch,
+//! and `FileBackend` writes the bulk requests of an import to a file.
+//!
+//! Only the importers and the admin tools use this trait: the queries of Bragi are built
+//! for the Elasticsearch scoring and stay on `Rubber`.
+
+pub mod file;
+pub mod memory;
+
+pub use self::file::FileBackend;
+pub use self::memory::MemoryBackend;
+
+use crate::objects::{Addr, Admin, Coord, MimirObject, Place, Street};
+use crate::rubber::{
+    get_date_index_name, get_geo_data_index, get_main_type_and_dataset_index, get_main_type_index,
+    split_date_index_name, IndexSettings, IndexVisibility, PublishChecks, Rubber, TypedIndex,
+};
+use failure::{bail, format_err, Error, ResultExt};
+use rs_es::query::Query;
+use rs_es::units as rs_u;
+use slog_scope::{debug, info};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// Changes made by `SearchBackend::update_index`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateSummary {
+    pub nb_unchanged: usize,
+    pub nb_upserted: usize,
+    pub nb_deleted: usize,
+}
+
+/// Hash of a document as serialized by the importers, to find the documents changed by an
+/// update. The `type` field added when the document is indexed is ignored
+pub fn content_hash(source: &serde_json::Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match source.as_object() {
+        Some(fields) => {
+            for (key, value) in fields.iter().filter(|(key, _)| key.as_str() != "type") {
+                key.hash(&mut hasher);
+                value.to_string().hash(&mut hasher);
+            }
+        }
+        None => source.to_string().hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+/// Is `index` an index of the dataset of `dataset_index` (`munin_stop_fr`): the patterns
+/// `munin_stop_fr_*` also match the indexes of the dataset `fr_north`
+fn is_dataset_index(index: &str, dataset_index: &str) -> bool {
+    split_date_index_name(index).map_or(false, |(base, _)| base == dataset_index)
+}
+
+/// The failures of the `min_documents` and `max_drop` checks for an index of `nb_documents`
+/// documents, `current` being the index it replaces with its number of documents
+fn check_counts(
+    checks: &PublishChecks,
+    nb_documents: u64,
+    current: Option<(&str, u64)>,
+) -> Vec<String> {
+    let mut failures = vec![];
+    if nb_documents < checks.min_documents {
+        failures.push(format!(
+            "{} documents, less than the minimum of {}",
+            nb_documents, checks.min_documents
+        ));
+    }
+    if let (Some(max_drop), Some((current_index, nb_current_documents))) =
+        (checks.max_drop, current)
+    {
+        if nb_current_documents > 0 {
+            let drop = 1. - nb_documents as f64 / nb_current_documents as f64;
+            if drop > max_drop {
+                failures.push(format!(
+                    "{} documents, {:.1}% less than the {} documents of {}",
+                    nb_documents,
+                    drop * 100.,
+                    nb_current_documents,
+                    current_index
+                ));
+            }
+        }
+    }
+    failures
+}
+
+pub trait SearchBackend {
+    /// Create an empty index, with the settings for bulk loading if the backend has some
+    fn create_index(&mut self, name: &str, index_settings: &IndexSettings) -> Result<(), Error>;
+
+    fn delete_index(&mut self, index: &str) -> Result<(), Error>;
+
+    /// Make all the documents added to the index visible to the searches
+    fn refresh_index(&mut self, index: &str) -> Result<(), Error>;
+
+    /// Give its final settings to an index created with the settings for bulk loading,
+    /// nothing to do for the backends without such settings
+    fn finish_index(&mut self, _index: &str, _index_settings: &IndexSettings) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Add all the elements of `iter` into the index, return the number of elements added
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>;
+
+    /// Close an index: it is kept but can no longer be searched
+    fn close_index(&mut self, index: &str) -> Result<(), Error>;
+
+    fn open_index(&mut self, index: &str) -> Result<(), Error>;
+
+    /// The closed indexes whose name starts with `base_index`
+    fn get_closed_indexes(&mut self, base_index: &str) -> Result<Vec<String>, Error>;
+
+    /// Number of previous indexes kept (closed) for each doc_type and dataset
+    /// when a new index is published, to be able to roll back to them
+    fn nb_kept_indexes(&self) -> usize;
+
+    /// Checks made on a new index before publishing it
+    fn publish_checks(&self) -> &PublishChecks;
+
+    /// Prefix of the indexes and aliases (`munin`)
+    fn namespace(&self) -> &str;
+
+    fn count_documents(&mut self, index: &str) -> Result<u64, Error>;
+
+    /// The ids of all the documents of an index, with the hash of their content
+    /// (see `content_hash`)
+    fn get_document_hashes(&mut self, index: &str) -> Result<HashMap<String, u64>, Error>;
+
+    /// Delete documents of an index by id, return the number of documents deleted
+    fn delete_documents<T: MimirObject>(
+        &mut self,
+        index: &TypedIndex<T>,
+        ids: &[String],
+    ) -> Result<usize, Error>;
+
+    /// Apply all the changes at once: the `(alias, index)` pairs of `add` are added
+    /// and the ones of `remove` are removed
+    fn update_aliases(
+        &mut self,
+        add: &[(String, String)],
+        remove: &[(String, String)],
+    ) -> Result<(), Error>;
+
+    /// add a list of new indexes to the alias
+    /// remove a list of indexes from the alias
+    fn alias(&mut self, alias: &str, add: &[String], remove: &[String]) -> Result<(), Error> {
+        let pairs = |indexes: &[String]| {
+            indexes
+                .iter()
+                .map(|index| (alias.to_owned(), index.clone()))
+                .collect::<Vec<_>>()
+        };
+        self.update_aliases(&pairs(add), &pairs(remove))
+    }
+
+    /// get all aliases for a doc_type/dataset
+    /// return a map with each index as key and all their aliases
+    fn get_all_aliased_index(
+        &mut self,
+        base_index: &str,
+    ) -> Result<BTreeMap<String, Vec<String>>, Error>;
+
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug;
+
+    /// Search the places whose label matches all the words of `text`
+    fn search_text(
+        &mut self,
+        indexes: &[&str],
+        text: &str,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error>;
+
+    /// Search the places less than `distance` meters from `coord`, the closest first
+    fn search_geo_distance(
+        &mut self,
+        indexes: &[&str],
+        coord: &Coord,
+        distance: f64,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error>;
+
+    /// Search the places by their ids
+    fn search_ids(&mut self, indexes: &[&str], ids: &[&str]) -> Result<Vec<Place>, Error>;
+
+    /// The closest address or street less than 1 km from `coord`
+    fn get_address(&mut self, coord: &Coord) -> Result<Vec<Place>, Error> {
+        self.search_geo_distance(
+            &[
+                &get_main_type_index::<Addr>(self.namespace()),
+                &get_main_type_index::<Street>(self.namespace()),
+            ],
+            coord,
+            1000.,
+            1,
+        )
+    }
+
+    fn make_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+    ) -> Result<TypedIndex<T>, Error> {
+        let index_name = get_date_index_name(&get_main_type_and_dataset_index::<T>(
+            self.namespace(),
+            dataset,
+        ));
+        info!("creating index {}", index_name);
+        self.create_index(&index_name, index_settings)?;
+        Ok(TypedIndex::new(index_name).with_final_settings(index_settings))
+    }
+
+    /// publish the index as the new index for this doc_type and this dataset
+    /// move the index alias of the doc_type and the dataset to point to this indexes
+    /// and remove the old index, but the last `nb_kept_indexes` which are closed.
+    /// An index made by `make_index` gets its final settings before the aliases are switched
+    /// visibility: Indicate if the index is public or private
+    fn publish_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index: TypedIndex<T>,
+        visibility: IndexVisibility,
+    ) -> Result<(), Error> {
+        debug!("publishing index");
+
+        // Refresh index before publishing
+        self.refresh_index(index.name())?;
+
+        let dataset_index = get_main_type_and_dataset_index::<T>(self.namespace(), dataset);
+        let kept_indexes: Vec<String> = self
+            .get_closed_indexes(&dataset_index)?
+            .into_iter()
+            .filter(|i| is_dataset_index(i, &dataset_index))
+            .collect();
+        // get the last indexes for this doc_type/dataset
+        // Note: to be resilient to ghost ES indexes, we take all indexes for this doc_type/dataset
+        // but the new index
+        let last_indexes: BTreeMap<String, Vec<String>> = self
+            .get_all_aliased_index(&dataset_index)?
+            .into_iter()
+            .filter(|(i, _)| {
+                i.as_str() != index.name()
+                    && !kept_indexes.contains(i)
+                    && is_dataset_index(i, &dataset_index)
+            })
+            .collect();
+
+        let current_index = last_indexes
+            .iter()
+            .find(|(_, aliases)| aliases.contains(&dataset_index))
+            .map(|(i, _)| i.as_str());
+        self.check_index::<T>(index.name(), current_index)
+            .with_context(|err| {
+                format!(
+                    "the index {} is not published in place of {}: {}",
+                    index.name(),
+                    current_index.unwrap_or("no index"),
+                    err
+                )
+            })?;
+        if let Some(index_settings) = index.final_settings() {
+            self.finish_index(index.name(), index_settings)?;
+        }
+
+        self.switch_aliases::<T>(
+            dataset,
+            &[index.name().to_owned()],
+            &last_indexes,
+            visibility,
+        )?;
+
+        // the published indexes are kept, the ghost indexes (from failed imports) are not
+        let mut previous_indexes: Vec<String> = last_indexes
+            .iter()
+            .filter(|(_, aliases)| aliases.contains(&dataset_index))
+            .map(|(i, _)| i.clone())
+            .chain(kept_indexes.iter().cloned())
+            .collect();
+        // the index names end with their creation date, so the most recent ones come first
+        previous_indexes.sort_unstable_by(|a, b| b.cmp(a));
+        let nb_kept_indexes = self.nb_kept_indexes();
+        for (rank, i) in previous_indexes.iter().enumerate() {
+            if rank >= nb_kept_indexes {
+                self.delete_index(i).with_context(|err| {
+                    format!("Error occurred when deleting index {}: {}", i, err)
+                })?;
+            } else if !kept_indexes.contains(i) {
+                info!("keeping index {}", i);
+                self.close_index(i).with_context(|err| {
+                    format!("Error occurred when closing index {}: {}", i, err)
+                })?;
+            }
+        }
+        for i in last_indexes
+            .keys()
+            .filter(|i| !previous_indexes.contains(i))
+        {
+            self.delete_index(i)
+                .with_context(|err| format!("Error occurred when deleting index {}: {}", i, err))?;
+        }
+        Ok(())
+    }
+
+    /// Check that a new index can replace the current index of its doc_type and dataset,
+    /// with the `publish_checks`. All the failed checks are reported in the error
+    fn check_index<T: MimirObject>(
+        &mut self,
+        index: &str,
+        current_index: Option<&str>,
+    ) -> Result<(), Error> {
+        let checks = self.publish_checks().clone();
+
+        let nb_documents = self.count_documents(index)?;
+        let current = match (checks.max_drop, current_index) {
+            (Some(_), Some(current_index)) => {
+                Some((current_index, self.count_documents(current_index)?))
+            }
+            _ => None,
+        };
+        let mut failures = check_counts(&checks, nb_documents, current);
+        let canaries = checks
+            .canaries
+            .iter()
+            .filter(|c| c.doc_type.as_deref().map_or(true, |t| t == T::doc_type()));
+        for canary in canaries {
+            let places = self.search_text(&[index], &canary.query, 10)?;
+            if !places.iter().any(|p| p.id() == canary.id) {
+                failures.push(format!("'{}' does not return {}", canary.query, canary.id));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            bail!("{}", failures.join(", "))
+        }
+    }
+
+    /// Update in place the index published for this doc_type and this dataset with the
+    /// documents of `iter`: the new and changed documents (compared by id and content hash)
+    /// are indexed, and the documents of the index missing from `iter` are deleted.
+    ///
+    /// The number of documents is checked with the `publish_checks` before any change
+    fn update_index<T, I>(&mut self, dataset: &str, iter: I) -> Result<UpdateSummary, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let dataset_index = get_main_type_and_dataset_index::<T>(self.namespace(), dataset);
+        self.update_aliased_index(&dataset_index, iter)
+    }
+
+    /// Update in place the index of the alias `alias` (like the global stop index) with the
+    /// documents of `iter`, as `update_index` does for the index of a dataset
+    fn update_aliased_index<T, I>(&mut self, alias: &str, iter: I) -> Result<UpdateSummary, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let alias = alias.to_owned();
+        let index = self
+            .get_all_aliased_index(&alias)?
+            .into_iter()
+            .find(|(_, aliases)| aliases.contains(&alias))
+            .map(|(index, _)| index)
+            .ok_or_else(|| format_err!("no index published for {}", alias))?;
+
+        let mut hashes = self.get_document_hashes(&index)?;
+        let nb_current_documents = hashes.len() as u64;
+        let mut changed = vec![];
+        let mut nb_unchanged = 0;
+        for object in iter {
+            let unchanged = match object.es_id() {
+                Some(id) => {
+                    hashes.remove(&id) == Some(content_hash(&serde_json::to_value(&object)?))
+                }
+                None => false,
+            };
+            if unchanged {
+                nb_unchanged += 1;
+            } else {
+                changed.push(object);
+            }
+        }
+        // the documents left are not in the update
+        let vanished: Vec<String> = hashes.into_iter().map(|(id, _)| id).collect();
+
+        let nb_documents = (nb_unchanged + changed.len()) as u64;
+        let failures = check_counts(
+            self.publish_checks(),
+            nb_documents,
+            Some((&index, nb_current_documents)),
+        );
+        if !failures.is_empty() {
+            bail!(
+                "the index {} is not updated: {}",
+                index,
+                failures.join(", ")
+            );
+        }
+
+        let index = TypedIndex::<T>::new(index);
+        let nb_upserted = self.bulk_index(&index, changed.into_iter())?;
+        let nb_deleted = self.delete_documents(&index, &vanished)?;
+        self.refresh_index(index.name())?;
+        info!(
+            "{} updated: {} documents unchanged, {} indexed, {} deleted",
+            index.name(),
+            nb_unchanged,
+            nb_upserted,
+            nb_deleted
+        );
+        Ok(UpdateSummary {
+            nb_unchanged,
+            nb_upserted,
+            nb_deleted,
+        })
+    }
+
+    /// Publish again an index kept for this doc_type and this dataset (by default
+    /// the most recent one), in place of the current index which is kept.
+    /// Return the name of the published index
+    fn rollback_index<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        index: Option<&str>,
+    ) -> Result<String, Error> {
+        let dataset_index = get_main_type_and_dataset_index::<T>(self.namespace(), dataset);
+        let mut kept_indexes: Vec<String> = self
+            .get_closed_indexes(&dataset_index)?
+            .into_iter()
+            .filter(|i| is_dataset_index(i, &dataset_index))
+            .collect();
+        kept_indexes.sort_unstable_by(|a, b| b.cmp(a));
+        let index = match index {
+            Some(index) if kept_indexes.iter().any(|i| i == index) => index.to_owned(),
+            Some(index) => bail!("{} is not a kept index of {}", index, dataset_index),
+            None => kept_indexes
+                .into_iter()
+                .next()
+                .ok_or_else(|| format_err!("no index kept for {}", dataset_index))?,
+        };
+
+        let current_indexes: BTreeMap<String, Vec<String>> = self
+            .get_all_aliased_index(&dataset_index)?
+            .into_iter()
+            .filter(|(_, aliases)| aliases.contains(&dataset_index))
+            .collect();
+        if current_indexes.is_empty() {
+            bail!("no index published for {}", dataset_index);
+        }
+        // the kept index takes the visibility of the current index
+        let type_index = get_main_type_index::<T>(self.namespace());
+        let visibility = if current_indexes.values().any(|a| a.contains(&type_index)) {
+            IndexVisibility::Public
+        } else {
+            IndexVisibility::Private
+        };
+
+        info!("rolling back {} to {}", dataset_index, index);
+        self.open_index(&index)?;
+        self.switch_aliases::<T>(dataset, &[index.clone()], &current_indexes, visibility)?;
+        for i in current_indexes.keys() {
+            info!("keeping index {}", i);
+            self.close_index(i)
+                .with_context(|err| format!("Error occurred when closing index {}: {}", i, err))?;
+        }
+        Ok(index)
+    }
+
+    /// Atomically move the aliases of the doc_type and the dataset from the `previous`
+    /// indexes (with their aliases) to the `new` indexes
+    fn switch_aliases<T: MimirObject>(
+        &mut self,
+        dataset: &str,
+        new: &[String],
+        previous: &BTreeMap<String, Vec<String>>,
+        visibility: IndexVisibility,
+    ) -> Result<(), Error> {
+        let dataset_index = get_main_type_and_dataset_index::<T>(self.namespace(), dataset);
+        let type_index = get_main_type_index::<T>(self.namespace());
+        let geo_data_index = get_geo_data_index(self.namespace());
+        let namespace = self.namespace().to_owned();
+        let mut aliases = vec![dataset_index.clone()];
+        if let IndexVisibility::Public = visibility {
+            aliases.push(type_index.clone());
+            if T::is_geo_data() {
+                aliases.push(geo_data_index.clone());
+            }
+            aliases.push(namespace.clone());
+        }
+        let managed_aliases = [
+            dataset_index.as_str(),
+            type_index.as_str(),
+            geo_data_index.as_str(),
+            namespace.as_str(),
+        ];
+
+        let add: Vec<(String, String)> = aliases
+            .iter()
+            .flat_map(|alias| new.iter().map(move |i| (alias.clone(), i.clone())))
+            .collect();
+        let remove: Vec<(String, String)> = previous
+            .iter()
+            .flat_map(|(i, aliases)| {
+                aliases
+                    .iter()
+                    .filter(|alias| managed_aliases.contains(&alias.as_str()))
+                    .map(move |alias| (alias.clone(), i.clone()))
+            })
+            .collect();
+        self.update_aliases(&add, &remove).with_context(|err| {
+            format!(
+                "Error occurred when making alias {}: {}",
+                dataset_index, err
+            )
+        })?;
+        Ok(())
+    }
+
+    /// add all the element of 'iter' into the backend
+    ///
+    /// To have zero downtime:
+    /// first all the elements are added in a temporary index and when all has been indexed
+    /// the index is published and the old index is removed
+    fn index<T, I>(
+        &mut self,
+        dataset: &str,
+        visibility: IndexVisibility,
+        index_settings: &IndexSettings,
+        iter: I,
+    ) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        let index = self
+            .make_index(dataset, index_settings)
+            .with_context(|err| format!("Error occurred when making index {}: {}", dataset, err))?;
+        let nb_elements = self.bulk_index(&index, iter)?;
+        self.publish_index(dataset, index, visibility)?;
+        Ok(nb_elements)
+    }
+
+    /// Shortcut to `index` for a public index
+    fn public_index<T, I>(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+        iter: I,
+    ) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        self.index(dataset, IndexVisibility::Public, index_settings, iter)
+    }
+
+    /// Shortcut to `index` for a private index
+    fn private_index<T, I>(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+        iter: I,
+    ) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        self.index(dataset, IndexVisibility::Private, index_settings, iter)
+    }
+
+    fn get_all_admins(&mut self) -> Result<Vec<Admin>, Error> {
+        self.get_all_objects_from_index(&get_main_type_index::<Admin>(self.namespace()))
+    }
+}
+
+impl SearchBackend for Rubber {
+    fn create_index(&mut self, name: &str, index_settings: &IndexSettings) -> Result<(), Error> {
+        Rubber::create_index(self, name, index_settings)
+    }
+
+    fn delete_index(&mut self, index: &str) -> Result<(), Error> {
+        Rubber::delete_index(self, index)
+    }
+
+    fn refresh_index(&mut self, index: &str) -> Result<(), Error> {
+        self.es_client.refresh().with_indexes(&[index]).send()?;
+        Ok(())
+    }
+
+    fn finish_index(&mut self, index: &str, index_settings: &IndexSettings) -> Result<(), Error> {
+        Rubber::finish_index(self, index, index_settings)
+    }
+
+    fn bulk_index<T, I>(&mut self, index: &TypedIndex<T>, iter: I) -> Result<usize, Error>
+    where
+        T: MimirObject + std::marker::Send + 'static,
+        I: Iterator<Item = T>,
+    {
+        Rubber::bulk_index(self, index, iter)
+    }
+
+    fn close_index(&mut self, index: &str) -> Result<(), Error> {
+        Rubber::close_index(self, index)
+    }
+
+    fn open_index(&mut self, index: &str) -> Result<(), Error> {
+        Rubber::open_index(self, index)
+    }
+
+    fn get_closed_indexes(&mut self, base_index: &str) -> Result<Vec<String>, Error> {
+        Rubber::get_closed_indexes(self, base_index)
+    }
+
+    fn nb_kept_indexes(&self) -> usize {
+        self.nb_kept_indexes
+    }
+
+    fn publish_checks(&self) -> &PublishChecks {
+        &self.publish_checks
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn count_documents(&mut self, index: &str) -> Result<u64, Error> {
+        Rubber::count_documents(self, index)
+    }
+
+    fn get_document_hashes(&mut self, index: &str) -> Result<HashMap<String, u64>, Error> {
+        let mut hashes = HashMap::new();
+        for document in self.clone().scroll_documents(index)? {
+            let document = document?;
+            if let Some(id) = document.get("id").and_then(serde_json::Value::as_str) {
+                hashes.insert(id.to_owned(), content_hash(&document));
+            }
+        }
+        Ok(hashes)
+    }
+
+    fn delete_documents<T: MimirObject>(
+        &mut self,
+        index: &TypedIndex<T>,
+        ids: &[String],
+    ) -> Result<usize, Error> {
+        Rubber::delete_documents(self, index, ids)
+    }
+
+    fn update_aliases(
+        &mut self,
+        add: &[(String, String)],
+        remove: &[(String, String)],
+    ) -> Result<(), Error> {
+        Rubber::update_aliases(self, add, remove)
+    }
+
+    fn get_all_aliased_index(
+        &mut self,
+        base_index: &str,
+    ) -> Result<BTreeMap<String, Vec<String>>, Error> {
+        Rubber::get_all_aliased_index(self, base_index)
+    }
+
+    fn get_all_objects_from_index<T>(&mut self, index: &str) -> Result<Vec<T>, Error>
+    where
+        for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug,
+    {
+        Ok(Rubber::get_all_objects_from_index(self, index)?)
+    }
+
+    fn search_text(
+        &mut self,
+        indexes: &[&str],
+        text: &str,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        let query = Query::build_bool()
+            .with_must(
+                Query::build_match("full_label.prefix", text)
+                    .with_operator("and")
+                    .build(),
+            )
+            .with_should(Query::build_match("label", text).build())
+            .build();
+        Ok(self.search_places(indexes, &query, limit, None)?)
+    }
+
+    fn search_geo_distance(
+        &mut self,
+        indexes: &[&str],
+        coord: &Coord,
+        distance: f64,
+        limit: u64,
+    ) -> Result<Vec<Place>, Error> {
+        let distance = rs_u::Distance::new(distance, rs_u::DistanceUnit::Meter);
+        let query = Query::build_bool()
+            .with_should(crate::rubber::build_proximity_with_boost(coord, 1.))
+            .with_must(
+                Query::build_geo_distance("coord", (coord.lat(), coord.lon()), distance).build(),
+            )
+            .build();
+        Ok(self.search_places(indexes, &query, limit, Some(coord))?)
+    }
+
+    fn search_ids(&mut self, indexes: &[&str], ids: &[&str]) -> Result<Vec<Place>, Error> {
+        let limit = ids.len() as u64;
+        let ids = ids
+            .iter()
+            .map(|id| rs_u::JsonVal::String((*id).to_owned()))
+            .collect();
+        let query = Query::build_ids(ids).build();
+        Ok(self.search_places(indexes, &query, limit, None)?)
+    }
+
+    fn get_address(&mut self, coord: &Coord) -> Result<Vec<Place>, Error> {
+        Ok(Rubber::get_address(self, coord)?)
+    }
+}
@@ -35,6 +35,7 @@
 // #[macro_use]
 // extern crate failure;
 
+pub mod backend;
 pub mod objects;
 pub mod rubber;
 
@@ -96,6 +96,16 @@ impl Place {
         }
     }
 
+    pub fn id(&self) -> &str {
+        match *self {
+            Place::Admin(ref o) => &o.id,
+            Place::Street(ref o) => &o.id,
+            Place::Addr(ref o) => &o.id,
+            Place::Poi(ref o) => &o.id,
+            Place::Stop(ref o) => &o.id,
+        }
+    }
+
     pub fn label(&self) -> &str {
         match *self {
             Place::Admin(ref o) => o.label(),
@@ -106,6 +116,17 @@ impl Place {
         }
     }
 
+    /// IANA timezone of the place (the timezone of the street for an address)
+    pub fn timezone(&self) -> Option<&str> {
+        match *self {
+            Place::Admin(ref o) => o.timezone.as_deref(),
+            Place::Street(ref o) => o.timezone.as_deref(),
+            Place::Addr(ref o) => o.street.timezone.as_deref(),
+            Place::Poi(ref o) => o.timezone.as_deref(),
+            Place::Stop(ref o) => Some(o.timezone.as_str()).filter(|tz| !tz.is_empty()),
+        }
+    }
+
     pub fn admins(&self) -> Vec<Arc<Admin>> {
         match *self {
             Place::Admin(ref o) => o.admins(),
@@ -156,6 +177,26 @@ impl Place {
         }
     }
 
+    pub fn score(&self) -> Option<f64> {
+        match *self {
+            Place::Admin(ref o) => o.score,
+            Place::Street(ref o) => o.score,
+            Place::Addr(ref o) => o.score,
+            Place::Poi(ref o) => o.score,
+            Place::Stop(ref o) => o.score,
+        }
+    }
+
+    pub fn set_score(&mut self, score: f64) {
+        match self {
+            Place::Admin(ref mut o) => o.score = Some(score),
+            Place::Street(ref mut o) => o.score = Some(score),
+            Place::Addr(ref mut o) => o.score = Some(score),
+            Place::Poi(ref mut o) => o.score = Some(score),
+            Place::Stop(ref mut o) => o.score = Some(score),
+        }
+    }
+
     pub fn set_context(&mut self, context: Context) {
         match self {
             Place::Admin(ref mut o) => o.context = Some(context),
@@ -282,6 +323,9 @@ pub struct Poi {
     pub address: Option<Address>,
     #[serde(default)]
     pub country_codes: Vec<String>,
+    /// IANA timezone, from the admins
+    #[serde(default)]
+    pub timezone: Option<String>,
 
     #[serde(default)]
     pub names: I18nProperties,
@@ -293,6 +337,11 @@ pub struct Poi {
     #[serde(default, skip)]
     pub distance: Option<u32>,
 
+    /// Elasticsearch score of the place in the query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub score: Option<f64>,
+
     pub context: Option<Context>,
 }
 
@@ -507,6 +556,11 @@ pub struct Stop {
     /// Not serialized as is because it is returned in the `Feature` object
     #[serde(default, skip)]
     pub distance: Option<u32>,
+
+    /// Elasticsearch score of the place in the query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub score: Option<f64>,
     #[serde(default)]
     pub lines: Vec<Line>,
     #[serde(default)]
@@ -573,6 +627,9 @@ pub struct Admin {
     pub parent_id: Option<String>, // id of the Admin's parent (from the cosmogony's hierarchy)
     #[serde(default)]
     pub country_codes: Vec<String>,
+    /// IANA timezone, from the `timezone` tag of the admin or of its parents
+    #[serde(default)]
+    pub timezone: Option<String>,
 
     #[serde(default)]
     pub codes: Vec<Code>,
@@ -587,6 +644,11 @@ pub struct Admin {
     #[serde(default, skip)]
     pub distance: Option<u32>,
 
+    /// Elasticsearch score of the place in the query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub score: Option<f64>,
+
     pub context: Option<Context>,
 }
 
@@ -596,6 +658,15 @@ impl Admin {
     }
 }
 
+/// Timezone of a place, given by its most precise admin having a timezone
+pub fn find_timezone(admins: &[Arc<Admin>]) -> Option<String> {
+    admins
+        .iter()
+        .filter(|admin| admin.timezone.is_some())
+        .max_by_key(|admin| admin.level)
+        .and_then(|admin| admin.timezone.clone())
+}
+
 fn custom_multi_polygon_serialize<S>(
     multi_polygon_option: &Option<MultiPolygon<f64>>,
     serializer: S,
@@ -722,11 +793,19 @@ pub struct Street {
     pub zip_codes: Vec<String>,
     #[serde(default)]
     pub country_codes: Vec<String>,
+    /// IANA timezone, from the admins
+    #[serde(default)]
+    pub timezone: Option<String>,
     /// Distance to the coord in query.
     /// Not serialized as is because it is returned in the `Feature` object
     #[serde(default, skip)]
     pub distance: Option<u32>,
 
+    /// Elasticsearch score of the place in the query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub score: Option<f64>,
+
     pub context: Option<Context>,
 }
 impl Incr for Street {
@@ -781,6 +860,11 @@ pub struct Addr {
     #[serde(default, skip)]
     pub distance: Option<u32>,
 
+    /// Elasticsearch score of the place in the query.
+    /// Not serialized as is because it is returned in the `Feature` object
+    #[serde(default, skip)]
+    pub score: Option<f64>,
+
     pub context: Option<Context>,
 }
 
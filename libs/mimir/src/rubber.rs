@@ -28,22 +28,26 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
-use super::objects::{Admin, Context, Explanation, MimirObject};
 use super::objects::{AliasOperation, AliasOperations, AliasParameter, Coord, Place};
+use super::objects::{Context, Explanation, MimirObject};
 use failure::{bail, format_err, Error, ResultExt};
 use prometheus::{exponential_buckets, histogram_opts, register_histogram, Histogram};
 use reqwest::StatusCode;
 use rs_es::error::EsError;
 use rs_es::operations::search::ScanResult;
+use rs_es::operations::search::SearchHitsHitsResult;
 use rs_es::operations::search::SearchResult;
+use rs_es::operations::search::{Sort, Source};
 use rs_es::query::functions::{FilteredFunction, Function};
 use rs_es::query::Query;
 use rs_es::units as rs_u;
 use rs_es::units::Duration;
 use rs_es::EsResponse;
+use serde::{Deserialize, Serialize};
 use slog_scope::{debug, info, warn};
 use std::collections::BTreeMap;
 use std::marker::PhantomData;
+use std::thread;
 use std::time;
 
 const SYNONYMS: [&str; 17] = [
@@ -97,11 +101,79 @@ pub struct Rubber {
     pub cnx_string: String,
     pub nb_insert_threads: usize,
     max_bulk_errors: usize,
+    pub bulk_retry: BulkRetry,
+    pub document_model: DocumentModel,
+    pub nb_kept_indexes: usize,
+    pub publish_checks: PublishChecks,
+    pub force_merge: bool,
+    /// Prefix of the indexes and aliases, taken from the connection string
+    pub namespace: String,
+}
+
+/// How the type of the documents is stored in Elasticsearch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentModel {
+    /// One mapping type per index (Elasticsearch 2 to 6), the type of a document is its `_type`
+    Typed,
+    /// Indexes without mapping type (Elasticsearch 7+), the type of a document is its `type` field
+    Typeless,
+}
+
+impl Default for DocumentModel {
+    fn default() -> Self {
+        DocumentModel::Typed
+    }
+}
+
+impl std::str::FromStr for DocumentModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typed" => Ok(DocumentModel::Typed),
+            "typeless" => Ok(DocumentModel::Typeless),
+            _ => Err(format!(
+                "invalid document model '{}', expected 'typed' or 'typeless'",
+                s
+            )),
+        }
+    }
+}
+
+impl DocumentModel {
+    /// Field to query to filter the documents by type
+    pub fn type_field(self) -> &'static str {
+        match self {
+            DocumentModel::Typed => "_type",
+            DocumentModel::Typeless => "type",
+        }
+    }
+
+    /// Mapping type given when indexing a document of type `doc_type`
+    pub(crate) fn mapping_type(self, doc_type: &'static str) -> &'static str {
+        match self {
+            DocumentModel::Typed => doc_type,
+            DocumentModel::Typeless => "_doc",
+        }
+    }
+}
+
+/// A document as indexed, with its explicit `type` field.
+/// The field is written with both document models, so the indexes created with the
+/// `typed` model can be reindexed into a typeless cluster.
+#[derive(Serialize)]
+pub(crate) struct Document<T> {
+    #[serde(rename = "type")]
+    pub(crate) doc_type: &'static str,
+    #[serde(flatten)]
+    pub(crate) object: T,
 }
 
 #[derive(Clone, Debug)]
 pub struct TypedIndex<T> {
     name: String,
+    final_settings: Option<IndexSettings>,
     _type: PhantomData<T>,
 }
 
@@ -109,9 +181,25 @@ impl<T> TypedIndex<T> {
     pub fn new(name: String) -> TypedIndex<T> {
         TypedIndex {
             name,
+            final_settings: None,
             _type: PhantomData,
         }
     }
+
+    /// The settings given to the index by `finish_index` when it is published,
+    /// for an index created with the bulk loading settings
+    pub fn with_final_settings(mut self, index_settings: &IndexSettings) -> Self {
+        self.final_settings = Some(index_settings.clone());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn final_settings(&self) -> Option<&IndexSettings> {
+        self.final_settings.as_ref()
+    }
 }
 
 /// Index Visibility
@@ -123,22 +211,185 @@ pub enum IndexVisibility {
     Private,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct IndexSettings {
     pub nb_shards: usize,
     pub nb_replicas: usize,
 }
 
+/// Retries of `bulk_index` when Elasticsearch is busy: the documents rejected
+/// with a 429 or 5xx status, and the bulk requests which failed, are sent again
+#[derive(Clone, Debug)]
+pub struct BulkRetry {
+    /// Maximum number of retries of a document or of a bulk request
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled at each retry
+    pub initial_delay: time::Duration,
+    /// Maximum delay between two retries
+    pub max_delay: time::Duration,
+}
+
+impl Default for BulkRetry {
+    fn default() -> Self {
+        BulkRetry {
+            max_retries: 5,
+            initial_delay: time::Duration::from_millis(500),
+            max_delay: time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl BulkRetry {
+    /// Delay before the retry `attempt` (starting at 0): exponential backoff, with a random
+    /// jitter so that the insert threads do not retry all at the same time
+    pub fn delay(&self, attempt: usize) -> time::Duration {
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        delay / 2 + (delay / 2).mul_f64(rand::random::<f64>())
+    }
+}
+
+/// Status of a bulk item rejected because Elasticsearch is busy or failing, that can be retried
+fn is_transient_status(status: u64) -> bool {
+    status == 429 || status >= 500
+}
+
+/// Whether a whole bulk request failing with `err` can be retried: a network failure or a
+/// transient HTTP status, but not a request rejected by Elasticsearch nor an invalid response
+fn is_transient_error(err: &EsError) -> bool {
+    match err {
+        EsError::HttpError { .. } => true,
+        err => error_status(&err.to_string()).map_or(false, is_transient_status),
+    }
+}
+
+// rs-es starts the message of an error response with its HTTP status
+// (`503 Service Unavailable - ...`)
+fn error_status(message: &str) -> Option<u64> {
+    message
+        .split_whitespace()
+        .next()
+        .and_then(|status| status.parse().ok())
+        .filter(|status| (100..600).contains(status))
+}
+
+/// Checks made on a new index before publishing it
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PublishChecks {
+    /// Minimum number of documents of the index
+    #[serde(default)]
+    pub min_documents: u64,
+    /// Maximum relative drop (between 0 and 1) of the number of documents
+    /// compared with the index being replaced
+    #[serde(default, deserialize_with = "deserialize_max_drop")]
+    pub max_drop: Option<f64>,
+    /// Queries which must return a given document
+    #[serde(default)]
+    pub canaries: Vec<Canary>,
+}
+
+/// Check that a maximum drop is a fraction of the documents, between 0 and 1
+pub fn check_max_drop(max_drop: f64) -> Result<f64, String> {
+    if (0.0..=1.0).contains(&max_drop) {
+        Ok(max_drop)
+    } else {
+        Err(format!(
+            "invalid max drop {}, expected a value between 0 and 1",
+            max_drop
+        ))
+    }
+}
+
+fn deserialize_max_drop<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<f64>::deserialize(deserializer)?
+        .map(check_max_drop)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// The types of the documents a canary can be restricted to
+const CANARY_TYPES: [&str; 5] = ["admin", "street", "addr", "poi", "stop"];
+
+/// A query which must return the document `id`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Canary {
+    /// Type of the indexes checked by the canary, all the indexes if not given
+    #[serde(rename = "type")]
+    pub doc_type: Option<String>,
+    pub query: String,
+    pub id: String,
+}
+
+impl std::str::FromStr for Canary {
+    type Err = String;
+
+    /// Read a canary written as `<query>=<id>`, or as `<type>:<query>=<id>` to only check
+    /// the indexes of a type
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (doc_type, canary) = match s.find(':') {
+            Some(pos) if CANARY_TYPES.contains(&&s[..pos]) => {
+                (Some(s[..pos].to_owned()), &s[pos + 1..])
+            }
+            _ => (None, s),
+        };
+        match canary.rfind('=') {
+            Some(pos) if pos > 0 && pos + 1 < canary.len() => Ok(Canary {
+                doc_type,
+                query: canary[..pos].to_owned(),
+                id: canary[pos + 1..].to_owned(),
+            }),
+            _ => Err(format!(
+                "invalid canary '{}', expected '<query>=<id>' or '<type>:<query>=<id>'",
+                s
+            )),
+        }
+    }
+}
+
+/// Namespace of the indexes and aliases when the connection string has no path
+pub const DEFAULT_NAMESPACE: &str = "munin";
+
+/// The namespace of the indexes and aliases: the last segment of the path of the connection
+/// string (`http://localhost:9200/munin`), `DEFAULT_NAMESPACE` if it has no path
+pub fn namespace_from_url(cnx: &str) -> String {
+    reqwest::Url::parse(cnx)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()?
+                .filter(|segment| !segment.is_empty())
+                .last()
+                .map(str::to_owned)
+        })
+        .unwrap_or_else(|| DEFAULT_NAMESPACE.to_owned())
+}
+
 /// return the index associated to the given type and dataset
 /// this will be an alias over another real index
-pub fn get_main_type_and_dataset_index<T: MimirObject>(dataset: &str) -> String {
-    format!("munin_{}_{}", T::doc_type(), dataset)
+pub fn get_main_type_and_dataset_index<T: MimirObject>(namespace: &str, dataset: &str) -> String {
+    format!("{}_{}_{}", namespace, T::doc_type(), dataset)
 }
 
 /// return the index associated to the given type
 /// this will be an alias over another real index
-pub fn get_main_type_index<T: MimirObject>() -> String {
-    format!("munin_{}", T::doc_type())
+pub fn get_main_type_index<T: MimirObject>(namespace: &str) -> String {
+    format!("{}_{}", namespace, T::doc_type())
+}
+
+/// return the alias over all the geographical indexes (admins, streets and addresses)
+pub fn get_geo_data_index(namespace: &str) -> String {
+    format!("{}_geo_data", namespace)
+}
+
+/// return the alias over the index merging the stops of all the datasets
+pub fn get_global_stop_index(namespace: &str) -> String {
+    format!("{}_global_stops", namespace)
 }
 
 pub fn get_date_index_name(base_index_name: &str) -> String {
@@ -149,7 +400,21 @@ pub fn get_date_index_name(base_index_name: &str) -> String {
     )
 }
 
-pub fn get_indexes_by_type(a_type: &str) -> String {
+/// Split the name of an index made by `get_date_index_name` into its base name and its date,
+/// `None` for another index
+pub fn split_date_index_name(index: &str) -> Option<(&str, chrono::DateTime<chrono::Utc>)> {
+    let mut parts = index.rsplitn(4, '_');
+    let (nanos, time, date, base) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if nanos.is_empty() || !nanos.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let date =
+        chrono::NaiveDateTime::parse_from_str(&format!("{}_{}", date, time), "%Y%m%d_%H%M%S")
+            .ok()?;
+    Some((base, chrono::DateTime::from_utc(date, chrono::Utc)))
+}
+
+pub fn get_indexes_by_type(namespace: &str, a_type: &str) -> String {
     let doc_type = match a_type {
         "public_transport:stop_area" => "stop",
         "city" | "zone" => "admin",
@@ -157,25 +422,151 @@ pub fn get_indexes_by_type(a_type: &str) -> String {
         _ => a_type,
     };
 
-    format!("munin_{}", doc_type)
+    format!("{}_{}", namespace, doc_type)
+}
+
+/// A search of `Rubber::search`
+pub struct SearchRequest<'a> {
+    indexes: &'a [&'a str],
+    query: &'a Query,
+    from: Option<u64>,
+    size: Option<u64>,
+    sort: Option<&'a Sort>,
+    with_boundary: bool,
+    explain: bool,
+}
+
+impl<'a> SearchRequest<'a> {
+    pub fn new(indexes: &'a [&'a str], query: &'a Query) -> Self {
+        SearchRequest {
+            indexes,
+            query,
+            from: None,
+            size: None,
+            sort: None,
+            with_boundary: true,
+            explain: false,
+        }
+    }
+
+    pub fn with_from(mut self, from: u64) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_sort(mut self, sort: &'a Sort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Fetch the boundaries of the documents or not: they are very large in some documents
+    /// (countries...)
+    pub fn with_boundary(mut self, with_boundary: bool) -> Self {
+        self.with_boundary = with_boundary;
+        self
+    }
+
+    /// Give the explanation of the score of each hit
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+}
+
+/// The hits of a search. rs-es reads the results of the typed clusters only, those of the
+/// typeless clusters (Elasticsearch 7+) are read by `Rubber` itself.
+#[derive(Debug)]
+pub struct SearchHits<T> {
+    pub took: u64,
+    pub total: u64,
+    pub hits: Vec<SearchHit<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchHit<T> {
+    /// The mapping type, `_doc` on the typeless clusters
+    #[serde(rename = "_type", default)]
+    pub doc_type: String,
+    #[serde(rename = "_score", default)]
+    pub score: Option<f64>,
+    #[serde(rename = "_source", default)]
+    pub source: Option<Box<T>>,
+    #[serde(rename = "_explanation", default)]
+    pub explanation: Option<serde_json::Value>,
+}
+
+impl<T> From<SearchHitsHitsResult<T>> for SearchHit<T> {
+    fn from(hit: SearchHitsHitsResult<T>) -> Self {
+        SearchHit {
+            doc_type: hit.doc_type,
+            score: hit.score,
+            source: hit.source,
+            explanation: hit.explanation,
+        }
+    }
+}
+
+impl<T> From<SearchResult<T>> for SearchHits<T> {
+    fn from(result: SearchResult<T>) -> Self {
+        SearchHits {
+            took: result.took,
+            total: result.hits.total,
+            hits: result.hits.hits.into_iter().map(SearchHit::from).collect(),
+        }
+    }
+}
+
+// A page of a search or of a scroll of the REST API, with `rest_total_hits_as_int` since
+// Elasticsearch 7 gives `hits.total` as an object otherwise
+#[derive(Deserialize)]
+struct RestSearchResult<T> {
+    took: u64,
+    hits: RestSearchHits<T>,
+    #[serde(rename = "_scroll_id", default)]
+    scroll_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RestSearchHits<T> {
+    total: u64,
+    hits: Vec<SearchHit<T>>,
+}
+
+impl<T> From<RestSearchResult<T>> for SearchHits<T> {
+    fn from(result: RestSearchResult<T>) -> Self {
+        SearchHits {
+            took: result.took,
+            total: result.hits.total,
+            hits: result.hits.hits,
+        }
+    }
 }
 
 pub fn read_places(
-    result: SearchResult<serde_json::Value>,
+    result: SearchHits<serde_json::Value>,
     coord: Option<&Coord>, // coord used to compute the distance of the place to the object
 ) -> Result<Vec<Place>, EsError> {
-    debug!(
-        "{} documents found in {} ms",
-        result.hits.total, result.took
-    );
+    debug!("{} documents found in {} ms", result.total, result.took);
     let point: Option<geo_types::Point<f64>> = coord.map(|c| c.0.into());
     // for the moment rs-es does not handle enum Document,
     // so we need to convert the ES glob to a Place
     Ok(result
-        .hits
         .hits
         .into_iter()
-        .filter_map(|hit| make_place(hit.doc_type, hit.source, hit.explanation))
+        .filter_map(|hit| {
+            let score = hit.score;
+            make_place(hit.doc_type, hit.source, hit.explanation).map(|mut place| {
+                if let Some(score) = score {
+                    place.set_score(score);
+                }
+                place
+            })
+        })
         .map(|mut place| {
             if let Some(ref p) = point {
                 use geo::algorithm::haversine_distance::HaversineDistance;
@@ -187,13 +578,172 @@ pub fn read_places(
         .collect())
 }
 
+/// Version of the templates of `config/*_settings.json`, stored in the `_meta` of the mappings
+/// of the templates and thus of the indexes built from them. It must be increased with each
+/// change of the templates: Bragi warns about the indexes built with another version.
+pub const TEMPLATE_VERSION: u64 = 3;
+
+const TEMPLATES: [(&str, &str); 5] = [
+    (
+        "template_addr",
+        include_str!("../../../config/addr_settings.json"),
+    ),
+    (
+        "template_stop",
+        include_str!("../../../config/stop_settings.json"),
+    ),
+    (
+        "template_admin",
+        include_str!("../../../config/admin_settings.json"),
+    ),
+    (
+        "template_street",
+        include_str!("../../../config/street_settings.json"),
+    ),
+    (
+        "template_poi",
+        include_str!("../../../config/poi_settings.json"),
+    ),
+];
+
+/// Maximum wait for the replicas of a new index
+const WAIT_FOR_REPLICAS_TIMEOUT: &str = "30m";
+
+/// The pattern of a template (`munin_addr_*`) for the indexes of a namespace
+fn namespaced_pattern(namespace: &str, pattern: &str) -> String {
+    format!(
+        "{}{}",
+        namespace,
+        pattern.trim_start_matches(DEFAULT_NAMESPACE)
+    )
+}
+
+/// Refresh interval of the template of an index, `null` (the default
+/// of Elasticsearch) if its template does not set it
+fn template_refresh_interval(namespace: &str, index: &str) -> serde_json::Value {
+    TEMPLATES
+        .iter()
+        .filter_map(|(_, settings)| serde_json::from_str::<serde_json::Value>(settings).ok())
+        .find(|template| {
+            template
+                .get("template")
+                .and_then(serde_json::Value::as_str)
+                .map_or(false, |pattern| {
+                    let pattern = namespaced_pattern(namespace, pattern);
+                    index.starts_with(pattern.trim_end_matches('*'))
+                })
+        })
+        .and_then(|template| template.pointer("/settings/refresh_interval").cloned())
+        .unwrap_or(serde_json::Value::Null)
+}
+
+/// Add `TEMPLATE_VERSION` to the `_meta` of the mapping types of a template
+fn versioned_template(settings: &str) -> Result<serde_json::Value, Error> {
+    let mut template: serde_json::Value = serde_json::from_str(settings)?;
+    if let Some(types) = template
+        .get_mut("mappings")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        for mapping in types
+            .values_mut()
+            .filter_map(serde_json::Value::as_object_mut)
+        {
+            mapping.insert(
+                "_meta".to_owned(),
+                serde_json::json!({ "template_version": TEMPLATE_VERSION }),
+            );
+        }
+    }
+    Ok(template)
+}
+
+/// Template version stored in typed or typeless mappings
+pub fn template_version(mappings: &serde_json::Value) -> Option<u64> {
+    let version = |mapping: &serde_json::Value| {
+        mapping
+            .pointer("/_meta/template_version")
+            .and_then(serde_json::Value::as_u64)
+    };
+    version(mappings).or_else(|| mappings.as_object()?.values().find_map(version))
+}
+
+/// Convert a template written for Elasticsearch 2 (with one mapping type)
+/// to a typeless template for Elasticsearch 7+
+pub fn typeless_template(settings: &str) -> Result<serde_json::Value, Error> {
+    let mut template: serde_json::Value = serde_json::from_str(settings)?;
+    let object = template
+        .as_object_mut()
+        .ok_or_else(|| format_err!("the template is not a JSON object"))?;
+    if let Some(pattern) = object.remove("template") {
+        object.insert("index_patterns".to_owned(), serde_json::json!([pattern]));
+    }
+    // the mapping of the only mapping type becomes the mapping of the index
+    let mapping = match object.remove("mappings") {
+        Some(serde_json::Value::Object(types)) => types.into_iter().next().map(|(_, m)| m),
+        _ => None,
+    };
+    if let Some(mut mapping) = mapping {
+        if let Some(mapping) = mapping.as_object_mut() {
+            mapping.remove("_all");
+        }
+        object.insert("mappings".to_owned(), mapping);
+    }
+    typeless_fields(&mut template);
+    Ok(template)
+}
+
+// the field definitions removed or renamed since Elasticsearch 2
+fn typeless_fields(value: &mut serde_json::Value) {
+    use serde_json::{json, Value};
+    match value {
+        Value::Object(fields) => {
+            if fields.get("type") == Some(&json!("string")) {
+                if fields.get("index") == Some(&json!("not_analyzed")) {
+                    fields.remove("index");
+                    fields.insert("type".to_owned(), json!("keyword"));
+                } else {
+                    fields.insert("type".to_owned(), json!("text"));
+                }
+            }
+            if fields.get("type") == Some(&json!("nGram")) {
+                fields.insert("type".to_owned(), json!("ngram"));
+            }
+            if fields.get("type") == Some(&json!("geo_point")) {
+                for removed in &["lat_lon", "geohash_prefix", "geohash_precision"] {
+                    fields.remove(*removed);
+                }
+            }
+            if fields.get("index") == Some(&json!("no")) {
+                fields.insert("index".to_owned(), json!(false));
+            }
+            if fields.get("norms") == Some(&json!({"enabled": false})) {
+                fields.insert("norms".to_owned(), json!(false));
+            }
+            fields.values_mut().for_each(typeless_fields);
+        }
+        Value::Array(values) => values.iter_mut().for_each(typeless_fields),
+        _ => {}
+    }
+}
+
+/// Type of a document: its `type` field, or its `_type` for the documents
+/// indexed before this field was added
+pub fn document_type(doc_type: String, value: Option<&serde_json::Value>) -> String {
+    value
+        .and_then(|v| v.get("type"))
+        .and_then(|t| t.as_str())
+        .map(str::to_owned)
+        .unwrap_or(doc_type)
+}
+
 /// takes a ES json blob and build a Place from it
-/// it uses the _type field of ES to know which type of the Place enum to fill
+/// it uses the `type` field (or the _type field of ES) to know which type of the Place enum to fill
 pub fn make_place(
     doc_type: String,
     value: Option<Box<serde_json::Value>>,
     explanation: Option<serde_json::Value>,
 ) -> Option<Place> {
+    let doc_type = document_type(doc_type, value.as_deref());
     let place = value.and_then(|v| {
         fn convert<T>(v: serde_json::Value, f: fn(T) -> Place) -> Option<Place>
         where
@@ -252,15 +802,16 @@ pub fn build_proximity_with_boost(coord: &Coord, boost: f64) -> Query {
 }
 
 pub fn get_indexes(
+    namespace: &str,
     all_data: bool,
     pt_datasets: &[&str],
     poi_datasets: &[&str],
     types: &[&str],
 ) -> Vec<String> {
-    // If we want it all, we return 'munin', which an alias over all public indices,
-    // and 'munin_poi_*' which returns all private indices (for poi).
+    // If we want it all, we return the namespace ('munin'), which an alias over all public
+    // indices, and 'munin_poi_*' which returns all private indices (for poi).
     if all_data {
-        return vec!["munin".to_string(), "munin_poi_*".to_string()];
+        return vec![namespace.to_string(), format!("{}_poi_*", namespace)];
     }
 
     let mut result: Vec<String> = vec![];
@@ -274,27 +825,27 @@ pub fn get_indexes(
     };
 
     if types.is_empty() {
-        result.push("munin_geo_data".to_string());
+        result.push(get_geo_data_index(namespace));
     } else {
         for type_ in types.iter().filter(|&&t| select_type(t)) {
-            result.push(get_indexes_by_type(type_));
+            result.push(get_indexes_by_type(namespace, type_));
         }
     }
 
     if types.is_empty() || types.contains(&"public_transport:stop_area") {
         match pt_datasets {
             [] => (),
-            [dataset] => result.push(format!("munin_stop_{}", dataset)),
+            [dataset] => result.push(format!("{}_stop_{}", namespace, dataset)),
             // TODO Investigate why we assume that if there is more than one dataset, then
             // we just end up using 'munin_global_stops'?
-            _ => result.push("munin_global_stops".to_string()),
+            _ => result.push(get_global_stop_index(namespace)),
         };
     }
 
     if types.is_empty() || types.contains(&"poi") {
         poi_datasets
             .iter()
-            .for_each(|dataset| result.push(format!("munin_poi_{}", dataset)));
+            .for_each(|dataset| result.push(format!("{}_poi_{}", namespace, dataset)));
     }
     result
 }
@@ -322,9 +873,21 @@ impl Rubber {
             timeout,
             nb_insert_threads: 1,
             max_bulk_errors: 0,
+            bulk_retry: BulkRetry::default(),
+            document_model: DocumentModel::default(),
+            nb_kept_indexes: 0,
+            publish_checks: PublishChecks::default(),
+            force_merge: false,
+            namespace: namespace_from_url(cnx),
         }
     }
 
+    /// Change the document model, `typeless` being needed by Elasticsearch 7+
+    pub fn with_document_model(mut self, document_model: DocumentModel) -> Self {
+        self.document_model = document_model;
+        self
+    }
+
     /// Change the number of parallel threads used to insert data into Elasticsearch
     /// during `bulk_index`. Note that if too many insertions are performed in parallel,
     /// Elasticsearch may raise an error.
@@ -338,6 +901,31 @@ impl Rubber {
         self
     }
 
+    /// Change the retries of the documents rejected by a busy Elasticsearch during `bulk_index`
+    pub fn with_bulk_retry(mut self, value: BulkRetry) -> Self {
+        self.bulk_retry = value;
+        self
+    }
+
+    /// Change the number of previous indexes kept (closed) for each doc_type and dataset
+    /// when a new index is published, to be able to roll back to them
+    pub fn with_nb_kept_indexes(mut self, value: usize) -> Self {
+        self.nb_kept_indexes = value;
+        self
+    }
+
+    /// Change the checks made on the new indexes before publishing them
+    pub fn with_publish_checks(mut self, value: PublishChecks) -> Self {
+        self.publish_checks = value;
+        self
+    }
+
+    /// Merge the segments of the new indexes into one before publishing them
+    pub fn with_force_merge(mut self, value: bool) -> Self {
+        self.force_merge = value;
+        self
+    }
+
     pub fn get(&self, path: &str) -> Result<reqwest::blocking::Response, EsError> {
         // Note: a bit duplicate on rs_es because some ES operations are not implemented
         debug!("doing a get on {}", path);
@@ -360,17 +948,15 @@ impl Rubber {
         check_response(result)
     }
 
-    pub fn make_index<T: MimirObject>(
-        &self,
-        dataset: &str,
-        index_settings: &IndexSettings,
-    ) -> Result<TypedIndex<T>, Error> {
-        let index_name = get_date_index_name(&get_main_type_and_dataset_index::<T>(dataset));
-        info!("creating index {}", index_name);
-        self.create_index(&index_name, index_settings)?;
-        Ok(TypedIndex::new(index_name))
+    fn delete(&self, path: &str, body: &str) -> Result<reqwest::blocking::Response, EsError> {
+        // Note: a bit duplicate on rs_es because some ES operations are not implemented
+        debug!("doing a delete on {} with {}", path, body);
+        let url = self.es_client.full_url(path);
+        let result = self.http_client.delete(url).body(body.to_owned()).send()?;
+        check_response(result)
     }
 
+    /// Create an index with the settings for bulk loading: no replica and no refresh
     pub fn create_index(&self, name: &str, index_settings: &IndexSettings) -> Result<(), Error> {
         debug!("creating index");
         // Note: in rs_es it can be done with MappingOperation but for the moment I think
@@ -391,12 +977,15 @@ impl Rubber {
             .pointer_mut("/settings/analysis/filter/synonym_filter/synonyms")
             .unwrap() = serde_json::Value::Array(synonyms);
 
+        // the index is bulk loaded without replicas nor refresh, its final
+        // settings are given by `finish_index`
         *settings_json_value
             .pointer_mut("/settings/number_of_shards")
             .unwrap() = serde_json::Value::from(index_settings.nb_shards);
         *settings_json_value
             .pointer_mut("/settings/number_of_replicas")
-            .unwrap() = serde_json::Value::from(index_settings.nb_replicas);
+            .unwrap() = serde_json::Value::from(0);
+        settings_json_value["settings"]["refresh_interval"] = serde_json::Value::from("-1");
 
         self.put(name, &settings_json_value.to_string())
             .map_err(|e| {
@@ -431,30 +1020,105 @@ impl Rubber {
             })
     }
 
-    pub fn initialize_templates(&self) -> Result<(), Error> {
-        self.create_template(
-            &"template_addr",
-            include_str!("../../../config/addr_settings.json"),
-        )?;
-        self.create_template(
-            &"template_stop",
-            include_str!("../../../config/stop_settings.json"),
-        )?;
-        self.create_template(
-            &"template_admin",
-            include_str!("../../../config/admin_settings.json"),
-        )?;
-        self.create_template(
-            &"template_street",
-            include_str!("../../../config/street_settings.json"),
-        )?;
-        self.create_template(
-            &"template_poi",
-            include_str!("../../../config/poi_settings.json"),
-        )?;
+    /// The templates of this version of mimirsbrunn, as installed for the namespace
+    /// and the document model
+    pub fn templates(&self) -> Result<Vec<(String, serde_json::Value)>, Error> {
+        TEMPLATES
+            .iter()
+            .map(|(name, settings)| {
+                let mut template = versioned_template(settings)?;
+                if let Some(pattern) = template.get("template").and_then(serde_json::Value::as_str)
+                {
+                    template["template"] = namespaced_pattern(&self.namespace, pattern).into();
+                }
+                let template = match self.document_model {
+                    DocumentModel::Typed => template,
+                    DocumentModel::Typeless => typeless_template(&template.to_string())?,
+                };
+                // the templates of the default namespace keep their names
+                let name = if self.namespace == DEFAULT_NAMESPACE {
+                    (*name).to_owned()
+                } else {
+                    format!("{}_{}", self.namespace, name)
+                };
+                Ok((name, template))
+            })
+            .collect()
+    }
+
+    /// The template installed in Elasticsearch, `None` if it is not installed
+    pub fn get_template(&self, name: &str) -> Result<Option<serde_json::Value>, Error> {
+        let res = self
+            .get(&format!("_template/{}", name))
+            .with_context(|_| format!("Error occurred when getting template {}", name))?;
+        match res.status() {
+            StatusCode::OK => {
+                let mut value: serde_json::Value = res.read_response()?;
+                Ok(value.get_mut(name).map(serde_json::Value::take))
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
+        }
+    }
+
+    /// Install the templates, but do not replace a template of a newer version unless `force`
+    pub fn apply_templates(&self, force: bool) -> Result<(), Error> {
+        for (name, template) in self.templates()? {
+            let installed_version = self
+                .get_template(&name)?
+                .and_then(|installed| installed.get("mappings").and_then(template_version));
+            match installed_version {
+                Some(version) if version == TEMPLATE_VERSION => {}
+                Some(version) if version > TEMPLATE_VERSION && !force => bail!(
+                    "the template {} has the version {}, newer than the version {} of this mimirsbrunn",
+                    name,
+                    version,
+                    TEMPLATE_VERSION
+                ),
+                _ => info!(
+                    "installing the version {} of the template {} (installed version: {:?})",
+                    TEMPLATE_VERSION, name, installed_version
+                ),
+            }
+            self.create_template(&name, &template.to_string())?;
+        }
         Ok(())
     }
 
+    pub fn initialize_templates(&self) -> Result<(), Error> {
+        self.apply_templates(false)
+    }
+
+    /// The template version of the indexes matching `index` (an index, an alias or a pattern),
+    /// `None` for an index built before the templates were versioned
+    pub fn get_template_versions(
+        &self,
+        index: &str,
+    ) -> Result<BTreeMap<String, Option<u64>>, Error> {
+        let res = self
+            .get(&format!("{}/_mapping", index))
+            .with_context(|_| format!("Error occurred when getting {}/_mapping", index))?;
+        match res.status() {
+            StatusCode::OK => {
+                let value: serde_json::Value = res.read_response()?;
+                Ok(value
+                    .as_object()
+                    .map(|indexes| {
+                        indexes
+                            .iter()
+                            .map(|(index, mapping)| {
+                                let version = mapping.get("mappings").and_then(template_version);
+                                (index.clone(), version)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default())
+            }
+            StatusCode::NOT_FOUND => Ok(BTreeMap::new()),
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
+        }
+    }
+
     // get all aliases for a doc_type/dataset
     // return a map with each index as key and all their aliases
     pub fn get_all_aliased_index(
@@ -492,27 +1156,26 @@ impl Rubber {
         }
     }
 
-    // get the last indexes for this doc_type/dataset
-    // Note: to be resilient to ghost ES indexes, we return all indexes for this doc_type/dataset
-    // but the new index
-    fn get_last_index<T: MimirObject>(
-        &self,
-        new_index: &TypedIndex<T>,
-        dataset: &str,
-    ) -> Result<Vec<String>, Error> {
-        let base_index = get_main_type_and_dataset_index::<T>(dataset);
-        // we don't want to remove the newly created index
-        Ok(self
-            .get_all_aliased_index(&base_index)?
-            .into_iter()
-            .map(|(k, _)| k)
-            .filter(|i| i.as_str() != new_index.name)
-            .collect())
+    /// Check that an alias exists and points to at least one index
+    pub fn alias_exists(&self, alias: &str) -> Result<bool, Error> {
+        let res = self
+            .get(&format!("_alias/{}", alias))
+            .with_context(|_| format!("Error occurred when getting alias {}", alias))?;
+        match res.status() {
+            StatusCode::OK => {
+                let value: serde_json::Value = res.read_response()?;
+                Ok(value
+                    .as_object()
+                    .map_or(false, |indexes| !indexes.is_empty()))
+            }
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
+        }
     }
 
     pub fn get_address(&mut self, coord: &Coord) -> Result<Vec<Place>, EsError> {
         let types = vec!["house", "street"];
-        let indexes = get_indexes(false, &[], &[], &types);
+        let indexes = get_indexes(&self.namespace, false, &[], &[], &types);
         let indexes = indexes
             .iter()
             .map(|index| index.as_str())
@@ -528,98 +1191,162 @@ impl Rubber {
 
         let timer = ES_REQ_HISTOGRAM.start_timer();
 
-        let timeout = self.timeout.map(|t| format!("{:?}", t));
-        let mut search_query = self.es_client.search_query();
-
-        let search_query = search_query
-            .with_ignore_unavailable(true)
-            .with_indexes(&indexes)
-            .with_query(&query)
-            .with_size(1);
-
-        if let Some(timeout) = &timeout {
-            search_query.with_timeout(timeout.as_str());
-        }
-        let result = search_query.send()?;
+        let result = self.search(&SearchRequest::new(&indexes, &query).with_size(1))?;
 
         timer.observe_duration();
         read_places(result, Some(coord))
     }
 
-    /// publish the index as the new index for this doc_type and this dataset
-    /// move the index alias of the doc_type and the dataset to point to this indexes
-    /// and remove the old index
-    /// visibility: Indicate if the index is public or private
-    pub fn publish_index<T: MimirObject>(
+    /// Search the places of `indexes` matching `query`
+    pub fn search_places(
         &mut self,
-        dataset: &str,
-        index: TypedIndex<T>,
-        visibility: IndexVisibility,
-    ) -> Result<(), Error> {
-        debug!("publishing index");
-
-        // Refresh index before publishing
-        self.es_client
-            .refresh()
-            .with_indexes(&[&index.name])
-            .send()?;
-        let last_indexes = self.get_last_index(&index, dataset)?;
-
-        let dataset_index = get_main_type_and_dataset_index::<T>(dataset);
-        self.alias(&dataset_index, &[index.name], &last_indexes)
-            .with_context(|err| {
-                format!(
-                    "Error occurred when making alias {}: {}",
-                    dataset_index, err
-                )
-            })?;
-
-        let type_index = get_main_type_index::<T>();
-        if let IndexVisibility::Public = visibility {
-            self.alias(&type_index, &[dataset_index], &last_indexes)
-                .with_context(|err| {
-                    format!("Error occurred when making alias {}: {}", type_index, err)
-                })?;
-        }
+        indexes: &[&str],
+        query: &Query,
+        size: u64,
+        coord: Option<&Coord>,
+    ) -> Result<Vec<Place>, EsError> {
+        let result = self.search(
+            &SearchRequest::new(indexes, query)
+                .with_size(size)
+                .with_boundary(false),
+        )?;
+        read_places(result, coord)
+    }
 
-        if let IndexVisibility::Public = visibility {
-            if T::is_geo_data() {
-                self.alias("munin_geo_data", &[type_index], &[])
-                    .context("Error occurred when making alias: munin_geo_data")?;
-                self.alias("munin", &["munin_geo_data".to_string()], &[])
-                    .context("Error occurred when making alias: munin")?;
-            } else {
-                self.alias("munin", &[type_index], &[])
-                    .context("Error occurred when making alias: munin")?;
+    /// Search the documents of `request.indexes`, the unavailable indexes being ignored
+    pub fn search(
+        &mut self,
+        request: &SearchRequest,
+    ) -> Result<SearchHits<serde_json::Value>, EsError> {
+        let timeout = self.timeout.map(|t| format!("{:?}", t));
+        if self.document_model == DocumentModel::Typed {
+            let mut search_query = self.es_client.search_query();
+            let search_query = search_query
+                .with_ignore_unavailable(true)
+                .with_indexes(request.indexes)
+                .with_query(request.query);
+            if let Some(from) = request.from {
+                search_query.with_from(from);
+            }
+            if let Some(size) = request.size {
+                search_query.with_size(size);
+            }
+            if let Some(sort) = request.sort {
+                search_query.with_sort(sort);
+            }
+            if !request.with_boundary {
+                search_query.with_source(Source::exclude(&["boundary"]));
             }
+            // We don't want to clutter the Query URL, so we only add an explanation if asked
+            if request.explain {
+                search_query.with_explain(true);
+            }
+            if let Some(timeout) = &timeout {
+                search_query.with_timeout(timeout.as_str());
+            }
+            return Ok(search_query.send()?.into());
+        }
+        let mut body = serde_json::json!({ "query": request.query });
+        if let Some(from) = request.from {
+            body["from"] = from.into();
+        }
+        if let Some(size) = request.size {
+            body["size"] = size.into();
+        }
+        if let Some(sort) = request.sort {
+            body["sort"] = serde_json::json!(sort);
+        }
+        if !request.with_boundary {
+            body["_source"] = serde_json::json!({ "excludes": ["boundary"] });
+        }
+        if request.explain {
+            body["explain"] = true.into();
+        }
+        if let Some(timeout) = timeout {
+            body["timeout"] = timeout.into();
         }
+        let result: RestSearchResult<serde_json::Value> = self
+            .post(
+                &format!(
+                    "{}/_search?ignore_unavailable=true&rest_total_hits_as_int=true",
+                    request.indexes.join(",")
+                ),
+                &body.to_string(),
+            )?
+            .read_response()?;
+        Ok(result.into())
+    }
 
-        for i in last_indexes {
-            self.delete_index(&i)
-                .with_context(|err| format!("Error occurred when deleting index {}: {}", i, err))?;
+    // Start a scroll of the REST API, sorted by `_doc` since the scan search type used by
+    // rs-es was removed in Elasticsearch 5
+    fn start_scroll<T>(
+        &self,
+        index: &str,
+        query: Option<&Query>,
+    ) -> Result<RestSearchResult<T>, EsError>
+    where
+        for<'de> T: serde::de::Deserialize<'de>,
+    {
+        let mut body = serde_json::json!({ "size": 1000, "sort": ["_doc"] });
+        if let Some(query) = query {
+            body["query"] = serde_json::json!(query);
         }
+        self.post(
+            &format!("{}/_search?scroll=1m&rest_total_hits_as_int=true", index),
+            &body.to_string(),
+        )?
+        .read_response()
+    }
+
+    fn continue_scroll<T>(&self, scroll_id: &str) -> Result<RestSearchResult<T>, EsError>
+    where
+        for<'de> T: serde::de::Deserialize<'de>,
+    {
+        let body = serde_json::json!({ "scroll": "1m", "scroll_id": scroll_id });
+        self.post(
+            "_search/scroll?rest_total_hits_as_int=true",
+            &body.to_string(),
+        )?
+        .read_response()
+    }
+
+    fn clear_scroll(&self, scroll_id: &str) -> Result<(), EsError> {
+        let body = serde_json::json!({ "scroll_id": [scroll_id] });
+        self.delete("_search/scroll", &body.to_string())?;
         Ok(())
     }
 
     /// add a list of new indexes to the alias
     /// remove a list of indexes from the alias
     pub fn alias(&self, alias: &str, add: &[String], remove: &[String]) -> Result<(), Error> {
-        info!(
-            "for {}, adding alias {:?}, removing {:?}",
-            alias, add, remove
-        );
-        let add_operations = add.iter().map(|x| AliasOperation {
+        let pairs = |indexes: &[String]| {
+            indexes
+                .iter()
+                .map(|index| (alias.to_owned(), index.clone()))
+                .collect::<Vec<_>>()
+        };
+        self.update_aliases(&pairs(add), &pairs(remove))
+    }
+
+    /// add and remove `(alias, index)` pairs in one atomic operation
+    pub fn update_aliases(
+        &self,
+        add: &[(String, String)],
+        remove: &[(String, String)],
+    ) -> Result<(), Error> {
+        info!("adding aliases {:?}, removing {:?}", add, remove);
+        let add_operations = add.iter().map(|(alias, index)| AliasOperation {
             remove: None,
             add: Some(AliasParameter {
-                index: x.clone(),
-                alias: alias.to_string(),
+                index: index.clone(),
+                alias: alias.clone(),
             }),
         });
-        let remove_operations = remove.iter().map(|x| AliasOperation {
+        let remove_operations = remove.iter().map(|(alias, index)| AliasOperation {
             add: None,
             remove: Some(AliasParameter {
-                index: x.clone(),
-                alias: alias.to_string(),
+                index: index.clone(),
+                alias: alias.clone(),
             }),
         });
         let operations = AliasOperations {
@@ -631,7 +1358,158 @@ impl Rubber {
             .context("Error occurred when POSTing: _alias")?;
         match res.status() {
             StatusCode::OK => Ok(()),
-            _ => bail!("failed to post aliases for {}: {:?}", alias, res),
+            _ => bail!("failed to post aliases {:?}: {:?}", add, res),
+        }
+    }
+
+    /// Close an index, it is kept but can no longer be searched
+    pub fn close_index(&self, index: &str) -> Result<(), Error> {
+        debug!("closing index {}", index);
+        let res = self
+            .post(&format!("{}/_close", index), "")
+            .with_context(|_| format!("Error occurred when closing index {}", index))?;
+        match res.status() {
+            StatusCode::OK => Ok(()),
+            _ => bail!("failed to close index {}: {:?}", index, res),
+        }
+    }
+
+    pub fn open_index(&self, index: &str) -> Result<(), Error> {
+        debug!("opening index {}", index);
+        let res = self
+            .post(&format!("{}/_open", index), "")
+            .with_context(|_| format!("Error occurred when opening index {}", index))?;
+        match res.status() {
+            StatusCode::OK => Ok(()),
+            _ => bail!("failed to open index {}: {:?}", index, res),
+        }
+    }
+
+    /// Give its final settings to an index created for bulk loading: its number of replicas
+    /// and the refresh interval of its template. The segments are merged before if `force_merge`,
+    /// and the replicas are waited for.
+    pub fn finish_index(&self, index: &str, index_settings: &IndexSettings) -> Result<(), Error> {
+        if self.force_merge {
+            info!("merging the segments of {}", index);
+            self.post(&format!("{}/_forcemerge?max_num_segments=1", index), "")
+                .with_context(|_| {
+                    format!("Error occurred when merging the segments of {}", index)
+                })?;
+        }
+        let settings = serde_json::json!({
+            "index": {
+                "number_of_replicas": index_settings.nb_replicas,
+                "refresh_interval": template_refresh_interval(&self.namespace, index),
+            }
+        });
+        let res = self
+            .put(&format!("{}/_settings", index), &settings.to_string())
+            .with_context(|_| format!("Error occurred when updating the settings of {}", index))?;
+        if res.status() != StatusCode::OK {
+            bail!("failed to update the settings of {}: {:?}", index, res);
+        }
+        self.wait_for_replicas(index, index_settings.nb_replicas)
+    }
+
+    /// Wait until the shards of an index are allocated: all of them (green status), or only
+    /// the primary ones (yellow status) if there are not enough data nodes for the replicas
+    fn wait_for_replicas(&self, index: &str, nb_replicas: usize) -> Result<(), Error> {
+        let cluster: serde_json::Value = self
+            .get("_cluster/health")
+            .context("Error occurred when getting the cluster health")?
+            .read_response()?;
+        let nb_data_nodes = cluster
+            .get("number_of_data_nodes")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(1);
+        let status = if (nb_replicas as u64) < nb_data_nodes {
+            "green"
+        } else {
+            warn!(
+                "{} data nodes, not enough for the {} replicas of {}",
+                nb_data_nodes, nb_replicas, index
+            );
+            "yellow"
+        };
+        debug!("waiting for the {} status of {}", status, index);
+        self.get(&format!(
+            "_cluster/health/{}?wait_for_status={}&timeout={}",
+            index, status, WAIT_FOR_REPLICAS_TIMEOUT
+        ))
+        .with_context(|_| {
+            format!(
+                "the status of {} is not {} after {}",
+                index, status, WAIT_FOR_REPLICAS_TIMEOUT
+            )
+        })?;
+        Ok(())
+    }
+
+    /// get the number of documents of an index
+    pub fn count_documents(&self, index: &str) -> Result<u64, Error> {
+        let res = self
+            .get(&format!("{}/_count", index))
+            .with_context(|_| format!("Error occurred when counting documents of {}", index))?;
+        match res.status() {
+            StatusCode::OK => {
+                let value: serde_json::Value = res.read_response()?;
+                value
+                    .get("count")
+                    .and_then(|c| c.as_u64())
+                    .ok_or_else(|| format_err!("invalid count of documents of {}", index))
+            }
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
+        }
+    }
+
+    /// get the number of shards and of replicas of an index
+    pub fn get_index_settings(&self, index: &str) -> Result<IndexSettings, Error> {
+        let res = self
+            .get(&format!("{}/_settings", index))
+            .with_context(|_| format!("Error occurred when getting {}/_settings", index))?;
+        match res.status() {
+            StatusCode::OK => {
+                let value: serde_json::Value = res.read_response()?;
+                // Elasticsearch gives back the settings by index name and their values as strings
+                let settings = value
+                    .as_object()
+                    .and_then(|indexes| indexes.values().next())
+                    .and_then(|index| index.pointer("/settings/index"));
+                let setting = |name: &str| {
+                    settings
+                        .and_then(|settings| settings.get(name))
+                        .and_then(|v| v.as_str())
+                        .and_then(|v| v.parse().ok())
+                        .ok_or_else(|| format_err!("invalid {} of {}", name, index))
+                };
+                Ok(IndexSettings {
+                    nb_shards: setting("number_of_shards")?,
+                    nb_replicas: setting("number_of_replicas")?,
+                })
+            }
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
+        }
+    }
+
+    /// get the closed indexes of a doc_type/dataset
+    pub fn get_closed_indexes(&self, base_index: &str) -> Result<Vec<String>, Error> {
+        let res = self
+            .get(&format!("_cat/indices/{}_*?h=status,index", base_index))
+            .with_context(|_| format!("Error occurred when getting {}_* indices", base_index))?;
+        match res.status() {
+            StatusCode::OK => Ok(res
+                .text()?
+                .lines()
+                .filter_map(|line| {
+                    let mut columns = line.split_whitespace();
+                    match (columns.next(), columns.next()) {
+                        (Some("close"), Some(index)) => Some(index.to_owned()),
+                        _ => None,
+                    }
+                })
+                .collect()),
+            StatusCode::NOT_FOUND => Ok(vec![]),
+            _ => Err(format_err!("invalid elasticsearch response: {:?}", res)),
         }
     }
 
@@ -659,7 +1537,12 @@ impl Rubber {
 
         struct BulkResultCount {
             nb_ok: usize,
+            /// documents refused by Elasticsearch, e.g. because they do not match the mapping
             nb_errors: usize,
+            /// documents still rejected by a busy Elasticsearch after all the retries
+            nb_transient_errors: usize,
+            /// documents sent again
+            nb_retries: usize,
         }
 
         impl BulkResultCount {
@@ -667,6 +1550,8 @@ impl Rubber {
                 BulkResultCount {
                     nb_ok: 0,
                     nb_errors: 0,
+                    nb_transient_errors: 0,
+                    nb_retries: 0,
                 }
             }
         }
@@ -677,6 +1562,8 @@ impl Rubber {
                 Self {
                     nb_ok: self.nb_ok + rhs.nb_ok,
                     nb_errors: self.nb_errors + rhs.nb_errors,
+                    nb_transient_errors: self.nb_transient_errors + rhs.nb_transient_errors,
+                    nb_retries: self.nb_retries + rhs.nb_retries,
                 }
             }
         }
@@ -684,122 +1571,171 @@ impl Rubber {
         let chunk_size = 1000;
         let index_name = index.name.to_owned();
         let client = self.es_client.clone();
+        let mapping_type = self.document_model.mapping_type(T::doc_type());
+        let retry = self.bulk_retry.clone();
 
         iter.pack(chunk_size)
             .par_map(|v| {
                 v.into_iter()
                     .map(|v| {
-                        v.es_id()
-                            .into_iter()
-                            .fold(Action::index(v), |action, id| action.with_id(id))
+                        let id = v.es_id();
+                        let document = Document {
+                            doc_type: T::doc_type(),
+                            object: v,
+                        };
+                        id.into_iter()
+                            .fold(Action::index(document), |action, id| action.with_id(id))
                     })
                     .collect::<Vec<_>>()
             })
             .with_nb_threads(self.nb_insert_threads)
-            .par_map(move |chunk| {
-                let res = client
-                    .clone()
-                    .bulk(&chunk)
-                    .with_index(&index_name)
-                    .with_doc_type(T::doc_type())
-                    .send()?;
-
-                let mut nb_errors_in_chunk = 0;
-
-                if res.errors {
-                    res.items
-                        .iter()
-                        .filter(|action_res| action_res.inner.status != 200)
-                        .for_each(|action_res| {
-                            nb_errors_in_chunk += 1;
-                            // We only display a warning if it brings some information, otherwise
-                            // the log is distracting
-                            if let Some(ref error) = action_res.inner.error {
-                                let error = serde_json::to_string(error)
-                                    .unwrap_or_else(|_| String::from("Could not serialize error"));
-
-                                warn!(
-                                    "An error occured while importing {} '{}'. Status {}: {}",
-                                    T::doc_type(),
-                                    action_res.inner.id,
-                                    action_res.inner.status,
-                                    error
-                                );
-                            }
-                        });
+            .par_map(move |mut chunk| {
+                let mut counts = BulkResultCount::zero();
+                let mut attempt = 0;
+                loop {
+                    let res = match client
+                        .clone()
+                        .bulk(&chunk)
+                        .with_index(&index_name)
+                        .with_doc_type(mapping_type)
+                        .send()
+                    {
+                        Ok(res) => res,
+                        Err(err) if is_transient_error(&err) && attempt < retry.max_retries => {
+                            warn!(
+                                "bulk request of {} {} failed, it will be retried: {}",
+                                chunk.len(),
+                                T::doc_type(),
+                                err
+                            );
+                            counts.nb_retries += chunk.len();
+                            thread::sleep(retry.delay(attempt));
+                            attempt += 1;
+                            continue;
+                        }
+                        Err(err) => return Err(Error::from(err)),
+                    };
+
+                    if !res.errors {
+                        counts.nb_ok += chunk.len();
+                        return Ok(counts);
+                    }
+
+                    // the items of the response are in the order of the actions
+                    let mut rejected = vec![];
+                    for (action, action_res) in chunk.into_iter().zip(res.items.iter()) {
+                        let status = action_res.inner.status;
+                        if (200..300).contains(&status) {
+                            counts.nb_ok += 1;
+                            continue;
+                        }
+                        let transient = is_transient_status(status);
+                        if transient && attempt < retry.max_retries {
+                            rejected.push(action);
+                            continue;
+                        }
+                        if transient {
+                            counts.nb_transient_errors += 1;
+                        } else {
+                            counts.nb_errors += 1;
+                        }
+                        // We only display a warning if it brings some information, otherwise
+                        // the log is distracting
+                        if let Some(ref error) = action_res.inner.error {
+                            let error = serde_json::to_string(error)
+                                .unwrap_or_else(|_| String::from("Could not serialize error"));
+
+                            warn!(
+                                "An error occured while importing {} '{}'. Status {}: {}",
+                                T::doc_type(),
+                                action_res.inner.id,
+                                status,
+                                error
+                            );
+                        }
+                    }
+                    if rejected.is_empty() {
+                        return Ok(counts);
+                    }
+                    debug!(
+                        "{} {} rejected by a busy Elasticsearch, they will be retried",
+                        rejected.len(),
+                        T::doc_type()
+                    );
+                    counts.nb_retries += rejected.len();
+                    thread::sleep(retry.delay(attempt));
+                    attempt += 1;
+                    chunk = rejected;
                 }
-                Ok(BulkResultCount {
-                    nb_ok: chunk.len() - nb_errors_in_chunk,
-                    nb_errors: nb_errors_in_chunk,
-                })
             })
             .try_fold(BulkResultCount::zero(), |sum, res| {
-                let result = res.map(|chunk_len| sum + chunk_len);
+                let result = res.map(|chunk_counts| sum + chunk_counts);
                 if let Ok(ref counts) = result {
-                    if counts.nb_errors > self.max_bulk_errors {
-                        return Err(format_err!("too many errors during bulk_index"));
+                    if counts.nb_errors + counts.nb_transient_errors > self.max_bulk_errors {
+                        return Err(format_err!(
+                            "too many errors during bulk_index: {} invalid documents, \
+                             {} documents rejected after {} retries",
+                            counts.nb_errors,
+                            counts.nb_transient_errors,
+                            self.bulk_retry.max_retries
+                        ));
                     }
                 }
                 result
             })
-            .map(|counts| counts.nb_ok)
-    }
-
-    /// Shortcut to `index` for a public index
-    pub fn public_index<T, I>(
-        &mut self,
-        dataset: &str,
-        index_settings: &IndexSettings,
-        iter: I,
-    ) -> Result<usize, Error>
-    where
-        T: MimirObject + std::marker::Send + 'static,
-        I: Iterator<Item = T>,
-    {
-        self.index(dataset, IndexVisibility::Public, index_settings, iter)
-    }
-
-    /// Shortcut to `index` for a private index
-    pub fn private_index<T, I>(
-        &mut self,
-        dataset: &str,
-        index_settings: &IndexSettings,
-        iter: I,
-    ) -> Result<usize, Error>
-    where
-        T: MimirObject + std::marker::Send + 'static,
-        I: Iterator<Item = T>,
-    {
-        self.index(dataset, IndexVisibility::Private, index_settings, iter)
+            .map(|counts| {
+                info!(
+                    "{} {} indexed in {}: {} invalid documents, {} documents rejected \
+                     after {} retries, {} documents retried",
+                    counts.nb_ok,
+                    T::doc_type(),
+                    index.name,
+                    counts.nb_errors,
+                    counts.nb_transient_errors,
+                    self.bulk_retry.max_retries,
+                    counts.nb_retries
+                );
+                counts.nb_ok
+            })
     }
 
-    /// add all the element of 'iter' into elasticsearch
-    ///
-    /// To have zero downtime:
-    /// first all the elements are added in a temporary index and when all has been indexed
-    /// the index is published and the old index is removed
-    pub fn index<T, I>(
+    /// Delete documents of an index by id, return the number of documents deleted
+    pub fn delete_documents<T: MimirObject>(
         &mut self,
-        dataset: &str,
-        visibility: IndexVisibility,
-        index_settings: &IndexSettings,
-        iter: I,
-    ) -> Result<usize, Error>
-    where
-        T: MimirObject + std::marker::Send + 'static,
-        I: Iterator<Item = T>,
-    {
-        // TODO better error handling
-        let index = self
-            .make_index(dataset, index_settings)
-            .with_context(|err| format!("Error occurred when making index {}: {}", dataset, err))?;
-        let nb_elements = self.bulk_index(&index, iter)?;
-        self.publish_index(dataset, index, visibility)?;
-        Ok(nb_elements)
-    }
+        index: &TypedIndex<T>,
+        ids: &[String],
+    ) -> Result<usize, Error> {
+        use rs_es::operations::bulk::Action;
 
-    pub fn get_all_admins(&mut self) -> Result<Vec<Admin>, rs_es::error::EsError> {
-        self.get_all_objects_from_index(&get_main_type_index::<Admin>())
+        let mapping_type = self.document_model.mapping_type(T::doc_type());
+        let mut nb_deleted = 0;
+        for chunk in ids.chunks(1000) {
+            let actions = chunk
+                .iter()
+                .map(|id| Action::<serde_json::Value>::delete(id.as_str()))
+                .collect::<Vec<_>>();
+            let res = self
+                .es_client
+                .bulk(&actions)
+                .with_index(&index.name)
+                .with_doc_type(mapping_type)
+                .send()?;
+            for action_res in &res.items {
+                // a document already deleted is not an error
+                if (200..300).contains(&action_res.inner.status) {
+                    nb_deleted += 1;
+                } else if action_res.inner.status != 404 {
+                    bail!(
+                        "impossible to delete {} '{}' of {}: status {}",
+                        T::doc_type(),
+                        action_res.inner.id,
+                        index.name,
+                        action_res.inner.status
+                    );
+                }
+            }
+        }
+        Ok(nb_deleted)
     }
 
     pub fn get_all_objects_from_index<T>(
@@ -810,12 +1746,32 @@ impl Rubber {
         for<'de> T: MimirObject + serde::de::Deserialize<'de> + std::fmt::Debug,
     {
         let mut result: Vec<T> = vec![];
+        let query = Query::build_term(self.document_model.type_field(), T::doc_type()).build();
+        if self.document_model == DocumentModel::Typeless {
+            let mut page = self.start_scroll::<T>(index, Some(&query))?;
+            loop {
+                let scroll_id = page.scroll_id.unwrap_or_default();
+                if page.hits.hits.is_empty() {
+                    self.clear_scroll(&scroll_id)?;
+                    break;
+                }
+                result.extend(
+                    page.hits
+                        .hits
+                        .into_iter()
+                        .filter_map(|hit| hit.source)
+                        .map(|ad| *ad),
+                );
+                page = self.continue_scroll(&scroll_id)?;
+            }
+            return Ok(result);
+        }
         let mut scan: ScanResult<T> = self
             .es_client
             .search_query()
             .with_indexes(&[&index])
             .with_size(1000)
-            .with_types(&[&T::doc_type()])
+            .with_query(&query)
             .scan(&Duration::minutes(1))?;
         loop {
             let page = scan.scroll(&mut self.es_client, &Duration::minutes(1))?;
@@ -833,6 +1789,153 @@ impl Rubber {
         scan.close(&mut self.es_client)?;
         Ok(result)
     }
+
+    /// Iterate over all the places of an index with the scroll API,
+    /// one page at a time, without loading the whole index in memory
+    pub fn scroll_places(self, index: &str) -> Result<PlaceScroll, EsError> {
+        self.scroll(index, |hit| make_place(hit.doc_type, hit.source, None))
+    }
+
+    /// Iterate over the raw JSON documents of an index with the scroll API,
+    /// with all their fields (including the boundaries of the admins)
+    pub fn scroll_documents(self, index: &str) -> Result<DocumentScroll, EsError> {
+        self.scroll(index, |hit| hit.source.map(|source| *source))
+    }
+
+    fn scroll<T>(
+        mut self,
+        index: &str,
+        convert: fn(SearchHit<serde_json::Value>) -> Option<T>,
+    ) -> Result<Scroll<T>, EsError> {
+        if self.document_model == DocumentModel::Typeless {
+            let page = self.start_scroll(index, None)?;
+            let mut scroll = Scroll {
+                rubber: self,
+                cursor: None,
+                page: vec![].into_iter(),
+                convert,
+            };
+            scroll.read_page(page);
+            return Ok(scroll);
+        }
+        let scan: ScanResult<serde_json::Value> = self
+            .es_client
+            .search_query()
+            .with_indexes(&[&index])
+            .with_size(1000)
+            .scan(&Duration::minutes(1))?;
+        Ok(Scroll {
+            rubber: self,
+            cursor: Some(ScrollCursor::Scan(scan)),
+            page: vec![].into_iter(),
+            convert,
+        })
+    }
+}
+
+/// Places of an index, given by `Rubber::scroll_places`
+pub type PlaceScroll = Scroll<Place>;
+
+/// Documents of an index, given by `Rubber::scroll_documents`
+pub type DocumentScroll = Scroll<serde_json::Value>;
+
+/// Hits of an index read with the scroll API.
+///
+/// The scroll is closed at the end of the iteration or when dropped.
+pub struct Scroll<T> {
+    rubber: Rubber,
+    cursor: Option<ScrollCursor>,
+    page: std::vec::IntoIter<T>,
+    convert: fn(SearchHit<serde_json::Value>) -> Option<T>,
+}
+
+// The scan of rs-es on the typed clusters, or the id of the scroll of the REST API on the
+// typeless clusters
+enum ScrollCursor {
+    Scan(ScanResult<serde_json::Value>),
+    Rest(String),
+}
+
+impl<T> Scroll<T> {
+    fn close(&mut self) {
+        let res = match self.cursor.take() {
+            Some(ScrollCursor::Scan(scan)) => scan.close(&mut self.rubber.es_client).map(|_| ()),
+            Some(ScrollCursor::Rest(scroll_id)) => self.rubber.clear_scroll(&scroll_id),
+            None => Ok(()),
+        };
+        if let Err(err) = res {
+            warn!("impossible to close the scroll: {}", err);
+        }
+    }
+
+    // Keep the hits of a page of the REST API and the id of the scroll to read the next one
+    fn read_page(&mut self, page: RestSearchResult<serde_json::Value>) {
+        if let Some(scroll_id) = page.scroll_id {
+            self.cursor = Some(ScrollCursor::Rest(scroll_id));
+        }
+        if page.hits.hits.is_empty() {
+            self.close();
+        }
+        self.page = page
+            .hits
+            .hits
+            .into_iter()
+            .filter_map(self.convert)
+            .collect::<Vec<_>>()
+            .into_iter();
+    }
+}
+
+impl<T> Iterator for Scroll<T> {
+    type Item = Result<T, EsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.page.next() {
+                return Some(Ok(item));
+            }
+            let page = match self.cursor.as_mut()? {
+                ScrollCursor::Scan(scan) => scan
+                    .scroll(&mut self.rubber.es_client, &Duration::minutes(1))
+                    .map(|page| {
+                        page.hits
+                            .hits
+                            .into_iter()
+                            .map(SearchHit::from)
+                            .collect::<Vec<_>>()
+                    }),
+                ScrollCursor::Rest(scroll_id) => match self.rubber.continue_scroll(scroll_id) {
+                    Ok(page) => {
+                        self.read_page(page);
+                        continue;
+                    }
+                    Err(err) => Err(err),
+                },
+            };
+            let page = match page {
+                Ok(page) => page,
+                Err(err) => {
+                    self.close();
+                    return Some(Err(err));
+                }
+            };
+            if page.is_empty() {
+                self.close();
+                return None;
+            }
+            self.page = page
+                .into_iter()
+                .filter_map(self.convert)
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+impl<T> Drop for Scroll<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
 
 #[cfg(test)]
@@ -840,6 +1943,174 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    pub fn test_bulk_retry_delay() {
+        let retry = BulkRetry {
+            max_retries: 10,
+            initial_delay: time::Duration::from_millis(100),
+            max_delay: time::Duration::from_secs(1),
+        };
+        for _ in 0..100 {
+            let delay = retry.delay(0);
+            assert!(delay >= time::Duration::from_millis(50));
+            assert!(delay <= time::Duration::from_millis(100));
+            let delay = retry.delay(2);
+            assert!(delay >= time::Duration::from_millis(200));
+            assert!(delay <= time::Duration::from_millis(400));
+            // the delay is capped
+            let delay = retry.delay(40);
+            assert!(delay >= time::Duration::from_millis(500));
+            assert!(delay <= time::Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    pub fn test_transient_status() {
+        assert!(is_transient_status(429));
+        assert!(is_transient_status(503));
+        assert!(!is_transient_status(400));
+        assert!(!is_transient_status(404));
+    }
+
+    #[test]
+    pub fn test_error_status() {
+        assert_eq!(
+            error_status("503 Service Unavailable - {\"error\":\"busy\"}"),
+            Some(503)
+        );
+        assert_eq!(error_status("400 Bad Request - {}"), Some(400));
+        assert_eq!(error_status("expected value at line 1 column 1"), None);
+        assert_eq!(error_status("1000 documents"), None);
+    }
+
+    #[test]
+    pub fn test_typeless_template() {
+        let template =
+            typeless_template(include_str!("../../../config/admin_settings.json")).unwrap();
+        assert_eq!(
+            template["index_patterns"],
+            serde_json::json!(["munin_admin_*"])
+        );
+        assert!(template.get("template").is_none());
+
+        let mapping = &template["mappings"];
+        assert!(mapping.get("admin").is_none());
+        assert!(mapping.get("_all").is_none());
+        let properties = &mapping["properties"];
+        assert_eq!(properties["type"]["type"], "keyword");
+        assert_eq!(properties["id"], serde_json::json!({"type": "keyword"}));
+        assert_eq!(properties["level"]["index"], false);
+        assert_eq!(properties["full_label"]["type"], "text");
+        assert_eq!(properties["full_label"]["index"], false);
+        assert_eq!(properties["full_label"]["norms"], false);
+        assert_eq!(
+            properties["coord"],
+            serde_json::json!({"type": "geo_point"})
+        );
+        assert_eq!(
+            template["settings"]["analysis"]["tokenizer"]["my_ngram_tokenizer"]["type"],
+            "ngram"
+        );
+        // no Elasticsearch 2 field definition is left
+        let template = template.to_string();
+        for removed in &["\"string\"", "\"not_analyzed\"", "\"nGram\"", "\"no\""] {
+            assert!(!template.contains(removed), "{} is still used", removed);
+        }
+    }
+
+    #[test]
+    pub fn test_template_version() {
+        let settings = include_str!("../../../config/addr_settings.json");
+        let template = versioned_template(settings).unwrap();
+        assert_eq!(
+            template_version(&template["mappings"]),
+            Some(TEMPLATE_VERSION)
+        );
+        let typeless = typeless_template(&template.to_string()).unwrap();
+        assert_eq!(
+            template_version(&typeless["mappings"]),
+            Some(TEMPLATE_VERSION)
+        );
+
+        let unversioned: serde_json::Value = serde_json::from_str(settings).unwrap();
+        assert_eq!(template_version(&unversioned["mappings"]), None);
+    }
+
+    #[test]
+    pub fn test_template_refresh_interval() {
+        assert_eq!(
+            template_refresh_interval("munin", "munin_admin_fr_20201203_101010_000000"),
+            serde_json::json!("60s")
+        );
+        assert_eq!(
+            template_refresh_interval("munin", "munin_street_fr_20201203_101010_000000"),
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            template_refresh_interval("munin", "munin_global_stops_20201203_101010_000000"),
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            template_refresh_interval("staging", "staging_admin_fr_20201203_101010_000000"),
+            serde_json::json!("60s")
+        );
+        assert_eq!(
+            template_refresh_interval("staging", "munin_admin_fr_20201203_101010_000000"),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    pub fn test_document_type() {
+        let value = serde_json::json!({"type": "admin", "id": "admin:fr:75056"});
+        assert_eq!(document_type("_doc".to_owned(), Some(&value)), "admin");
+        let value = serde_json::json!({"id": "admin:fr:75056"});
+        assert_eq!(document_type("admin".to_owned(), Some(&value)), "admin");
+    }
+
+    #[test]
+    pub fn test_max_drop() {
+        let checks = |value| serde_json::from_value::<PublishChecks>(value);
+        assert_eq!(
+            checks(serde_json::json!({"max_drop": 0.1}))
+                .unwrap()
+                .max_drop,
+            Some(0.1)
+        );
+        assert_eq!(checks(serde_json::json!({})).unwrap().max_drop, None);
+        assert!(checks(serde_json::json!({"max_drop": 10})).is_err());
+        assert!(checks(serde_json::json!({"max_drop": -0.1})).is_err());
+    }
+
+    #[test]
+    pub fn test_canary_from_str() {
+        assert_eq!(
+            "20 rue hector malot=addr:2.37;48.85".parse(),
+            Ok(Canary {
+                doc_type: None,
+                query: "20 rue hector malot".to_owned(),
+                id: "addr:2.37;48.85".to_owned(),
+            })
+        );
+        assert_eq!(
+            "admin:Paris=admin:osm:relation:7444".parse(),
+            Ok(Canary {
+                doc_type: Some("admin".to_owned()),
+                query: "Paris".to_owned(),
+                id: "admin:osm:relation:7444".to_owned(),
+            })
+        );
+        // a query with a colon is not a type
+        assert_eq!(
+            "gare: quai 1=stop:1".parse::<Canary>().map(|c| c.doc_type),
+            Ok(None)
+        );
+        assert!("admin:=admin:osm:relation:7444".parse::<Canary>().is_err());
+        assert!("rue hector malot".parse::<Canary>().is_err());
+        assert!("=addr:2.37;48.85".parse::<Canary>().is_err());
+        assert!("rue hector malot=".parse::<Canary>().is_err());
+    }
+
     #[test]
     pub fn test_valid_url() {
         Rubber::new("http://localhost:9200");
@@ -857,16 +2128,19 @@ mod tests {
     fn test_get_indexes_impl() {
         // all_data
         assert_eq!(
-            get_indexes(true, &[], &[], &[]),
+            get_indexes("munin", true, &[], &[], &[]),
             vec!["munin", "munin_poi_*"]
         );
 
         // no dataset and no types
-        assert_eq!(get_indexes(false, &[], &[], &[]), vec!["munin_geo_data"]);
+        assert_eq!(
+            get_indexes("munin", false, &[], &[], &[]),
+            vec!["munin_geo_data"]
+        );
 
         // dataset fr + no types
         assert_eq!(
-            get_indexes(false, &["fr"], &[], &[]),
+            get_indexes("munin", false, &["fr"], &[], &[]),
             vec!["munin_geo_data", "munin_stop_fr"]
         );
 
@@ -874,6 +2148,7 @@ mod tests {
         // => munin_stop is not included
         assert_eq!(
             get_indexes(
+                "munin",
                 false,
                 &[],
                 &[],
@@ -890,13 +2165,14 @@ mod tests {
 
         // no dataset fr + type public_transport:stop_area only
         assert_eq!(
-            get_indexes(false, &[], &[], &["public_transport:stop_area"]),
+            get_indexes("munin", false, &[], &[], &["public_transport:stop_area"]),
             Vec::<String>::new()
         );
 
         // dataset fr + types poi, city, street, house and public_transport:stop_area
         assert_eq!(
             get_indexes(
+                "munin",
                 false,
                 &["fr"],
                 &[],
@@ -920,7 +2196,13 @@ mod tests {
         // dataset fr types poi, city, street, house without public_transport:stop_area
         //  => munin_stop_fr is not included
         assert_eq!(
-            get_indexes(false, &["fr"], &[], &["poi", "city", "street", "house"],),
+            get_indexes(
+                "munin",
+                false,
+                &["fr"],
+                &[],
+                &["poi", "city", "street", "house"],
+            ),
             vec!["munin_poi", "munin_admin", "munin_street", "munin_addr"]
         );
 
@@ -928,6 +2210,7 @@ mod tests {
         //  => munin_poi should not be included, and munin_poi_mti is included
         assert_eq!(
             get_indexes(
+                "munin",
                 false,
                 &["fr"],
                 &["mti"],
@@ -935,5 +2218,56 @@ mod tests {
             ),
             vec!["munin_admin", "munin_street", "munin_addr", "munin_poi_mti"]
         );
+
+        // another namespace
+        assert_eq!(
+            get_indexes("staging", true, &[], &[], &[]),
+            vec!["staging", "staging_poi_*"]
+        );
+        assert_eq!(
+            get_indexes("staging", false, &["fr", "be"], &["mti"], &[]),
+            vec![
+                "staging_geo_data",
+                "staging_global_stops",
+                "staging_poi_mti"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_namespace_from_url() {
+        assert_eq!(namespace_from_url("http://localhost:9200/munin"), "munin");
+        assert_eq!(
+            namespace_from_url("http://localhost:9200/staging"),
+            "staging"
+        );
+        assert_eq!(namespace_from_url("http://localhost:9200/"), "munin");
+        assert_eq!(namespace_from_url("http://localhost:9200"), "munin");
+        assert_eq!(namespace_from_url("localhost:9200"), "munin");
+    }
+
+    #[test]
+    fn test_split_date_index_name() {
+        let (base, date) =
+            split_date_index_name("munin_addr_fr_20201015_101734_532478911").unwrap();
+        assert_eq!(base, "munin_addr_fr");
+        assert_eq!(date.to_rfc3339(), "2020-10-15T10:17:34+00:00");
+        let (base, _) =
+            split_date_index_name("munin_poi_my_dataset_20201015_101734_532478911").unwrap();
+        assert_eq!(base, "munin_poi_my_dataset");
+        let index = get_date_index_name("munin_global_stops");
+        assert_eq!(
+            split_date_index_name(&index).unwrap().0,
+            "munin_global_stops"
+        );
+        assert_eq!(split_date_index_name("munin_addr_fr"), None);
+        assert_eq!(
+            split_date_index_name("munin_addr_fr_20201015_101734_"),
+            None
+        );
+        assert_eq!(
+            split_date_index_name("munin_addr_fr_2020_101734_532478911"),
+            None
+        );
     }
 }
@@ -39,7 +39,8 @@ impl<'a> ElasticSearchWrapper<'a> {
     pub fn new(docker_wrapper: &DockerWrapper) -> ElasticSearchWrapper<'_> {
         let mut es_wrapper = ElasticSearchWrapper {
             docker_wrapper,
-            rubber: mimir::rubber::Rubber::new(&docker_wrapper.host()),
+            rubber: mimir::rubber::Rubber::new(&docker_wrapper.host())
+                .with_document_model(docker_wrapper.document_model()),
         };
         es_wrapper.init();
         es_wrapper
@@ -168,18 +169,45 @@ pub struct BragiHandler {
 
 impl BragiHandler {
     pub fn new(url: String) -> BragiHandler {
-        let ctx = bragi::Context::try_from(&bragi::Args {
+        Self::new_with_api_keys(url, None)
+    }
+
+    pub fn new_with_api_keys(
+        url: String,
+        api_keys: Option<bragi::api_key_middleware::ApiKeysConfig>,
+    ) -> BragiHandler {
+        let args = bragi::Args {
             connection_string: url,
             ..Default::default()
-        })
-        .expect("failed to create bragi Context");
+        };
+        Self::new_with_args(args, api_keys)
+    }
+
+    /// A bragi for the document model of the Elasticsearch of `es_wrapper`
+    pub fn new_for(es_wrapper: &ElasticSearchWrapper<'_>) -> BragiHandler {
+        let args = bragi::Args {
+            connection_string: es_wrapper.host(),
+            document_model: es_wrapper.docker_wrapper.document_model(),
+            ..Default::default()
+        };
+        Self::new_with_args(args, None)
+    }
+
+    fn new_with_args(
+        args: bragi::Args,
+        api_keys: Option<bragi::api_key_middleware::ApiKeysConfig>,
+    ) -> BragiHandler {
+        let ctx = bragi::Context::try_from(&args).expect("failed to create bragi Context");
 
         let prometheus = bragi::prometheus_middleware::PrometheusMetrics::new("bragi", "/metrics");
+        let api_keys = bragi::api_key_middleware::ApiKeys::new(api_keys);
         let srv = actix_http_test::TestServer::new(move || {
             actix_http::HttpService::new(
                 actix_web::App::new()
                     .data(ctx.clone())
-                    .wrap(actix_cors::Cors::new().allowed_methods(vec!["GET"]))
+                    .wrap(api_keys.clone())
+                    .wrap(bragi::server::build_cors(&args))
+                    .wrap(bragi::server::build_compress(&args))
                     .wrap(prometheus.clone())
                     .wrap(actix_web::middleware::Logger::default())
                     .configure(bragi::server::configure_server)
@@ -1,7 +1,8 @@
 use crate::Error;
 use failure::ResultExt;
 use flate2::read::GzDecoder;
-use mimir::rubber::{IndexSettings, IndexVisibility, Rubber};
+use mimir::backend::SearchBackend;
+use mimir::rubber::{IndexSettings, IndexVisibility};
 use mimir::Addr;
 use par_map::ParMap;
 use serde::de::DeserializeOwned;
@@ -13,7 +14,7 @@ use std::marker::{Send, Sync};
 use std::path::PathBuf;
 
 fn import_addresses<T, F>(
-    rubber: &mut Rubber,
+    rubber: &mut impl SearchBackend,
     nb_threads: usize,
     index_settings: IndexSettings,
     dataset: &str,
@@ -84,7 +85,7 @@ where
 }
 
 pub fn import_addresses_from_streams<T, F>(
-    rubber: &mut Rubber,
+    rubber: &mut impl SearchBackend,
     has_headers: bool,
     nb_threads: usize,
     index_settings: IndexSettings,
@@ -113,7 +114,7 @@ where
 }
 
 pub fn import_addresses_from_files<T, F>(
-    rubber: &mut Rubber,
+    rubber: &mut impl SearchBackend,
     has_headers: bool,
     nb_threads: usize,
     index_settings: IndexSettings,
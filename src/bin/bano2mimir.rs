@@ -30,11 +30,16 @@
 
 use failure::ensure;
 use lazy_static::lazy_static;
+use mimir::backend::SearchBackend;
 use mimir::objects::Admin;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::rubber::{namespace_from_url, IndexSettings, Rubber};
 use mimirsbrunn::addr_reader::{import_addresses_from_files, import_addresses_from_streams};
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
 use mimirsbrunn::labels;
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::output::OutputArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
 use serde::{Deserialize, Serialize};
 use slog_scope::{info, warn};
 use std::collections::BTreeMap;
@@ -122,6 +127,7 @@ impl Bano {
 
         let zip_codes: Vec<_> = self.zip.split(';').map(str::to_string).collect();
         let coord = mimir::Coord::new(self.lon, self.lat);
+        let timezone = mimir::find_timezone(&admins);
         let street = mimir::Street {
             id: street_id,
             name: self.street,
@@ -132,7 +138,9 @@ impl Bano {
             coord,
             approx_coord: None,
             distance: None,
+            score: None,
             country_codes: country_codes.clone(),
+            timezone,
             context: None,
         };
         Ok(mimir::Addr {
@@ -166,6 +174,7 @@ impl Bano {
             weight,
             zip_codes,
             distance: None,
+            score: None,
             country_codes,
             context: None,
         })
@@ -193,6 +202,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
     /// Name of the dataset.
     #[structopt(short = "d", long = "dataset", default_value = "fr")]
     dataset: String,
@@ -213,18 +224,26 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
+    #[structopt(flatten)]
+    output: OutputArgs,
     /// If set to true, the number inside the address won't be used for the index generation,
     /// therefore, different addresses with the same position will disappear.
     #[structopt(long = "use-old-index-format")]
     use_old_index_format: bool,
 }
 
-fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
-    info!("importing bano into Mimir");
-
-    let mut rubber =
-        Rubber::new(&args.connection_string).with_nb_insert_threads(args.nb_insert_threads);
-
+fn import_bano(rubber: &mut impl SearchBackend, args: Args) -> Result<(), mimirsbrunn::Error> {
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
@@ -260,7 +279,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         if input_path.is_dir() {
             let paths: std::fs::ReadDir = fs::read_dir(&input_path)?;
             import_addresses_from_files(
-                &mut rubber,
+                rubber,
                 false,
                 args.nb_threads,
                 index_settings,
@@ -270,7 +289,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
             )
         } else {
             import_addresses_from_files(
-                &mut rubber,
+                rubber,
                 false,
                 args.nb_threads,
                 index_settings,
@@ -282,7 +301,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
     } else {
         // Import from stdin
         import_addresses_from_streams(
-            &mut rubber,
+            rubber,
             false,
             args.nb_threads,
             index_settings,
@@ -292,6 +311,28 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         )
     }
 }
+
+fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+    info!("importing bano into Mimir");
+
+    if let Some(mut backend) = args.output.file_backend(
+        args.document_model.into(),
+        &namespace_from_url(&args.connection_string),
+    )? {
+        import_bano(&mut backend, args)?;
+        return backend.into_inner().finish();
+    }
+    let mut rubber = Rubber::new(&args.connection_string)
+        .with_nb_insert_threads(args.nb_insert_threads)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.clone().into())
+        .with_bulk_retry(args.bulk_retry.clone().into());
+
+    import_bano(&mut rubber, args)
+}
+
 fn main() {
     mimirsbrunn::utils::launch_run(run);
 }
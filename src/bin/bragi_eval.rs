@@ -0,0 +1,376 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Relevance evaluation of bragi's autocomplete.
+//!
+//! The queries of a corpus are run against an Elasticsearch index with one or two
+//! query settings files, and the results are compared to the expected ones.
+
+use failure::{format_err, Error, ResultExt};
+use mimir::rubber::Rubber;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use serde::Deserialize;
+use slog_scope::info;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Corpus file, one JSON query per line
+    #[structopt(short = "i", long = "input", parse(from_os_str))]
+    input: PathBuf,
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/munin"
+    )]
+    connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
+    /// Query settings files (like config/bragi-settings.toml) to evaluate.
+    /// Give two files to compare them side by side. The default settings are used if not set.
+    #[structopt(short = "s", long = "settings", parse(from_os_str))]
+    settings: Vec<PathBuf>,
+    /// Number of results considered for the precision
+    #[structopt(short = "k", default_value = "5")]
+    k: usize,
+    /// Number of results fetched for each query
+    #[structopt(short = "l", long = "limit", default_value = "10")]
+    limit: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Expected {
+    id: String,
+    /// the expected (1-based) maximal position of the result, any position if not set
+    position: Option<usize>,
+}
+
+/// A query of the corpus
+#[derive(Deserialize, Debug)]
+struct Case {
+    q: String,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    #[serde(default, rename = "type")]
+    types: Vec<String>,
+    #[serde(default)]
+    pt_dataset: Vec<String>,
+    #[serde(default)]
+    poi_dataset: Vec<String>,
+    expected: Vec<Expected>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Ok,
+    /// expected id not found in the results
+    Missing(String),
+    /// expected id found too far, at the given position
+    TooFar(String, usize),
+    Error(String),
+}
+
+#[derive(Debug)]
+struct Evaluation {
+    /// (1-based) rank of each expected result
+    ranks: Vec<Option<usize>>,
+    precision: f64,
+    reciprocal_rank: f64,
+    outcome: Outcome,
+}
+
+fn evaluate(case: &Case, result_ids: &[&str], k: usize) -> Evaluation {
+    let ranks: Vec<_> = case
+        .expected
+        .iter()
+        .map(|e| result_ids.iter().position(|id| *id == e.id).map(|p| p + 1))
+        .collect();
+    let nb_relevant_in_k = ranks
+        .iter()
+        .filter(|r| matches!(r, Some(r) if *r <= k))
+        .count();
+    let reciprocal_rank = ranks
+        .iter()
+        .filter_map(|r| *r)
+        .min()
+        .map(|r| 1. / r as f64)
+        .unwrap_or(0.);
+    let outcome = case
+        .expected
+        .iter()
+        .zip(ranks.iter())
+        .find_map(|(e, r)| match (r, e.position) {
+            (None, _) => Some(Outcome::Missing(e.id.clone())),
+            (Some(r), Some(p)) if *r > p => Some(Outcome::TooFar(e.id.clone(), *r)),
+            _ => None,
+        })
+        .unwrap_or(Outcome::Ok);
+    Evaluation {
+        ranks,
+        precision: nb_relevant_in_k as f64 / k as f64,
+        reciprocal_rank,
+        outcome,
+    }
+}
+
+fn read_corpus(path: &Path) -> Result<Vec<Case>, Error> {
+    let file = File::open(path).with_context(|_| format!("impossible to open {:?}", path))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| match line {
+            Ok(l) => !l.trim().is_empty() && !l.trim_start().starts_with('#'),
+            Err(_) => true,
+        })
+        .map(|(n, line)| {
+            let line = line?;
+            serde_json::from_str(&line)
+                .with_context(|_| format!("invalid query at line {} of {:?}", n + 1, path))
+                .map_err(Error::from)
+        })
+        .collect()
+}
+
+fn read_settings(path: Option<&PathBuf>) -> Result<bragi::QuerySettings, Error> {
+    let content = match path {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|_| format!("impossible to read {:?}", path))?,
+        None => include_str!("../../config/bragi-settings.toml").to_owned(),
+    };
+    bragi::QuerySettings::new(&content)
+        .map_err(|e| format_err!("invalid settings {:?}: {}", path, e))
+}
+
+fn run_case(
+    case: &Case,
+    rubber: &Rubber,
+    settings: &bragi::QuerySettings,
+    args: &Args,
+) -> Evaluation {
+    let coord = match (case.lon, case.lat) {
+        (Some(lon), Some(lat)) => Some(mimir::Coord::new(lon, lat)),
+        _ => None,
+    };
+    let as_str = |v: &[String]| v.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = bragi::query_autocomplete(
+        &case.q,
+        &as_str(&case.pt_dataset),
+        &as_str(&case.poi_dataset),
+        false,
+        0,
+        args.limit,
+        coord,
+        None,
+        &[],
+        &as_str(&case.types),
+        &[],
+        &[],
+        &[],
+        rubber.clone(),
+        false,
+        settings,
+        None,
+    );
+    match result {
+        Ok(res) => {
+            let ids: Vec<_> = res.places.iter().map(|p| p.id()).collect();
+            evaluate(case, &ids, args.k)
+        }
+        Err(e) => Evaluation {
+            ranks: case.expected.iter().map(|_| None).collect(),
+            precision: 0.,
+            reciprocal_rank: 0.,
+            outcome: Outcome::Error(e.to_string()),
+        },
+    }
+}
+
+fn format_outcome(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Ok => "ok".to_owned(),
+        Outcome::Missing(id) => format!("{} not found", id),
+        Outcome::TooFar(id, rank) => format!("{} at position {}", id, rank),
+        Outcome::Error(e) => format!("error: {}", e),
+    }
+}
+
+fn format_ranks(ranks: &[Option<usize>]) -> String {
+    ranks
+        .iter()
+        .map(|r| r.map_or_else(|| "-".to_owned(), |r| r.to_string()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    if args.settings.len() > 2 {
+        return Err(format_err!("at most two settings files can be compared"));
+    }
+    if args.k == 0 {
+        return Err(format_err!("k must be positive"));
+    }
+    let corpus = read_corpus(&args.input)?;
+    info!("{} queries read from {:?}", corpus.len(), args.input);
+
+    let settings = if args.settings.is_empty() {
+        vec![("default".to_owned(), read_settings(None)?)]
+    } else {
+        args.settings
+            .iter()
+            .map(|p| Ok((p.display().to_string(), read_settings(Some(p))?)))
+            .collect::<Result<Vec<_>, Error>>()?
+    };
+
+    let rubber =
+        Rubber::new(&args.connection_string).with_document_model(args.document_model.into());
+    // evaluations[settings][case]
+    let evaluations: Vec<Vec<Evaluation>> = settings
+        .iter()
+        .map(|(_, s)| {
+            corpus
+                .iter()
+                .map(|case| run_case(case, &rubber, s, &args))
+                .collect()
+        })
+        .collect();
+
+    println!("# failed cases");
+    for (i, case) in corpus.iter().enumerate() {
+        let outcomes: Vec<_> = evaluations.iter().map(|e| &e[i]).collect();
+        if outcomes.iter().all(|e| e.outcome == Outcome::Ok) {
+            continue;
+        }
+        let columns = outcomes
+            .iter()
+            .map(|e| {
+                format!(
+                    "[{}] {}",
+                    format_ranks(&e.ranks),
+                    format_outcome(&e.outcome)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        println!("{:<40} {}", case.q, columns);
+    }
+
+    let nb_cases = corpus.len().max(1) as f64;
+    println!();
+    println!(
+        "{:<20} {:>10} {:>10} {:>10}",
+        "settings",
+        format!("P@{}", args.k),
+        "MRR",
+        "success"
+    );
+    for ((name, _), evaluations) in settings.iter().zip(evaluations.iter()) {
+        println!(
+            "{:<20} {:>10.3} {:>10.3} {:>6}/{:<3}",
+            name,
+            evaluations.iter().map(|e| e.precision).sum::<f64>() / nb_cases,
+            evaluations.iter().map(|e| e.reciprocal_rank).sum::<f64>() / nb_cases,
+            evaluations
+                .iter()
+                .filter(|e| e.outcome == Outcome::Ok)
+                .count(),
+            corpus.len()
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(expected: &[(&str, Option<usize>)]) -> Case {
+        Case {
+            q: "20 rue hector malot".to_owned(),
+            lat: None,
+            lon: None,
+            types: vec![],
+            pt_dataset: vec![],
+            poi_dataset: vec![],
+            expected: expected
+                .iter()
+                .map(|(id, position)| Expected {
+                    id: id.to_string(),
+                    position: *position,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let results = ["a", "b", "c", "d"];
+
+        let eval = evaluate(&case(&[("b", Some(2)), ("d", None)]), &results, 2);
+        assert_eq!(eval.ranks, vec![Some(2), Some(4)]);
+        assert_eq!(eval.precision, 0.5);
+        assert_eq!(eval.reciprocal_rank, 0.5);
+        assert_eq!(eval.outcome, Outcome::Ok);
+
+        let eval = evaluate(&case(&[("c", Some(1))]), &results, 2);
+        assert_eq!(eval.precision, 0.);
+        assert_eq!(eval.outcome, Outcome::TooFar("c".to_owned(), 3));
+
+        let eval = evaluate(&case(&[("z", None)]), &results, 2);
+        assert_eq!(eval.reciprocal_rank, 0.);
+        assert_eq!(eval.outcome, Outcome::Missing("z".to_owned()));
+    }
+
+    #[test]
+    fn test_read_corpus() {
+        let path = std::env::temp_dir().join(format!("bragi_eval_corpus_{}", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"# a comment
+{"q": "hector malot", "lat": 48.85, "lon": 2.37, "type": ["street"], "expected": [{"id": "street:1", "position": 1}]}
+
+{"q": "gare de lyon", "pt_dataset": ["fr-idf"], "expected": [{"id": "stop_area:GDL"}]}
+"#,
+        )
+        .unwrap();
+        let corpus = read_corpus(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(corpus.len(), 2);
+        assert_eq!(corpus[0].types, vec!["street".to_owned()]);
+        assert_eq!(corpus[1].expected[0].position, None);
+    }
+}
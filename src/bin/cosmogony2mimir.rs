@@ -28,197 +28,52 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
-use cosmogony::{Zone, ZoneIndex};
 use failure::Error;
+use mimir::backend::SearchBackend;
 use mimir::objects::Admin;
-use mimir::rubber::{IndexSettings, Rubber};
-use mimirsbrunn::osm_reader::admin;
-use mimirsbrunn::osm_reader::osm_utils;
-use mimirsbrunn::utils;
-use slog_scope::{info, warn};
-use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::sync::Arc;
+use mimir::rubber::{namespace_from_url, IndexSettings, Rubber};
+use mimirsbrunn::cosmogony_reader::read_admins;
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::output::OutputArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
+use slog_scope::info;
 use structopt::StructOpt;
 
-trait IntoAdmin {
-    fn into_admin(
-        self,
-        _: &BTreeMap<ZoneIndex, (String, Option<String>)>,
-        langs: &[String],
-        retrocompat_on_french_id: bool,
-        max_weight: f64,
-        all_admins: Option<&HashMap<String, Arc<Admin>>>,
-    ) -> Admin;
-}
-
-fn get_weight(tags: &osmpbfreader::Tags, center_tags: &osmpbfreader::Tags) -> f64 {
-    // to have an admin weight we use the osm 'population' tag to priorize
-    // the big zones over the small one.
-    // Note: this tags is not often filled , so only some zones
-    // will have a weight (but the main cities have it).
-    tags.get("population")
-        .and_then(|p| p.parse().ok())
-        .or_else(|| center_tags.get("population")?.parse().ok())
-        .unwrap_or(0.)
-}
-
-impl IntoAdmin for Zone {
-    fn into_admin(
-        self,
-        zones_osm_id: &BTreeMap<ZoneIndex, (String, Option<String>)>,
-        langs: &[String],
-        french_id_retrocompatibility: bool,
-        max_weight: f64,
-        all_admins: Option<&HashMap<String, Arc<Admin>>>,
-    ) -> Admin {
-        let insee = admin::read_insee(&self.tags).map(|s| s.to_owned());
-        let zip_codes = admin::read_zip_codes(&self.tags);
-        let label = self.label;
-        let weight = get_weight(&self.tags, &self.center_tags);
-        let center = self.center.map_or(mimir::Coord::default(), |c| {
-            mimir::Coord::new(c.lng(), c.lat())
-        });
-        let format_id = |id, insee| {
-            // for retrocompatibity reasons, Navitia needs the
-            // french admins to have an id with the insee for cities
-            match insee {
-                Some(insee) if french_id_retrocompatibility => format!("admin:fr:{}", insee),
-                _ => format!("admin:osm:{}", id),
-            }
-        };
-        let parent_osm_id = self
-            .parent
-            .and_then(|id| zones_osm_id.get(&id))
-            .map(|(id, insee)| format_id(id, insee.as_ref()));
-        let codes = osm_utils::get_osm_codes_from_tags(&self.tags);
-        let mut admin = Admin {
-            id: zones_osm_id
-                .get(&self.id)
-                .map(|(id, insee)| format_id(id, insee.as_ref()))
-                .expect("unable to find zone id in zones_osm_id"),
-            insee: insee.unwrap_or_else(|| "".to_owned()),
-            level: self.admin_level.unwrap_or(0),
-            label,
-            name: self.name,
-            zip_codes,
-            weight: utils::normalize_weight(weight, max_weight),
-            bbox: self.bbox,
-            boundary: self.boundary,
-            coord: center,
-            approx_coord: Some(center.into()),
-            zone_type: self.zone_type,
-            parent_id: parent_osm_id,
-            // Note: Since we do not really attach an admin to its hierarchy, for the moment an admin only have it's own coutry code,
-            // not the country code of it's country from the hierarchy
-            // (so it has a country code mainly if it is a country)
-            country_codes: utils::get_country_code(&codes).into_iter().collect(),
-            codes,
-            names: osm_utils::get_names_from_tags(&self.tags, &langs),
-            labels: self
-                .international_labels
-                .into_iter()
-                .filter(|(k, _)| langs.contains(&k))
-                .collect(),
-            distance: None,
-            context: None,
-            administrative_regions: Vec::new(),
-        };
-        if let Some(ref admins) = all_admins {
-            // Get a list of encompassing parent ids, which will be used as the get
-            // administrative_regions.
-            let mut parent_ids = Vec::new();
-            let mut current = &admin;
-            while current.parent_id.is_some() {
-                parent_ids.push(current.parent_id.clone().unwrap());
-                if let Some(par) = admins.get(parent_ids.last().unwrap()) {
-                    current = par;
-                } else {
-                    break;
-                }
-            }
-            admin.administrative_regions = parent_ids
-                .into_iter()
-                .filter_map(|a| admins.get(&a))
-                .map(|x| Arc::clone(x))
-                .collect::<Vec<_>>();
-        }
-        admin
-    }
-}
-
 fn send_to_es(
     admins: impl Iterator<Item = Admin>,
-    cnx_string: &str,
+    backend: &mut impl SearchBackend,
     dataset: &str,
     index_settings: IndexSettings,
 ) -> Result<(), Error> {
-    let mut rubber = Rubber::new(cnx_string);
-    rubber.initialize_templates()?;
-    let nb_admins = rubber.public_index(dataset, &index_settings, admins)?;
+    let nb_admins = backend.public_index(dataset, &index_settings, admins)?;
     info!("{} admins added.", nb_admins);
     Ok(())
 }
 
-fn read_zones(input: &str) -> Result<impl Iterator<Item = Zone>, Error> {
-    Ok(cosmogony::read_zones_from_file(input)?
-        .filter_map(|r| r.map_err(|e| warn!("impossible to read zone: {}", e)).ok()))
-}
-
 fn index_cosmogony(args: Args) -> Result<(), Error> {
-    info!("building maps");
-    use cosmogony::ZoneType::City;
-
-    let mut cosmogony_id_to_osm_id = BTreeMap::new();
-    let max_weight = utils::ADMIN_MAX_WEIGHT;
-    for z in read_zones(&args.input)? {
-        let insee = match z.zone_type {
-            Some(City) => admin::read_insee(&z.tags).map(|s| s.to_owned()),
-            _ => None,
-        };
-        cosmogony_id_to_osm_id.insert(z.id, (z.osm_id.clone(), insee));
-    }
-    let cosmogony_id_to_osm_id = cosmogony_id_to_osm_id;
-
-    info!("building admins hierarchy");
-    let admins_without_boundaries = read_zones(&args.input)?
-        .map(|mut z| {
-            z.boundary = None;
-            let admin = z.into_admin(
-                &cosmogony_id_to_osm_id,
-                &args.langs,
-                args.french_id_retrocompatibility,
-                max_weight,
-                None,
-            );
-            (admin.id.clone(), Arc::new(admin))
-        })
-        .collect::<HashMap<_, _>>();
+    let admins = read_admins(&args.input, &args.langs, args.french_id_retrocompatibility)?;
 
     info!("importing cosmogony into Mimir");
-
-    let admins = read_zones(&args.input)?.map(|z| {
-        z.into_admin(
-            &cosmogony_id_to_osm_id,
-            &args.langs,
-            args.french_id_retrocompatibility,
-            max_weight,
-            Some(&admins_without_boundaries),
-        )
-    });
-
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
     };
-    send_to_es(
-        admins,
-        &args.connection_string,
-        &args.dataset,
-        index_settings,
-    )?;
-
-    Ok(())
+    if let Some(mut backend) = args.output.file_backend(
+        args.document_model.into(),
+        &namespace_from_url(&args.connection_string),
+    )? {
+        send_to_es(admins, &mut backend, &args.dataset, index_settings)?;
+        return backend.into_inner().finish();
+    }
+    let mut rubber = Rubber::new(&args.connection_string)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.into())
+        .with_bulk_retry(args.bulk_retry.into());
+    rubber.initialize_templates()?;
+    send_to_es(admins, &mut rubber, &args.dataset, index_settings)
 }
 
 #[derive(StructOpt, Debug)]
@@ -233,6 +88,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
     /// Name of the dataset.
     #[structopt(short = "d", long = "dataset", default_value = "fr")]
     dataset: String,
@@ -242,6 +99,19 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
+    #[structopt(flatten)]
+    output: OutputArgs,
     /// Languages codes, used to build i18n names and labels
     #[structopt(name = "lang", short, long)]
     langs: Vec<String>,
@@ -0,0 +1,291 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use failure::{bail, Error};
+use mimir::rubber::{get_global_stop_index, split_date_index_name, Rubber};
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::stops::rebuild_global_stop_index;
+use slog_scope::info;
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+
+const DOC_TYPES: [&str; 5] = ["admin", "street", "addr", "poi", "stop"];
+
+/// List, inspect and delete the datasets of the namespace of the connection string.
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/"
+    )]
+    connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// List the datasets of each type, with the number of documents and the build date of
+    /// their published index.
+    List {
+        /// Only list the datasets of this type.
+        #[structopt(short = "t", long = "type", possible_values = &DOC_TYPES)]
+        doc_type: Option<String>,
+    },
+    /// Show the aliases with the indexes they point to, and the indexes without alias.
+    Aliases,
+    /// Delete all the indexes of a dataset, after removing them from their aliases.
+    Delete {
+        /// Type of the documents of the dataset.
+        #[structopt(short = "t", long = "type", possible_values = &DOC_TYPES)]
+        doc_type: String,
+        /// Name of the dataset.
+        #[structopt(short = "d", long = "dataset")]
+        dataset: String,
+        /// Only show the indexes that would be deleted.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+    /// Delete the dated indexes that no alias points to. The closed indexes, kept for a
+    /// rollback, are not deleted.
+    Gc {
+        /// Only delete the indexes built at least this number of hours ago, not to delete
+        /// the index of a running import.
+        #[structopt(long = "min-age", default_value = "24")]
+        min_age: i64,
+        /// Only show the indexes that would be deleted.
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+struct Index {
+    aliases: Vec<String>,
+    closed: bool,
+}
+
+/// The indexes `{base}_*`, with their aliases
+fn get_indexes(rubber: &Rubber, base: &str) -> Result<BTreeMap<String, Index>, Error> {
+    let closed = rubber.get_closed_indexes(base)?;
+    let mut indexes: BTreeMap<String, Index> = rubber
+        .get_all_aliased_index(base)?
+        .into_iter()
+        .map(|(name, aliases)| {
+            let closed = closed.contains(&name);
+            (name, Index { aliases, closed })
+        })
+        .collect();
+    for name in closed {
+        indexes.entry(name).or_insert(Index {
+            aliases: vec![],
+            closed: true,
+        });
+    }
+    Ok(indexes)
+}
+
+fn list(rubber: &Rubber, only_type: Option<&str>) -> Result<(), Error> {
+    for doc_type in DOC_TYPES
+        .iter()
+        .filter(|t| only_type.map_or(true, |only_type| only_type == **t))
+    {
+        let type_index = format!("{}_{}", rubber.namespace, doc_type);
+        let prefix = format!("{}_", type_index);
+        let mut datasets: BTreeMap<String, Vec<(String, Index)>> = BTreeMap::new();
+        for (name, index) in get_indexes(rubber, &type_index)? {
+            let dataset = match split_date_index_name(&name) {
+                Some((base, _)) if base.starts_with(&prefix) => base[prefix.len()..].to_owned(),
+                _ => continue,
+            };
+            datasets.entry(dataset).or_default().push((name, index));
+        }
+        println!("{}:", doc_type);
+        for (dataset, indexes) in datasets {
+            let dataset_index = format!("{}{}", prefix, dataset);
+            let nb_kept = indexes.iter().filter(|(_, index)| index.closed).count();
+            let kept = if nb_kept > 0 {
+                format!(", {} kept indexes", nb_kept)
+            } else {
+                String::new()
+            };
+            match indexes
+                .iter()
+                .find(|(_, index)| index.aliases.contains(&dataset_index))
+            {
+                Some((name, index)) => {
+                    let visibility = if index.aliases.contains(&type_index) {
+                        "public"
+                    } else {
+                        "private"
+                    };
+                    let date = split_date_index_name(name).map(|(_, date)| date);
+                    println!(
+                        "  {}: {} documents, built {}, {}{}",
+                        dataset,
+                        rubber.count_documents(name)?,
+                        date.map_or_else(String::new, |d| d
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()),
+                        visibility,
+                        kept
+                    );
+                }
+                None => println!("  {}: not published{}", dataset, kept),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn aliases(rubber: &Rubber) -> Result<(), Error> {
+    let indexes = get_indexes(rubber, &rubber.namespace)?;
+    let mut aliases: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, index) in &indexes {
+        for alias in &index.aliases {
+            aliases
+                .entry(alias.as_str())
+                .or_default()
+                .push(name.as_str());
+        }
+    }
+    for (alias, names) in aliases {
+        println!("{}:", alias);
+        for name in names {
+            println!("  {}", name);
+        }
+    }
+    for (name, index) in indexes.iter().filter(|(_, index)| index.aliases.is_empty()) {
+        if index.closed {
+            println!("{} (closed, kept for a rollback)", name);
+        } else {
+            println!("{} (no alias)", name);
+        }
+    }
+    Ok(())
+}
+
+fn delete(rubber: &mut Rubber, doc_type: &str, dataset: &str, dry_run: bool) -> Result<(), Error> {
+    let dataset_index = format!("{}_{}_{}", rubber.namespace, doc_type, dataset);
+    // `munin_poi_fr_*` also matches the indexes of the dataset `fr_north`
+    let indexes: Vec<(String, Index)> = get_indexes(rubber, &dataset_index)?
+        .into_iter()
+        .filter(|(name, _)| {
+            split_date_index_name(name).map_or(false, |(base, _)| base == dataset_index)
+        })
+        .collect();
+    if indexes.is_empty() {
+        bail!("no index for {}", dataset_index);
+    }
+    let removed_aliases: Vec<(String, String)> = indexes
+        .iter()
+        .flat_map(|(name, index)| {
+            index
+                .aliases
+                .iter()
+                .map(move |alias| (alias.clone(), name.clone()))
+        })
+        .collect();
+    if dry_run {
+        for (alias, name) in &removed_aliases {
+            println!("{} would be removed from {}", name, alias);
+        }
+        for (name, _) in &indexes {
+            println!("{} would be deleted", name);
+        }
+        if doc_type == "stop" {
+            println!(
+                "{} would be rebuilt without the stops of {}",
+                get_global_stop_index(&rubber.namespace),
+                dataset
+            );
+        }
+        return Ok(());
+    }
+    // the dataset is no longer searched as soon as it is removed from the aliases
+    if !removed_aliases.is_empty() {
+        rubber.update_aliases(&[], &removed_aliases)?;
+    }
+    for (name, _) in &indexes {
+        rubber.delete_index(name)?;
+        info!("{} deleted", name);
+    }
+    if doc_type == "stop" {
+        let global_index = get_global_stop_index(&rubber.namespace);
+        let index_settings = rubber.get_index_settings(&global_index)?;
+        rebuild_global_stop_index(rubber, &index_settings)?;
+        info!("{} rebuilt without the stops of {}", global_index, dataset);
+    }
+    Ok(())
+}
+
+fn gc(rubber: &mut Rubber, min_age: i64, dry_run: bool) -> Result<(), Error> {
+    let max_date = chrono::Utc::now() - chrono::Duration::hours(min_age);
+    let orphans: Vec<String> = get_indexes(rubber, &rubber.namespace)?
+        .into_iter()
+        .filter(|(_, index)| index.aliases.is_empty() && !index.closed)
+        .filter(|(name, _)| split_date_index_name(name).map_or(false, |(_, date)| date <= max_date))
+        .map(|(name, _)| name)
+        .collect();
+    if orphans.is_empty() {
+        info!("no orphaned index");
+    }
+    for name in orphans {
+        if dry_run {
+            println!("{} would be deleted", name);
+        } else {
+            rubber.delete_index(&name)?;
+            info!("{} deleted", name);
+        }
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let mut rubber =
+        Rubber::new(&args.connection_string).with_document_model(args.document_model.into());
+    match args.command {
+        Command::List { doc_type } => list(&rubber, doc_type.as_deref()),
+        Command::Aliases => aliases(&rubber),
+        Command::Delete {
+            doc_type,
+            dataset,
+            dry_run,
+        } => delete(&mut rubber, &doc_type, &dataset, dry_run),
+        Command::Gc { min_age, dry_run } => gc(&mut rubber, min_age, dry_run),
+    }
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
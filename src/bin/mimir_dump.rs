@@ -0,0 +1,97 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use failure::{format_err, ResultExt};
+use mimir::rubber::Rubber;
+use mimir::{Addr, Admin, MimirObject, Poi, Stop, Street};
+use mimirsbrunn::dump::{published_index_header, write_dump};
+use slog_scope::info;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Dump all the documents of the index published for a dataset to a gzipped NDJSON file,
+/// that can be loaded again with `mimir_restore`.
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/"
+    )]
+    connection_string: String,
+    /// Type of the documents of the index.
+    #[structopt(
+        short = "t",
+        long = "type",
+        possible_values = &["admin", "street", "addr", "poi", "stop"]
+    )]
+    doc_type: String,
+    /// Name of the dataset.
+    #[structopt(short = "d", long = "dataset")]
+    dataset: String,
+    /// Dump file (gzipped NDJSON).
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: PathBuf,
+}
+
+fn dump<T: MimirObject>(args: Args) -> Result<(), failure::Error> {
+    let rubber = Rubber::new(&args.connection_string);
+    let header = published_index_header::<T>(&rubber, &args.dataset)?;
+    info!(
+        "dump of the {} documents of {} to {}",
+        header.nb_documents,
+        header.index,
+        args.output.display()
+    );
+    let file = File::create(&args.output)
+        .with_context(|_| format!("impossible to create {}", args.output.display()))?;
+    let documents = rubber.scroll_documents(&header.index)?;
+    let nb_documents = write_dump(BufWriter::new(file), &header, documents)?;
+    info!("{} documents dumped", nb_documents);
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), failure::Error> {
+    match args.doc_type.as_str() {
+        "admin" => dump::<Admin>(args),
+        "street" => dump::<Street>(args),
+        "addr" => dump::<Addr>(args),
+        "poi" => dump::<Poi>(args),
+        "stop" => dump::<Stop>(args),
+        other => Err(format_err!("unknown type {}", other)),
+    }
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
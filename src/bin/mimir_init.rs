@@ -28,11 +28,18 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 
+use failure::{format_err, Error};
+use mimir::rubber::{template_version, IndexSettings, PublishChecks, Rubber, TEMPLATE_VERSION};
+use mimir::{Addr, Admin, MimirObject, Poi, Stop, Street};
+use mimirsbrunn::dump::{published_index_header, restore};
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use serde_json::Value;
 use slog_scope::info;
-
-use mimir::rubber::Rubber;
+use std::collections::BTreeMap;
 use structopt::StructOpt;
 
+/// Manage the index templates of Elasticsearch. Without a command, the templates are installed.
 #[derive(StructOpt, Debug)]
 struct Args {
     /// Elasticsearch parameters.
@@ -42,12 +49,224 @@ struct Args {
         default_value = "http://localhost:9200/"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Show the version of the installed templates and of the indexes.
+    Show,
+    /// Show the differences between the installed templates and the ones of this version.
+    Diff,
+    /// Install the templates of this version.
+    Apply {
+        /// Replace the templates even if they have a newer version.
+        #[structopt(long = "force")]
+        force: bool,
+    },
+    /// Rebuild the index published for a dataset with the templates of this version.
+    Migrate(MigrateArgs),
+}
+
+#[derive(StructOpt, Debug)]
+struct MigrateArgs {
+    /// Type of the documents of the index.
+    #[structopt(
+        short = "t",
+        long = "type",
+        possible_values = &["admin", "street", "addr", "poi", "stop"]
+    )]
+    doc_type: String,
+    /// Name of the dataset.
+    #[structopt(short = "d", long = "dataset")]
+    dataset: String,
+    /// Number of shards for the es index, those of the migrated index if not given
+    #[structopt(short = "s", long = "nb-shards")]
+    nb_shards: Option<usize>,
+    /// Number of replicas for the es index, those of the migrated index if not given
+    #[structopt(short = "r", long = "nb-replicas")]
+    nb_replicas: Option<usize>,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    /// Rebuild the index even if it already has the template version of this version.
+    #[structopt(long = "force")]
+    force: bool,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
+}
+
+fn show(rubber: &Rubber) -> Result<(), Error> {
+    println!("template version: {}", TEMPLATE_VERSION);
+    for (name, _) in rubber.templates()? {
+        match rubber.get_template(&name)? {
+            Some(installed) => println!(
+                "{}: version {}",
+                name,
+                version_name(installed.get("mappings").and_then(template_version))
+            ),
+            None => println!("{}: not installed", name),
+        }
+    }
+    for (index, version) in rubber.get_template_versions(&format!("{}_*", rubber.namespace))? {
+        let outdated = if version == Some(TEMPLATE_VERSION) {
+            ""
+        } else {
+            " (to migrate)"
+        };
+        println!("{}: version {}{}", index, version_name(version), outdated);
+    }
+    Ok(())
+}
+
+fn version_name(version: Option<u64>) -> String {
+    version.map_or_else(|| "unknown".to_owned(), |v| v.to_string())
+}
+
+fn diff(rubber: &Rubber) -> Result<(), Error> {
+    for (name, template) in rubber.templates()? {
+        let installed = match rubber.get_template(&name)? {
+            Some(installed) => installed,
+            None => {
+                println!("{}: not installed", name);
+                continue;
+            }
+        };
+        let installed = template_leaves(&installed);
+        let template = template_leaves(&template);
+        if installed == template {
+            println!("{}: up to date", name);
+            continue;
+        }
+        println!("{}:", name);
+        for (path, value) in &installed {
+            match template.get(path) {
+                Some(new_value) if new_value == value => {}
+                Some(new_value) => {
+                    println!("- {}: {}", path, value);
+                    println!("+ {}: {}", path, new_value);
+                }
+                None => println!("- {}: {}", path, value),
+            }
+        }
+        for (path, value) in &template {
+            if !installed.contains_key(path) {
+                println!("+ {}: {}", path, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The values of the settings and of the mappings of a template by their path. Elasticsearch
+// gives back the settings under `index` and their values as strings, so they are compared
+// as strings.
+fn template_leaves(template: &Value) -> BTreeMap<String, String> {
+    let mut leaves = BTreeMap::new();
+    if let Some(settings) = template.get("settings") {
+        let settings = settings.get("index").unwrap_or(settings);
+        add_leaves(settings, "/settings".to_owned(), &mut leaves);
+    }
+    if let Some(mappings) = template.get("mappings") {
+        add_leaves(mappings, "/mappings".to_owned(), &mut leaves);
+    }
+    leaves
+}
+
+fn add_leaves(value: &Value, path: String, leaves: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(fields) => fields
+            .iter()
+            .for_each(|(key, value)| add_leaves(value, format!("{}/{}", path, key), leaves)),
+        Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .for_each(|(n, value)| add_leaves(value, format!("{}/{}", path, n), leaves)),
+        Value::String(value) => {
+            leaves.insert(path, value.clone());
+        }
+        _ => {
+            leaves.insert(path, value.to_string());
+        }
+    }
+}
+
+fn migrate<T>(rubber: Rubber, args: MigrateArgs) -> Result<(), Error>
+where
+    T: MimirObject + std::marker::Send + 'static,
+{
+    let header = published_index_header::<T>(&rubber, &args.dataset)?;
+    let version = rubber
+        .get_template_versions(&header.index)?
+        .remove(&header.index)
+        .flatten();
+    if version == Some(TEMPLATE_VERSION) && !args.force {
+        info!(
+            "{} already has the template version {}",
+            header.index, TEMPLATE_VERSION
+        );
+        return Ok(());
+    }
+    info!(
+        "migration of the {} documents of {} from the template version {} to {}",
+        header.nb_documents,
+        header.index,
+        version_name(version),
+        TEMPLATE_VERSION
+    );
+    let mut rubber = rubber
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(PublishChecks {
+            min_documents: header.nb_documents,
+            ..PublishChecks::default()
+        })
+        .with_bulk_retry(args.bulk_retry.into());
+    rubber.initialize_templates()?;
+    let documents = rubber
+        .clone()
+        .scroll_documents(&header.index)?
+        .map(|document| document.map_err(Error::from));
+    let index_settings = rubber.get_index_settings(&header.index)?;
+    let index_settings = IndexSettings {
+        nb_shards: args.nb_shards.unwrap_or(index_settings.nb_shards),
+        nb_replicas: args.nb_replicas.unwrap_or(index_settings.nb_replicas),
+    };
+    let nb_documents = restore::<T>(&mut rubber, &header, documents, &index_settings)?;
+    info!("{} documents migrated", nb_documents);
+    Ok(())
 }
 
-fn run(args: Args) -> Result<(), failure::Error> {
-    info!("creating templates");
-    let rubber = Rubber::new(&args.connection_string);
-    rubber.initialize_templates()
+fn run(args: Args) -> Result<(), Error> {
+    let rubber =
+        Rubber::new(&args.connection_string).with_document_model(args.document_model.into());
+    match args.command {
+        Some(Command::Show) => show(&rubber),
+        Some(Command::Diff) => diff(&rubber),
+        Some(Command::Apply { force }) => {
+            info!("creating templates");
+            rubber.apply_templates(force)
+        }
+        Some(Command::Migrate(args)) => match args.doc_type.as_str() {
+            "admin" => migrate::<Admin>(rubber, args),
+            "street" => migrate::<Street>(rubber, args),
+            "addr" => migrate::<Addr>(rubber, args),
+            "poi" => migrate::<Poi>(rubber, args),
+            "stop" => migrate::<Stop>(rubber, args),
+            other => Err(format_err!("unknown type {}", other)),
+        },
+        None => {
+            info!("creating templates");
+            rubber.initialize_templates()
+        }
+    }
 }
 
 fn main() {
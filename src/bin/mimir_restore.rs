@@ -0,0 +1,125 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use failure::{format_err, ResultExt};
+use mimir::rubber::{IndexSettings, PublishChecks, Rubber};
+use mimir::{Addr, Admin, Poi, Stop, Street};
+use mimirsbrunn::dump::{read_dump, restore};
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
+use slog_scope::info;
+use std::fs::File;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Load a dump made by `mimir_dump` in a new index, published like the index of an import.
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Dump file (gzipped NDJSON).
+    #[structopt(short = "i", long = "input", parse(from_os_str))]
+    input: PathBuf,
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/munin"
+    )]
+    connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
+    /// Name of the dataset, the dataset of the dump if not given.
+    #[structopt(short = "d", long = "dataset")]
+    dataset: Option<String>,
+    /// Number of shards for the es index
+    #[structopt(short = "s", long = "nb-shards", default_value = "1")]
+    nb_shards: usize,
+    /// Number of replicas for the es index
+    #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
+    nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    /// Publish the index even if some documents of the dump are invalid.
+    #[structopt(long = "allow-incomplete")]
+    allow_incomplete: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
+}
+
+fn run(args: Args) -> Result<(), failure::Error> {
+    let file = File::open(&args.input)
+        .with_context(|_| format!("impossible to open {}", args.input.display()))?;
+    let (mut header, documents) = read_dump(file)?;
+    if let Some(dataset) = args.dataset {
+        header.dataset = dataset;
+    }
+    info!(
+        "restore of the {} {} documents of {} (dumped on {}) in the dataset {}",
+        header.nb_documents, header.doc_type, header.index, header.date, header.dataset
+    );
+
+    let mut publish_checks = PublishChecks::from(args.publish_checks);
+    if !args.allow_incomplete {
+        publish_checks.min_documents = publish_checks.min_documents.max(header.nb_documents);
+    }
+    let mut rubber = Rubber::new(&args.connection_string)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(publish_checks)
+        .with_bulk_retry(args.bulk_retry.into());
+    rubber.initialize_templates()?;
+    let index_settings = IndexSettings {
+        nb_shards: args.nb_shards,
+        nb_replicas: args.nb_replicas,
+    };
+
+    let nb_documents = match header.doc_type.as_str() {
+        "admin" => restore::<Admin>(&mut rubber, &header, documents, &index_settings),
+        "street" => restore::<Street>(&mut rubber, &header, documents, &index_settings),
+        "addr" => restore::<Addr>(&mut rubber, &header, documents, &index_settings),
+        "poi" => restore::<Poi>(&mut rubber, &header, documents, &index_settings),
+        "stop" => restore::<Stop>(&mut rubber, &header, documents, &index_settings),
+        other => Err(format_err!("unknown type {}", other)),
+    }?;
+    info!("{} documents restored", nb_documents);
+    Ok(())
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
@@ -0,0 +1,116 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use mimir::backend::SearchBackend;
+use mimir::rubber::{get_global_stop_index, get_main_type_and_dataset_index, Rubber};
+use mimir::{Addr, Admin, MimirObject, Poi, Stop, Street};
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::stops::rebuild_global_stop_index;
+use slog_scope::info;
+use structopt::StructOpt;
+
+/// Publish again an index kept by a previous import (see the `--nb-kept-indexes` option
+/// of the importers) in place of the current index of a dataset.
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/"
+    )]
+    connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
+    /// Type of the documents of the index.
+    #[structopt(
+        short = "t",
+        long = "type",
+        possible_values = &["admin", "street", "addr", "poi", "stop"]
+    )]
+    doc_type: String,
+    /// Name of the dataset.
+    #[structopt(short = "d", long = "dataset")]
+    dataset: String,
+    /// Kept index to publish, the most recent one if not given.
+    #[structopt(short = "i", long = "index")]
+    index: Option<String>,
+    /// Only list the published and the kept indexes of the dataset.
+    #[structopt(short = "l", long = "list")]
+    list: bool,
+}
+
+fn list<T: MimirObject>(rubber: &Rubber, dataset: &str) -> Result<(), failure::Error> {
+    let dataset_index = get_main_type_and_dataset_index::<T>(&rubber.namespace, dataset);
+    for (index, aliases) in rubber.get_all_aliased_index(&dataset_index)? {
+        if aliases.contains(&dataset_index) {
+            println!("{} (published)", index);
+        }
+    }
+    let mut kept_indexes = rubber.get_closed_indexes(&dataset_index)?;
+    kept_indexes.sort_unstable_by(|a, b| b.cmp(a));
+    for index in kept_indexes {
+        println!("{}", index);
+    }
+    Ok(())
+}
+
+fn rollback<T: MimirObject>(rubber: &mut Rubber, args: &Args) -> Result<(), failure::Error> {
+    if args.list {
+        return list::<T>(rubber, &args.dataset);
+    }
+    let index = rubber.rollback_index::<T>(&args.dataset, args.index.as_deref())?;
+    info!("{} is published", index);
+    // the global stop index still has the stops of the index replaced by the rollback
+    if T::doc_type() == Stop::doc_type() {
+        let global_index = get_global_stop_index(&rubber.namespace);
+        let index_settings = rubber.get_index_settings(&global_index)?;
+        rebuild_global_stop_index(rubber, &index_settings)?;
+        info!("{} is rebuilt with the stops of {}", global_index, index);
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), failure::Error> {
+    let mut rubber =
+        Rubber::new(&args.connection_string).with_document_model(args.document_model.into());
+    match args.doc_type.as_str() {
+        "admin" => rollback::<Admin>(&mut rubber, &args),
+        "street" => rollback::<Street>(&mut rubber, &args),
+        "addr" => rollback::<Addr>(&mut rubber, &args),
+        "poi" => rollback::<Poi>(&mut rubber, &args),
+        "stop" => rollback::<Stop>(&mut rubber, &args),
+        other => Err(failure::format_err!("unknown type {}", other)),
+    }
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
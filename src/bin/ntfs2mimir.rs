@@ -29,7 +29,10 @@
 // www.navitia.io
 
 use failure::ResultExt;
-use mimir::rubber::IndexSettings;
+use mimir::rubber::{IndexSettings, Rubber};
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
 use mimirsbrunn::stops::*;
 use slog_scope::{info, warn};
 use std::cmp::Ordering;
@@ -55,6 +58,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
     /// Deprecated option.
     #[structopt(short = "C", long = "city-level")]
     city_level: Option<String>,
@@ -64,6 +69,21 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    /// Update the published stop index of the dataset in place, instead of replacing it
+    /// with a new index: only the changed stops are indexed, and the missing ones deleted.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
 }
 
 fn get_lines(idx: Idx<navitia::StopArea>, navitia: &transit_model::Model) -> Vec<mimir::Line> {
@@ -223,11 +243,18 @@ fn run(args: Args) -> Result<(), transit_model::Error> {
         nb_replicas: args.nb_replicas,
     };
 
+    let rubber = Rubber::new(&args.connection_string)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.into())
+        .with_bulk_retry(args.bulk_retry.into());
     import_stops(
         stops,
-        &args.connection_string,
+        rubber,
         &args.dataset,
         index_settings,
+        args.incremental,
     )
     .with_context(|err| {
         format!(
@@ -243,10 +270,16 @@ fn test_bad_connection_string() {
     let args = Args {
         input: PathBuf::from("./tests/fixtures/ntfs"),
         connection_string: "http://localhost:1".to_string(),
+        document_model: DocumentModelArgs::default(),
         dataset: "bob".to_string(),
         city_level: None,
         nb_replicas: 1,
         nb_shards: 1,
+        nb_kept_indexes: 0,
+        force_merge: false,
+        incremental: false,
+        publish_checks: PublishChecksArgs::default(),
+        bulk_retry: BulkRetryArgs::default(),
     };
     let causes = run(args)
         .unwrap_err()
@@ -256,8 +289,9 @@ fn test_bad_connection_string() {
     assert_eq!(
         causes,
         [
-            "Error occurred when importing stops into bob on http://localhost:1: Error: HTTP Error while creating template template_addr".to_string(),
-            "Error: HTTP Error while creating template template_addr".to_string(),
+            "Error occurred when importing stops into bob on http://localhost:1: Error occurred when getting template template_addr".to_string(),
+            "Error occurred when getting template template_addr".to_string(),
+            "HTTP Error".to_string(),
         ]
     );
 }
@@ -267,10 +301,16 @@ fn test_bad_file() {
     let args = Args {
         input: PathBuf::from("./tests/fixtures/not_exist"),
         connection_string: "http://localhost:9200".to_string(),
+        document_model: DocumentModelArgs::default(),
         dataset: "bob".to_string(),
         city_level: None,
         nb_replicas: 1,
         nb_shards: 1,
+        nb_kept_indexes: 0,
+        force_merge: false,
+        incremental: false,
+        publish_checks: PublishChecksArgs::default(),
+        bulk_retry: BulkRetryArgs::default(),
     };
     let causes = run(args)
         .unwrap_err()
@@ -29,9 +29,14 @@
 // www.navitia.io
 
 use lazy_static::lazy_static;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::backend::SearchBackend;
+use mimir::rubber::{namespace_from_url, IndexSettings, Rubber};
 use mimirsbrunn::addr_reader::{import_addresses_from_files, import_addresses_from_streams};
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::output::OutputArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
 use mimirsbrunn::{labels, utils};
 use serde::{Deserialize, Serialize};
 use slog_scope::{info, warn};
@@ -90,6 +95,7 @@ impl OpenAddress {
 
         let zip_codes: Vec<_> = self.postcode.split(';').map(str::to_string).collect();
         let coord = mimir::Coord::new(self.lon, self.lat);
+        let timezone = mimir::find_timezone(&admins);
         let street = mimir::Street {
             id: street_id,
             name: self.street,
@@ -100,7 +106,9 @@ impl OpenAddress {
             coord,
             approx_coord: None,
             distance: None,
+            score: None,
             country_codes: country_codes.clone(),
+            timezone,
             context: None,
         };
 
@@ -148,6 +156,7 @@ impl OpenAddress {
             weight,
             zip_codes,
             distance: None,
+            score: None,
             country_codes,
             context: None,
         })
@@ -171,6 +180,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
     /// Name of the dataset.
     #[structopt(short = "d", long = "dataset", default_value = "fr")]
     dataset: String,
@@ -194,22 +205,26 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
+    #[structopt(flatten)]
+    output: OutputArgs,
     /// If set to true, the number inside the address won't be used for the index generation,
     /// therefore, different addresses with the same position will disappear.
     #[structopt(long = "use-old-index-format")]
     use_old_index_format: bool,
 }
 
-fn run(args: Args) -> Result<(), failure::Error> {
-    info!("importing open addresses into Mimir");
-
-    if args.city_level.is_some() {
-        warn!("city-level option is deprecated, it now has no effect.");
-    }
-
-    let mut rubber =
-        Rubber::new(&args.connection_string).with_nb_insert_threads(args.nb_insert_threads);
-
+fn import_oa(rubber: &mut impl SearchBackend, args: Args) -> Result<(), mimirsbrunn::Error> {
     let index_settings = IndexSettings {
         nb_shards: args.nb_shards,
         nb_replicas: args.nb_replicas,
@@ -252,7 +267,7 @@ fn run(args: Args) -> Result<(), failure::Error> {
                 });
 
             import_addresses_from_files(
-                &mut rubber,
+                rubber,
                 true,
                 args.nb_threads,
                 index_settings,
@@ -262,7 +277,7 @@ fn run(args: Args) -> Result<(), failure::Error> {
             )
         } else {
             import_addresses_from_files(
-                &mut rubber,
+                rubber,
                 true,
                 args.nb_threads,
                 index_settings,
@@ -274,7 +289,7 @@ fn run(args: Args) -> Result<(), failure::Error> {
     } else {
         // Import from stdin
         import_addresses_from_streams(
-            &mut rubber,
+            rubber,
             true,
             args.nb_threads,
             index_settings,
@@ -285,6 +300,31 @@ fn run(args: Args) -> Result<(), failure::Error> {
     }
 }
 
+fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+    info!("importing open addresses into Mimir");
+
+    if args.city_level.is_some() {
+        warn!("city-level option is deprecated, it now has no effect.");
+    }
+
+    if let Some(mut backend) = args.output.file_backend(
+        args.document_model.into(),
+        &namespace_from_url(&args.connection_string),
+    )? {
+        import_oa(&mut backend, args)?;
+        return backend.into_inner().finish();
+    }
+    let mut rubber = Rubber::new(&args.connection_string)
+        .with_nb_insert_threads(args.nb_insert_threads)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.clone().into())
+        .with_bulk_retry(args.bulk_retry.clone().into());
+
+    import_oa(&mut rubber, args)
+}
+
 fn main() {
     mimirsbrunn::utils::launch_run(run);
 }
@@ -29,27 +29,47 @@
 // www.navitia.io
 
 use failure::ResultExt;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::backend::SearchBackend;
+use mimir::rubber::{namespace_from_url, IndexSettings, Rubber};
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
 use mimirsbrunn::osm_reader::admin::read_administrative_regions;
-use mimirsbrunn::osm_reader::make_osm_reader;
 use mimirsbrunn::osm_reader::poi::{add_address, compute_poi_weight, pois, PoiConfig};
 use mimirsbrunn::osm_reader::street::{compute_street_weight, streets};
+use mimirsbrunn::osm_reader::{make_osm_reader, OsmPbfReader};
 use mimirsbrunn::settings::osm2mimir::{Args, Settings};
 use slog_scope::{debug, info};
 
-fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+fn run(mut args: Args) -> Result<(), mimirsbrunn::Error> {
     let input = args.input.clone(); // we save the input, because args will be consumed by settings.
+    let output = std::mem::take(&mut args.output);
     validate_args(&args)?;
     let settings = Settings::new(args)?;
 
     let mut osm_reader = make_osm_reader(&input)?;
+    if let Some(mut backend) = output.file_backend(
+        settings.elasticsearch.document_model,
+        &namespace_from_url(&settings.elasticsearch.connection_string),
+    )? {
+        import(&mut backend, &mut osm_reader, &settings)?;
+        return backend.into_inner().finish();
+    }
     debug!("creation of indexes");
     let mut rubber = Rubber::new(&settings.elasticsearch.connection_string)
-        .with_nb_insert_threads(settings.elasticsearch.insert_thread_count);
+        .with_nb_insert_threads(settings.elasticsearch.insert_thread_count)
+        .with_document_model(settings.elasticsearch.document_model)
+        .with_nb_kept_indexes(settings.elasticsearch.kept_indexes)
+        .with_force_merge(settings.elasticsearch.force_merge)
+        .with_publish_checks(settings.elasticsearch.publish_checks.clone())
+        .with_bulk_retry(settings.elasticsearch.bulk_retry.clone().into());
     rubber.initialize_templates()?;
+    import(&mut rubber, &mut osm_reader, &settings)
+}
 
-    let settings = &settings;
+fn import(
+    rubber: &mut impl SearchBackend,
+    osm_reader: &mut OsmPbfReader,
+    settings: &Settings,
+) -> Result<(), mimirsbrunn::Error> {
     let admins = if settings
         .admin
         .as_ref()
@@ -64,7 +84,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         let levels = admins.levels.iter().cloned().collect();
         let city_level = admins.city_level;
         info!("creating administrative regions from OSM");
-        read_administrative_regions(&mut osm_reader, levels, city_level)
+        read_administrative_regions(osm_reader, levels, city_level)
     } else {
         info!("retrieving administrative regions from Elasticsearch");
         rubber.get_all_admins()?
@@ -79,7 +99,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         .unwrap_or_else(|| false)
     {
         info!("Extracting streets from osm");
-        let mut streets = streets(&mut osm_reader, &admins_geofinder, &settings)?;
+        let mut streets = streets(osm_reader, &admins_geofinder, &settings)?;
 
         info!("computing street weight");
         compute_street_weight(&mut streets);
@@ -141,13 +161,13 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
             .unwrap_or_else(PoiConfig::default);
 
         info!("Extracting pois from osm");
-        let mut pois = pois(&mut osm_reader, &config, &admins_geofinder);
+        let mut pois = pois(osm_reader, &config, &admins_geofinder);
 
         info!("computing poi weight");
         compute_poi_weight(&mut pois);
 
         info!("Adding address in poi");
-        add_address(&mut pois, &mut rubber);
+        add_address(&mut pois, rubber);
 
         let poi_index_settings = IndexSettings {
             nb_shards: settings.elasticsearch.pois_shards,
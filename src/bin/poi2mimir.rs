@@ -32,8 +32,12 @@ use slog_scope::{error, info};
 
 use failure::format_err;
 use lazy_static::lazy_static;
+use mimir::backend::SearchBackend;
 use mimir::objects::{Coord, I18nProperties, Poi, PoiType, Property};
 use mimir::rubber::{IndexSettings, IndexVisibility, Rubber, TypedIndex};
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
 use mimirsbrunn::{admin_geofinder::AdminGeoFinder, labels, utils};
 use navitia_poi_model::{Model as NavitiaModel, Poi as NavitiaPoi, PoiType as NavitiaPoiType};
 use std::collections::HashMap;
@@ -87,6 +91,8 @@ fn into_mimir_poi(
     let label =
         labels::format_poi_label(&poi.name, admins.iter().map(|a| a.deref()), &country_codes);
 
+    let timezone = mimir::find_timezone(&admins);
+
     let poi = Poi {
         id: mimir::objects::normalize_id("poi", &poi.id),
         label,
@@ -100,25 +106,22 @@ fn into_mimir_poi(
         properties: poi.properties.into_iter().map(Property::from).collect(),
         address: addr,
         country_codes,
+        timezone,
         names: I18nProperties::default(),
         labels: I18nProperties::default(),
         distance: None,
+        score: None,
         context: None,
     };
 
     Ok(poi)
 }
 
-fn import_pois(
+fn read_pois(
     rubber: &mut Rubber,
-    index: &TypedIndex<Poi>,
     admins_geofinder: AdminGeoFinder,
     file: &Path,
-) -> Result<(), mimirsbrunn::Error>
-where
-{
-    info!("Add data in elasticsearch db.");
-
+) -> Result<Vec<Poi>, mimirsbrunn::Error> {
     let model = NavitiaModel::try_from_path(file)?;
     let poi_types = model.poi_types;
 
@@ -133,6 +136,16 @@ where
         })
         .collect(); // TODO Can we get rid of collect, and chain with the following rubber...?
 
+    Ok(pois)
+}
+
+fn import_pois(
+    rubber: &mut Rubber,
+    index: &TypedIndex<Poi>,
+    pois: Vec<Poi>,
+) -> Result<(), mimirsbrunn::Error> {
+    info!("Add data in elasticsearch db.");
+
     let count = rubber
         .bulk_index(&index, pois.into_iter())
         .map_err(|err| format_err!("Failed bulk insertion {}", err))?;
@@ -144,17 +157,18 @@ where
 
 /// This function initializes the ES context: It creates an index for this dataset,
 /// and then import the POIs in it.
+/// If `incremental`, the published POI index of the dataset is updated in place instead.
 fn index_poi(
-    cnx_string: &str,
+    mut rubber: Rubber,
     dataset: &str,
     file: &Path,
     visibility: IndexVisibility,
     nb_shards: usize,
     nb_replicas: usize,
+    incremental: bool,
 ) -> Result<(), mimirsbrunn::Error>
 where
 {
-    let mut rubber = Rubber::new(cnx_string);
     rubber.initialize_templates()?;
 
     let settings = IndexSettings {
@@ -162,15 +176,22 @@ where
         nb_replicas,
     };
 
-    let index = rubber.make_index(dataset, &settings)?;
-
     let admins = rubber.get_all_admins().map_err(|err| {
         error!("Administratives regions not found in es db");
         err
     })?;
     let admins_geofinder = admins.into_iter().collect();
 
-    import_pois(&mut rubber, &index, admins_geofinder, file)?;
+    let pois = read_pois(&mut rubber, admins_geofinder, file)?;
+
+    if incremental {
+        rubber.update_index(dataset, pois.into_iter())?;
+        return Ok(());
+    }
+
+    let index = rubber.make_index(dataset, &settings)?;
+
+    import_pois(&mut rubber, &index, pois)?;
 
     rubber
         .publish_index(dataset, index, visibility)
@@ -190,6 +211,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
 
     /// Name of the dataset.
     /// A dataset is a label, that can be used for filtering the data.
@@ -215,6 +238,25 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+
+    /// Update the published POI index of the dataset in place, instead of replacing it
+    /// with a new index: only the changed POIs are indexed, and the missing ones deleted.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
 }
 
 fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
@@ -224,13 +266,20 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         IndexVisibility::Public
     };
 
+    let rubber = Rubber::new(&args.connection_string)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.into())
+        .with_bulk_retry(args.bulk_retry.into());
     index_poi(
-        &args.connection_string,
+        rubber,
         &args.dataset,
         &args.input,
         visibility,
         args.nb_shards,
         args.nb_replicas,
+        args.incremental,
     )
 }
 fn main() {
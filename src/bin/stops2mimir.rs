@@ -29,7 +29,10 @@
 // www.navitia.io
 
 use failure::ResultExt;
-use mimir::rubber::IndexSettings;
+use mimir::rubber::{IndexSettings, Rubber};
+use mimirsbrunn::settings::bulk_retry::BulkRetryArgs;
+use mimirsbrunn::settings::document_model::DocumentModelArgs;
+use mimirsbrunn::settings::publish_checks::PublishChecksArgs;
 use mimirsbrunn::stops::*;
 use serde::Deserialize;
 use slog_scope::{info, warn};
@@ -58,6 +61,8 @@ struct Args {
         default_value = "http://localhost:9200/munin"
     )]
     connection_string: String,
+    #[structopt(flatten)]
+    document_model: DocumentModelArgs,
     /// Deprecated option.
     #[structopt(short = "C", long = "city-level")]
     city_level: Option<String>,
@@ -67,6 +72,21 @@ struct Args {
     /// Number of replicas for the es index
     #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
     nb_replicas: usize,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes", default_value = "0")]
+    nb_kept_indexes: usize,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    /// Update the published stop index of the dataset in place, instead of replacing it
+    /// with a new index: only the changed stops are indexed, and the missing ones deleted.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+    #[structopt(flatten)]
+    publish_checks: PublishChecksArgs,
+    #[structopt(flatten)]
+    bulk_retry: BulkRetryArgs,
 }
 
 #[derive(Deserialize, Debug)]
@@ -165,11 +185,18 @@ fn run(args: Args) -> Result<(), failure::Error> {
         nb_replicas: args.nb_replicas,
     };
 
+    let rubber = Rubber::new(&args.connection_string)
+        .with_document_model(args.document_model.into())
+        .with_nb_kept_indexes(args.nb_kept_indexes)
+        .with_force_merge(args.force_merge)
+        .with_publish_checks(args.publish_checks.into())
+        .with_bulk_retry(args.bulk_retry.into());
     import_stops(
         stops,
-        &args.connection_string,
+        rubber,
         &args.dataset,
         index_settings,
+        args.incremental,
     )
     .context("Error while importing stops")?;
     Ok(())
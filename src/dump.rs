@@ -0,0 +1,346 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+//! Dump of the documents of an index to a gzipped NDJSON file, and restore of such a dump.
+//!
+//! The first line of a dump is a `DumpHeader`, the next ones are the documents as they are
+//! stored in Elasticsearch.
+
+use failure::{bail, format_err, Error, ResultExt};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mimir::backend::SearchBackend;
+use mimir::rubber::{
+    get_main_type_and_dataset_index, get_main_type_index, IndexSettings, IndexVisibility, Rubber,
+};
+use mimir::MimirObject;
+use serde::{Deserialize, Serialize};
+use slog_scope::warn;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::marker::PhantomData;
+
+/// Version of the dump format
+pub const DUMP_VERSION: u32 = 1;
+
+/// Description of a dump, written on its first line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DumpHeader {
+    pub version: u32,
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    pub dataset: String,
+    /// Index published when the dump was made
+    pub index: String,
+    /// If the index was searchable through the global aliases
+    pub public: bool,
+    /// Date of the dump (RFC 3339)
+    pub date: String,
+    pub nb_documents: u64,
+}
+
+/// A document of a dump, indexed as is with the type `T`
+pub struct DumpedDocument<T> {
+    source: serde_json::Value,
+    doc_type: PhantomData<T>,
+}
+
+impl<T> DumpedDocument<T> {
+    /// The `type` field of the stored document is removed, as the backends add it
+    /// when indexing the document
+    pub fn new(mut source: serde_json::Value) -> Self {
+        if let Some(fields) = source.as_object_mut() {
+            fields.remove("type");
+        }
+        DumpedDocument {
+            source,
+            doc_type: PhantomData,
+        }
+    }
+}
+
+impl<T> Serialize for DumpedDocument<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.source.serialize(serializer)
+    }
+}
+
+impl<T: MimirObject> MimirObject for DumpedDocument<T> {
+    fn is_geo_data() -> bool {
+        T::is_geo_data()
+    }
+    fn doc_type() -> &'static str {
+        T::doc_type()
+    }
+    fn es_id(&self) -> Option<String> {
+        self.source
+            .get("id")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_owned)
+    }
+}
+
+/// Header of a dump of the index published for a dataset
+pub fn published_index_header<T: MimirObject>(
+    rubber: &Rubber,
+    dataset: &str,
+) -> Result<DumpHeader, Error> {
+    let dataset_index = get_main_type_and_dataset_index::<T>(&rubber.namespace, dataset);
+    let (index, aliases) = rubber
+        .get_all_aliased_index(&dataset_index)?
+        .into_iter()
+        .find(|(_, aliases)| aliases.contains(&dataset_index))
+        .ok_or_else(|| format_err!("no index is published for {}", dataset_index))?;
+    Ok(DumpHeader {
+        version: DUMP_VERSION,
+        doc_type: T::doc_type().to_owned(),
+        dataset: dataset.to_owned(),
+        public: aliases.contains(&get_main_type_index::<T>(&rubber.namespace)),
+        date: chrono::Utc::now().to_rfc3339(),
+        nb_documents: rubber.count_documents(&index)?,
+        index,
+    })
+}
+
+/// Write the header and the documents of a dump, returns the number of documents written
+pub fn write_dump<W, I, E>(writer: W, header: &DumpHeader, documents: I) -> Result<u64, Error>
+where
+    W: Write,
+    I: Iterator<Item = Result<serde_json::Value, E>>,
+    Error: From<E>,
+{
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    serde_json::to_writer(&mut encoder, header)?;
+    encoder.write_all(b"\n")?;
+    let mut nb_documents = 0;
+    for document in documents {
+        serde_json::to_writer(&mut encoder, &document?)?;
+        encoder.write_all(b"\n")?;
+        nb_documents += 1;
+    }
+    encoder.finish()?;
+    Ok(nb_documents)
+}
+
+/// Read the header of a dump, and give an iterator on its documents
+pub fn read_dump<R: Read>(
+    reader: R,
+) -> Result<
+    (
+        DumpHeader,
+        impl Iterator<Item = Result<serde_json::Value, Error>>,
+    ),
+    Error,
+> {
+    let mut lines = BufReader::new(GzDecoder::new(reader)).lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| format_err!("the dump is empty"))??;
+    let header: DumpHeader =
+        serde_json::from_str(&header).context("the first line is not a dump header")?;
+    if header.version != DUMP_VERSION {
+        bail!(
+            "version {} of the dump is not supported, only version {} is",
+            header.version,
+            DUMP_VERSION
+        );
+    }
+    let documents = lines.enumerate().map(|(n, line)| {
+        let line = line?;
+        serde_json::from_str(&line)
+            .with_context(|err| format!("invalid document on line {}: {}", n + 2, err))
+            .map_err(Error::from)
+    });
+    Ok((header, documents))
+}
+
+/// Index the documents of a dump in a new index of the dataset of the header, and publish it.
+///
+/// The invalid documents are skipped, the number of documents of the header can be given
+/// as `min_documents` to the publish checks to avoid publishing an incomplete index.
+pub fn restore<T>(
+    backend: &mut impl SearchBackend,
+    header: &DumpHeader,
+    documents: impl Iterator<Item = Result<serde_json::Value, Error>>,
+    index_settings: &IndexSettings,
+) -> Result<usize, Error>
+where
+    T: MimirObject + std::marker::Send + 'static,
+{
+    if header.doc_type != T::doc_type() {
+        bail!(
+            "the dump contains {} documents, not {}",
+            header.doc_type,
+            T::doc_type()
+        );
+    }
+    let visibility = if header.public {
+        IndexVisibility::Public
+    } else {
+        IndexVisibility::Private
+    };
+    let documents = documents
+        .filter_map(|document| {
+            document
+                .map_err(|err| warn!("document skipped: {}", err))
+                .ok()
+        })
+        .map(DumpedDocument::<T>::new);
+    backend.index(&header.dataset, visibility, index_settings, documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::{LineString, MultiPolygon, Polygon};
+    use mimir::backend::{FileBackend, MemoryBackend};
+    use mimir::Admin;
+
+    fn admin(id: &str) -> Admin {
+        let square = Polygon::new(
+            LineString::from(vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.), (0., 0.)]),
+            vec![],
+        );
+        Admin {
+            id: id.into(),
+            name: id.into(),
+            label: id.into(),
+            boundary: Some(MultiPolygon(vec![square])),
+            ..Default::default()
+        }
+    }
+
+    fn header(nb_documents: u64) -> DumpHeader {
+        DumpHeader {
+            version: DUMP_VERSION,
+            doc_type: "admin".into(),
+            dataset: "fr".into(),
+            index: "munin_admin_fr_20201015_101734_532478911".into(),
+            public: true,
+            date: "2020-10-15T10:17:34+00:00".into(),
+            nb_documents,
+        }
+    }
+
+    #[test]
+    fn test_dump_and_restore() {
+        let documents = vec![admin("admin:1"), admin("admin:2")]
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Vec<_>>();
+        let mut dump = vec![];
+        assert_eq!(
+            write_dump(&mut dump, &header(2), documents.into_iter()).unwrap(),
+            2
+        );
+
+        let (read_header, documents) = read_dump(dump.as_slice()).unwrap();
+        assert_eq!(read_header, header(2));
+        let mut backend = MemoryBackend::new();
+        let index_settings = IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        };
+        assert_eq!(
+            restore::<Admin>(&mut backend, &read_header, documents, &index_settings).unwrap(),
+            2
+        );
+
+        let mut admins: Vec<Admin> = backend.get_all_objects_from_index("munin_admin").unwrap();
+        admins.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(
+            admins.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            vec!["admin:1", "admin:2"]
+        );
+        assert!(admins.iter().all(|a| a.boundary.is_some()));
+    }
+
+    #[test]
+    fn test_restore_type_field() {
+        // the documents are dumped as stored, with the `type` field added when indexing them
+        let documents = vec![admin("admin:1")]
+            .iter()
+            .map(|admin| {
+                let mut document = serde_json::to_value(admin)?;
+                document["type"] = "admin".into();
+                Ok(document)
+            })
+            .collect::<Vec<Result<_, Error>>>();
+        let mut dump = vec![];
+        write_dump(&mut dump, &header(1), documents.into_iter()).unwrap();
+
+        let (header, documents) = read_dump(dump.as_slice()).unwrap();
+        let mut backend = FileBackend::new(vec![]);
+        let index_settings = IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        };
+        restore::<Admin>(&mut backend, &header, documents, &index_settings).unwrap();
+
+        let output = String::from_utf8(backend.into_inner()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // an action line and a document line
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].matches("\"type\":").count(), 1);
+        let document: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(document["type"], "admin");
+        assert_eq!(document["id"], "admin:1");
+    }
+
+    #[test]
+    fn test_restore_wrong_type() {
+        let mut dump = vec![];
+        write_dump(
+            &mut dump,
+            &header(0),
+            std::iter::empty::<Result<_, Error>>(),
+        )
+        .unwrap();
+        let (header, documents) = read_dump(dump.as_slice()).unwrap();
+        let index_settings = IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        };
+        let err = restore::<mimir::Stop>(
+            &mut MemoryBackend::new(),
+            &header,
+            documents,
+            &index_settings,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the dump contains admin documents, not stop"
+        );
+    }
+}
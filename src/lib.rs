@@ -30,6 +30,8 @@
 
 pub mod addr_reader;
 pub mod admin_geofinder;
+pub mod cosmogony_reader;
+pub mod dump;
 pub mod labels;
 pub mod osm_reader;
 pub mod settings;
@@ -172,10 +172,12 @@ pub fn read_administrative_regions(
                 zone_type,
                 parent_id: None,
                 country_codes: utils::get_country_code(&codes).into_iter().collect(),
+                timezone: read_timezone(&relation.tags),
                 codes,
                 names: mimir::I18nProperties::default(),
                 labels: mimir::I18nProperties::default(),
                 distance: None,
+                score: None,
                 context: None,
                 administrative_regions: Vec::new(),
             };
@@ -184,6 +186,7 @@ pub fn read_administrative_regions(
     }
 
     utils::normalize_admin_weight(&mut administrative_regions);
+    inherit_timezones(&mut administrative_regions);
 
     administrative_regions
 }
@@ -225,6 +228,38 @@ pub fn read_insee(tags: &osmpbfreader::Tags) -> Option<&str> {
     tags.get("ref:INSEE").map(|v| v.trim_start_matches('0'))
 }
 
+/// IANA timezone of the `timezone` tag (like "Europe/Paris")
+pub fn read_timezone(tags: &osmpbfreader::Tags) -> Option<String> {
+    tags.get("timezone")
+        .filter(|tz| !tz.is_empty())
+        .map(|tz| tz.to_string())
+}
+
+/// Without a `timezone` tag, an admin takes the timezone of the most precise admin containing
+/// it (the OSM admins have no hierarchy, unlike the cosmogony ones)
+fn inherit_timezones(admins: &mut [mimir::Admin]) {
+    use geo::algorithm::contains::Contains;
+    let timezones: Vec<(u32, geo_types::MultiPolygon<f64>, String)> = admins
+        .iter()
+        .filter_map(|admin| {
+            Some((
+                admin.level,
+                admin.boundary.clone()?,
+                admin.timezone.clone()?,
+            ))
+        })
+        .collect();
+    for admin in admins.iter_mut().filter(|admin| admin.timezone.is_none()) {
+        let center = geo_types::Point(admin.coord.0);
+        let timezone = timezones
+            .iter()
+            .filter(|(level, boundary, _)| *level < admin.level && boundary.contains(&center))
+            .max_by_key(|(level, _, _)| *level)
+            .map(|(_, _, timezone)| timezone.clone());
+        admin.timezone = timezone;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,4 +272,68 @@ mod tests {
         );
         assert_eq!(get_zone_type(2, 1), None);
     }
+
+    #[test]
+    fn test_timezone() {
+        let admin = |level, timezone: Option<&str>| {
+            std::sync::Arc::new(mimir::Admin {
+                level,
+                timezone: timezone.map(str::to_string),
+                ..Default::default()
+            })
+        };
+        let mut tags = osmpbfreader::Tags::new();
+        assert_eq!(read_timezone(&tags), None);
+        tags.insert("timezone".into(), "America/Cayenne".into());
+        assert_eq!(read_timezone(&tags), Some("America/Cayenne".to_string()));
+
+        // the most precise admin with a timezone gives the timezone
+        let admins = vec![
+            admin(8, None),
+            admin(4, Some("America/Cayenne")),
+            admin(2, Some("Europe/Paris")),
+        ];
+        assert_eq!(
+            mimir::find_timezone(&admins),
+            Some("America/Cayenne".to_string())
+        );
+        assert_eq!(mimir::find_timezone(&[admin(8, None)]), None);
+    }
+
+    #[test]
+    fn test_inherit_timezones() {
+        let square = |min: f64, max: f64| {
+            geo_types::MultiPolygon(vec![geo_types::Polygon::new(
+                vec![(min, min), (max, min), (max, max), (min, max), (min, min)].into(),
+                vec![],
+            )])
+        };
+        let admin = |id: &str, level, lon, boundary, timezone: Option<&str>| mimir::Admin {
+            id: id.to_string(),
+            level,
+            coord: mimir::Coord::new(lon, 1.),
+            boundary: Some(boundary),
+            timezone: timezone.map(str::to_string),
+            ..Default::default()
+        };
+        let mut admins = vec![
+            admin("country", 2, 5., square(0., 10.), Some("Europe/Paris")),
+            admin("region", 4, 1., square(0., 2.), Some("America/Cayenne")),
+            admin("city", 8, 1., square(0.5, 1.5), None),
+            admin("other_city", 8, 5., square(4.5, 5.5), None),
+            admin("outside", 8, 20., square(19.5, 20.5), None),
+        ];
+        inherit_timezones(&mut admins);
+        let timezones: Vec<_> = admins.iter().map(|a| a.timezone.as_deref()).collect();
+        assert_eq!(
+            timezones,
+            vec![
+                Some("Europe/Paris"),
+                Some("America/Cayenne"),
+                Some("America/Cayenne"),
+                Some("Europe/Paris"),
+                None
+            ]
+        );
+    }
 }
@@ -33,7 +33,8 @@ use super::osm_utils::make_centroid;
 use super::OsmPbfReader;
 use crate::admin_geofinder::AdminGeoFinder;
 use crate::{labels, settings::osm2mimir::Settings, utils};
-use mimir::{rubber, Poi, PoiType};
+use mimir::backend::SearchBackend;
+use mimir::{Poi, PoiType};
 use osm_boundaries_utils::build_boundary;
 use serde::{Deserialize, Serialize};
 use slog_scope::{info, warn};
@@ -177,6 +178,7 @@ fn parse_poi(
         _ => utils::get_zip_codes_from_admins(&adms),
     };
     let country_codes = utils::find_country_codes(adms.iter().map(|a| a.deref()));
+    let timezone = mimir::find_timezone(&adms);
     Some(mimir::Poi {
         id,
         name: name.to_string(),
@@ -192,7 +194,9 @@ fn parse_poi(
         names: mimir::I18nProperties::default(),
         labels: mimir::I18nProperties::default(),
         distance: None,
+        score: None,
         country_codes,
+        timezone,
         context: None,
     })
 }
@@ -225,7 +229,7 @@ pub fn compute_poi_weight(pois_vec: &mut [Poi]) {
     }
 }
 
-pub fn add_address(pois_vec: &mut [Poi], rubber: &mut rubber::Rubber) {
+pub fn add_address(pois_vec: &mut [Poi], rubber: &mut impl SearchBackend) {
     for poi in pois_vec {
         poi.address = rubber
             .get_address(&poi.coord)
@@ -116,6 +116,7 @@ pub fn streets(
         |id: String, name: String, coord: mimir::Coord, admins: Vec<Arc<mimir::Admin>>| {
             let admins_iter = admins.iter().map(Deref::deref);
             let country_codes = utils::find_country_codes(admins_iter.clone());
+            let timezone = mimir::find_timezone(&admins);
             mimir::Street {
                 id,
                 label: labels::format_street_label(&name, admins_iter, &country_codes),
@@ -126,7 +127,9 @@ pub fn streets(
                 coord,
                 approx_coord: Some(coord.into()),
                 distance: None,
+                score: None,
                 country_codes,
+                timezone,
                 context: None,
             }
         };
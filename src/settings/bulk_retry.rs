@@ -0,0 +1,40 @@
+use mimir::rubber::BulkRetry;
+use serde::Deserialize;
+use std::time::Duration;
+use structopt::StructOpt;
+
+/// Options of the import tools to retry the documents rejected by a busy Elasticsearch
+#[derive(StructOpt, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BulkRetryArgs {
+    /// Maximum number of retries of the documents rejected by a busy Elasticsearch
+    /// (429 or 5xx status) and of the failed bulk requests.
+    #[structopt(long = "bulk-max-retries", default_value = "5")]
+    pub max_retries: usize,
+    /// Delay in milliseconds before the first retry, doubled at each retry.
+    #[structopt(long = "bulk-retry-delay", default_value = "500")]
+    pub retry_delay: u64,
+    /// Maximum delay in milliseconds between two retries.
+    #[structopt(long = "bulk-max-retry-delay", default_value = "30000")]
+    pub max_retry_delay: u64,
+}
+
+impl Default for BulkRetryArgs {
+    fn default() -> Self {
+        BulkRetryArgs {
+            max_retries: 5,
+            retry_delay: 500,
+            max_retry_delay: 30000,
+        }
+    }
+}
+
+impl From<BulkRetryArgs> for BulkRetry {
+    fn from(args: BulkRetryArgs) -> Self {
+        BulkRetry {
+            max_retries: args.max_retries,
+            initial_delay: Duration::from_millis(args.retry_delay),
+            max_delay: Duration::from_millis(args.max_retry_delay),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use mimir::rubber::DocumentModel;
+use structopt::StructOpt;
+
+/// Option of the tools to choose the document model of Elasticsearch
+#[derive(StructOpt, Debug, Clone, Copy, Default)]
+pub struct DocumentModelArgs {
+    /// Document model of Elasticsearch: `typed` (Elasticsearch 2 to 6)
+    /// or `typeless` (Elasticsearch 7+)
+    #[structopt(
+        long = "document-model",
+        default_value = "typed",
+        possible_values = &["typed", "typeless"]
+    )]
+    pub document_model: DocumentModel,
+}
+
+impl From<DocumentModelArgs> for DocumentModel {
+    fn from(args: DocumentModelArgs) -> Self {
+        args.document_model
+    }
+}
@@ -1 +1,5 @@
+pub mod bulk_retry;
+pub mod document_model;
 pub mod osm2mimir;
+pub mod output;
+pub mod publish_checks;
@@ -1,5 +1,6 @@
 use config::{Config, ConfigError, File, FileFormat, Source, Value};
 use failure::ResultExt;
+use mimir::rubber::{DocumentModel, PublishChecks};
 use serde::Deserialize;
 use slog_scope::{info, warn};
 use std::collections::HashMap;
@@ -8,6 +9,9 @@ use std::path::PathBuf;
 use structopt::StructOpt;
 
 use crate::osm_reader::poi;
+use crate::settings::bulk_retry::BulkRetryArgs;
+use crate::settings::output::OutputArgs;
+use crate::settings::publish_checks::parse_max_drop;
 use crate::Error;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -45,13 +49,21 @@ pub struct Database {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Elasticsearch {
     pub connection_string: String,
+    pub document_model: DocumentModel,
     pub insert_thread_count: usize,
+    pub kept_indexes: usize,
+    #[serde(default)]
+    pub force_merge: bool,
     pub streets_shards: usize,
     pub streets_replicas: usize,
     pub admins_shards: usize,
     pub admins_replicas: usize,
     pub pois_shards: usize,
     pub pois_replicas: usize,
+    #[serde(default)]
+    pub publish_checks: PublishChecks,
+    #[serde(default)]
+    pub bulk_retry: BulkRetryArgs,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -188,6 +200,10 @@ pub struct Args {
     /// Elasticsearch parameters.
     #[structopt(short = "c", long = "connection-string")]
     connection_string: Option<String>,
+    /// Document model of Elasticsearch: `typed` (Elasticsearch 2 to 6)
+    /// or `typeless` (Elasticsearch 7+)
+    #[structopt(long = "document-model", possible_values = &["typed", "typeless"])]
+    document_model: Option<String>,
     /// Import ways.
     #[structopt(short = "w", long = "import-way")]
     import_way: Option<bool>,
@@ -235,6 +251,20 @@ pub struct Args {
     /// to handle values that are too high.
     #[structopt(short = "T", long = "nb-insert-threads")]
     nb_insert_threads: Option<usize>,
+    /// Number of previous indexes kept to roll back to them with `mimir_rollback`
+    #[structopt(long = "nb-kept-indexes")]
+    nb_kept_indexes: Option<usize>,
+    /// Merge the segments of the new indexes into one before publishing them, for a faster
+    /// search at the cost of a longer import.
+    #[structopt(long = "force-merge")]
+    force_merge: bool,
+    /// Minimum number of documents of a new index to publish it
+    #[structopt(long = "min-documents")]
+    min_documents: Option<u64>,
+    /// Maximum drop (between 0 and 1) of the number of documents of a new index compared to the
+    /// published one
+    #[structopt(long = "max-drop", parse(try_from_str = parse_max_drop))]
+    max_drop: Option<f64>,
 
     /// Path to the config directory
     /// osm2mimir will read the default configuration in there, and maybe
@@ -250,6 +280,9 @@ pub struct Args {
     /// be set)
     #[structopt(short = "s", long = "settings")]
     settings: Option<String>,
+
+    #[structopt(flatten)]
+    pub output: OutputArgs,
 }
 
 impl Source for Args {
@@ -322,6 +355,13 @@ impl Source for Args {
             );
         }
 
+        if let Some(document_model) = self.document_model.clone() {
+            m.insert(
+                String::from("elasticsearch.document_model"),
+                Value::new(None, document_model),
+            );
+        }
+
         if let Some(nb_way_shards) = self.nb_street_shards {
             m.insert(
                 String::from("elasticsearch.streets_shards"),
@@ -427,6 +467,50 @@ impl Source for Args {
             );
         }
 
+        if let Some(nb_kept_indexes) = self.nb_kept_indexes {
+            m.insert(
+                String::from("elasticsearch.kept_indexes"),
+                Value::new(
+                    None,
+                    i64::try_from(nb_kept_indexes).map_err(|e| {
+                        ConfigError::Message(format!(
+                            "Could not convert count of kept indexes to integer: {}",
+                            e
+                        ))
+                    })?,
+                ),
+            );
+        }
+
+        if self.force_merge {
+            m.insert(
+                String::from("elasticsearch.force_merge"),
+                Value::new(None, true),
+            );
+        }
+
+        if let Some(min_documents) = self.min_documents {
+            m.insert(
+                String::from("elasticsearch.publish_checks.min_documents"),
+                Value::new(
+                    None,
+                    i64::try_from(min_documents).map_err(|e| {
+                        ConfigError::Message(format!(
+                            "Could not convert minimum count of documents to integer: {}",
+                            e
+                        ))
+                    })?,
+                ),
+            );
+        }
+
+        if let Some(max_drop) = self.max_drop {
+            m.insert(
+                String::from("elasticsearch.publish_checks.max_drop"),
+                Value::new(None, max_drop),
+            );
+        }
+
         // DATABASE
         #[cfg(feature = "db-storage")]
         if let Some(db_file) = self.db_file.clone() {
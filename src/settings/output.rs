@@ -0,0 +1,129 @@
+use crate::cosmogony_reader;
+use crate::dump::read_dump;
+use failure::{bail, format_err, Error, ResultExt};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mimir::backend::{FileBackend, MemoryBackend, SearchBackend};
+use mimir::rubber::{DocumentModel, IndexSettings};
+use mimir::{Admin, MimirObject};
+use slog_scope::info;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Options of the import tools to write the documents to a file instead of Elasticsearch
+#[derive(StructOpt, Clone, Debug, Default)]
+pub struct OutputArgs {
+    /// Write the bulk requests (action and document lines) to this NDJSON file, gzipped if
+    /// its name ends with `.gz`, instead of sending them to Elasticsearch.
+    #[structopt(long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// With `--output`, the admins used to enrich the documents: a file made by `mimir_dump`
+    /// or a cosmogony file. No admin is used if not given.
+    #[structopt(long = "admins", parse(from_os_str), requires = "output")]
+    pub admins: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    /// The backend writing to the `--output` file, if given, with the indexes of `namespace`
+    pub fn file_backend(
+        &self,
+        document_model: DocumentModel,
+        namespace: &str,
+    ) -> Result<Option<FileBackend<OutputFile>>, Error> {
+        let path = match &self.output {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let file = BufWriter::new(
+            File::create(path)
+                .with_context(|_| format!("impossible to create {}", path.display()))?,
+        );
+        let output = if path.extension().map_or(false, |ext| ext == "gz") {
+            OutputFile::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            OutputFile::Plain(file)
+        };
+        let mut lookup = MemoryBackend::new().with_namespace(namespace);
+        if let Some(admins_path) = &self.admins {
+            let admins = read_admins(admins_path)?;
+            info!(
+                "{} admins read from {}",
+                admins.len(),
+                admins_path.display()
+            );
+            let index_settings = IndexSettings {
+                nb_shards: 1,
+                nb_replicas: 0,
+            };
+            lookup.public_index("lookup", &index_settings, admins.into_iter())?;
+        }
+        Ok(Some(
+            FileBackend::new(output)
+                .with_document_model(document_model)
+                .with_lookup(lookup),
+        ))
+    }
+}
+
+/// The `--output` file, gzipped or not
+pub enum OutputFile {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl OutputFile {
+    /// Write the end of the file: the gzip trailer and the buffered data.
+    /// Dropping the file instead would ignore the errors of these writes.
+    pub fn finish(self) -> Result<(), Error> {
+        let writer = match self {
+            OutputFile::Plain(writer) => writer,
+            OutputFile::Gzip(encoder) => encoder.finish()?,
+        };
+        writer.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputFile::Plain(writer) => writer.write(buf),
+            OutputFile::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputFile::Plain(writer) => writer.flush(),
+            OutputFile::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// The admins of a dump of `mimir_dump`, or else of a cosmogony file
+fn read_admins(path: &Path) -> Result<Vec<Admin>, Error> {
+    let file =
+        File::open(path).with_context(|_| format!("impossible to open {}", path.display()))?;
+    match read_dump(file) {
+        Ok((header, documents)) => {
+            if header.doc_type != Admin::doc_type() {
+                bail!(
+                    "{} is a dump of {}, not of admins",
+                    path.display(),
+                    header.doc_type
+                );
+            }
+            documents
+                .map(|document| Ok(serde_json::from_value(document?)?))
+                .collect()
+        }
+        Err(_) => {
+            let path = path
+                .to_str()
+                .ok_or_else(|| format_err!("invalid path {}", path.display()))?;
+            Ok(cosmogony_reader::read_admins(path, &[], false)?.collect())
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use mimir::rubber::{check_max_drop, Canary, PublishChecks};
+use structopt::StructOpt;
+
+/// Options of the import tools to check a new index before publishing it
+#[derive(StructOpt, Clone, Debug, Default)]
+pub struct PublishChecksArgs {
+    /// Minimum number of documents of a new index to publish it.
+    #[structopt(long = "min-documents", default_value = "0")]
+    pub min_documents: u64,
+    /// Maximum drop (between 0 and 1) of the number of documents of a new index
+    /// compared with the index it replaces, to publish it.
+    #[structopt(long = "max-drop", parse(try_from_str = parse_max_drop))]
+    pub max_drop: Option<f64>,
+    /// Query which must return a document to publish a new index, as `<query>=<id>`,
+    /// or as `<type>:<query>=<id>` to only check the indexes of a type.
+    #[structopt(long = "canary")]
+    pub canaries: Vec<Canary>,
+}
+
+/// Read a `--max-drop` argument, between 0 and 1
+pub fn parse_max_drop(s: &str) -> Result<f64, String> {
+    check_max_drop(
+        s.parse()
+            .map_err(|e| format!("invalid max drop '{}': {}", s, e))?,
+    )
+}
+
+impl From<PublishChecksArgs> for PublishChecks {
+    fn from(args: PublishChecksArgs) -> Self {
+        PublishChecks {
+            min_documents: args.min_documents,
+            max_drop: args.max_drop,
+            canaries: args.canaries,
+        }
+    }
+}
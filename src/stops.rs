@@ -32,6 +32,7 @@ use crate::admin_geofinder::AdminGeoFinder;
 use crate::{labels, utils};
 use failure::format_err;
 use failure::{Error, ResultExt};
+use mimir::backend::SearchBackend;
 use mimir::rubber::{IndexSettings, Rubber, TypedIndex};
 use slog_scope::{info, warn};
 use std::collections::HashMap;
@@ -39,8 +40,6 @@ use std::mem::replace;
 use std::ops::Deref;
 use std::sync::Arc;
 
-const GLOBAL_STOP_INDEX_NAME: &str = "munin_global_stops";
-
 pub fn initialize_weights<'a, It, S: ::std::hash::BuildHasher>(
     stops: It,
     nb_stop_points: &HashMap<String, u32, S>,
@@ -58,16 +57,30 @@ pub fn initialize_weights<'a, It, S: ::std::hash::BuildHasher>(
 }
 
 pub fn import_stops(
-    mut stops: Vec<mimir::Stop>,
-    connection_string: &str,
+    stops: Vec<mimir::Stop>,
+    mut rubber: Rubber,
     dataset: &str,
     index_settings: IndexSettings,
+    incremental: bool,
 ) -> Result<(), Error> {
     info!("creation of indexes");
-    let mut rubber = Rubber::new(connection_string);
     rubber.initialize_templates()?;
 
-    attach_stops_to_admins(stops.iter_mut(), &mut rubber);
+    index_stops(stops, &mut rubber, dataset, index_settings, incremental)
+}
+
+/// Attach the stops to the admins of the backend, then index them in the
+/// stop index of the dataset and in the global stop index.
+/// If `incremental`, the published stop index of the dataset is updated in place
+/// instead of being replaced by a new index
+pub fn index_stops(
+    mut stops: Vec<mimir::Stop>,
+    rubber: &mut impl SearchBackend,
+    dataset: &str,
+    index_settings: IndexSettings,
+    incremental: bool,
+) -> Result<(), Error> {
+    attach_stops_to_admins(stops.iter_mut(), rubber);
 
     for stop in &mut stops {
         stop.coverages.push(dataset.to_string());
@@ -87,15 +100,27 @@ pub fn import_stops(
         stop.weight = (stop.weight + admin_weight) / 2.0;
     }
 
+    if incremental {
+        // only the changed stops are indexed or deleted, in both indexes
+        let all_stops: Vec<mimir::Stop> =
+            merge_all_stops(rubber, stops.iter(), Some(dataset))?.collect();
+        info!("Updating {} stops in Mimir", stops.len());
+        rubber.update_index(dataset, stops.into_iter())?;
+        let global_index = mimir::rubber::get_global_stop_index(rubber.namespace());
+        rubber
+            .update_aliased_index(&global_index, all_stops.into_iter())
+            .context("Error while updating global index")?;
+        return Ok(());
+    }
+
     let global_index =
-        update_global_stop_index(&mut rubber, stops.iter(), dataset, &index_settings)?;
+        update_global_stop_index(rubber, stops.iter(), Some(dataset), &index_settings)?;
 
     info!("Importing {} stops into Mimir", stops.len());
     let nb_stops = rubber.public_index(dataset, &index_settings, stops.into_iter())?;
     info!("Nb of indexed stops: {}", nb_stops);
 
-    publish_global_index(&mut rubber, &global_index)
-        .context("Error while publishing global index")?;
+    publish_global_index(rubber, &global_index).context("Error while publishing global index")?;
     Ok(())
 }
 
@@ -107,6 +132,10 @@ fn attach_stop(stop: &mut mimir::Stop, admins: Vec<Arc<mimir::Admin>>) {
     stop.zip_codes = utils::get_zip_codes_from_admins(&admins);
 
     stop.country_codes = country_codes;
+    // the stops without timezone in their dataset take the timezone of their admins
+    if stop.timezone.is_empty() {
+        stop.timezone = mimir::find_timezone(&admins).unwrap_or_default();
+    }
     stop.administrative_regions = admins;
 }
 
@@ -117,7 +146,7 @@ fn attach_stop(stop: &mut mimir::Stop, admins: Vec<Arc<mimir::Admin>>) {
 /// the coordinate of the stop
 fn attach_stops_to_admins<'a, It: Iterator<Item = &'a mut mimir::Stop>>(
     stops: It,
-    rubber: &mut Rubber,
+    rubber: &mut impl SearchBackend,
 ) {
     let admins = rubber.get_all_admins().unwrap_or_else(|_| {
         warn!("Administratives regions not found in elasticsearch db");
@@ -186,23 +215,36 @@ fn merge_stops<It: IntoIterator<Item = mimir::Stop>>(
     stops_by_id.into_iter().map(|(_, v)| v)
 }
 
-fn get_all_stops(rubber: &mut Rubber, index: String) -> Result<Vec<mimir::Stop>, Error> {
+fn get_all_stops(
+    rubber: &mut impl SearchBackend,
+    index: String,
+) -> Result<Vec<mimir::Stop>, Error> {
     rubber
         .get_all_objects_from_index(&index)
         .map_err(|e| format_err!("Getting all stops {}", e.to_string()))
 }
 
-fn update_global_stop_index<'a, It: Iterator<Item = &'a mimir::Stop>>(
-    rubber: &mut Rubber,
+/// The stops of the global index: the stops of the published indexes of the datasets
+/// other than `dataset` (of all of them if `None`) merged with `stops`
+fn merge_all_stops<'a, It: Iterator<Item = &'a mimir::Stop>>(
+    rubber: &mut impl SearchBackend,
     stops: It,
-    dataset: &str,
-    index_settings: &IndexSettings,
-) -> Result<String, Error> {
-    let dataset_index = mimir::rubber::get_main_type_and_dataset_index::<mimir::Stop>(dataset);
+    dataset: Option<&str>,
+) -> Result<impl Iterator<Item = mimir::Stop>, Error> {
+    let dataset_index = dataset.map(|dataset| {
+        mimir::rubber::get_main_type_and_dataset_index::<mimir::Stop>(rubber.namespace(), dataset)
+    });
+    let type_index = mimir::rubber::get_main_type_index::<mimir::Stop>(rubber.namespace());
+    // only the published indexes, not the ones kept for a rollback
     let stops_indexes = rubber
-        .get_all_aliased_index(&mimir::rubber::get_main_type_index::<mimir::Stop>())?
+        .get_all_aliased_index(&type_index)?
         .into_iter()
-        .filter(|&(_, ref aliases)| !aliases.contains(&dataset_index))
+        .filter(|&(_, ref aliases)| {
+            aliases.contains(&type_index)
+                && dataset_index
+                    .as_ref()
+                    .map_or(true, |d| !aliases.contains(d))
+        })
         .map(|(index, _)| index);
 
     let all_es_stops = stops_indexes
@@ -212,30 +254,61 @@ fn update_global_stop_index<'a, It: Iterator<Item = &'a mimir::Stop>>(
         .flat_map(|stops| stops.into_iter())
         .chain(stops.cloned());
 
-    let all_merged_stops = merge_stops(all_es_stops);
-    let es_index_name = mimir::rubber::get_date_index_name(GLOBAL_STOP_INDEX_NAME);
+    Ok(merge_stops(all_es_stops))
+}
+
+fn update_global_stop_index<'a, It: Iterator<Item = &'a mimir::Stop>>(
+    rubber: &mut impl SearchBackend,
+    stops: It,
+    dataset: Option<&str>,
+    index_settings: &IndexSettings,
+) -> Result<String, Error> {
+    let all_merged_stops = merge_all_stops(rubber, stops, dataset)?;
+    let global_index = mimir::rubber::get_global_stop_index(rubber.namespace());
+    let es_index_name = mimir::rubber::get_date_index_name(&global_index);
 
     rubber.create_index(&es_index_name, &index_settings)?;
     let typed_index = TypedIndex::new(es_index_name.clone());
 
     let nb_stops_added = rubber.bulk_index(&typed_index, all_merged_stops)?;
     info!("{} stops added in the global index", nb_stops_added);
+    rubber.finish_index(&es_index_name, &index_settings)?;
     // create global index
     // fill structure for each stop indexes
     Ok(es_index_name)
 }
 
+/// Rebuild the global stop index from the published stop indexes of all the datasets,
+/// once one of them is rolled back or deleted
+pub fn rebuild_global_stop_index(
+    rubber: &mut impl SearchBackend,
+    index_settings: &IndexSettings,
+) -> Result<(), Error> {
+    let global_index = update_global_stop_index(
+        rubber,
+        std::iter::empty::<&mimir::Stop>(),
+        None,
+        index_settings,
+    )?;
+    publish_global_index(rubber, &global_index).context("Error while publishing global index")?;
+    Ok(())
+}
+
 // publish the global stop index
 // alias the new index to the global stop alias, and remove the old index
-fn publish_global_index(rubber: &mut Rubber, new_global_index: &str) -> Result<(), Error> {
+fn publish_global_index(
+    rubber: &mut impl SearchBackend,
+    new_global_index: &str,
+) -> Result<(), Error> {
+    let global_index = mimir::rubber::get_global_stop_index(rubber.namespace());
     let last_global_indexes: Vec<_> = rubber
-        .get_all_aliased_index(GLOBAL_STOP_INDEX_NAME)?
+        .get_all_aliased_index(&global_index)?
         .into_iter()
         .map(|(k, _)| k)
         .filter(|k| k != new_global_index)
         .collect();
     rubber.alias(
-        GLOBAL_STOP_INDEX_NAME,
+        &global_index,
         &[new_global_index.to_string()],
         &last_global_indexes,
     )?;
@@ -245,3 +318,119 @@ fn publish_global_index(rubber: &mut Rubber, new_global_index: &str) -> Result<(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mimir::backend::MemoryBackend;
+
+    fn stop(id: &str) -> mimir::Stop {
+        mimir::Stop {
+            id: id.into(),
+            label: id.into(),
+            name: id.into(),
+            coord: mimir::Coord::new(2.35, 48.85),
+            ..Default::default()
+        }
+    }
+
+    fn index_settings() -> IndexSettings {
+        IndexSettings {
+            nb_shards: 1,
+            nb_replicas: 0,
+        }
+    }
+
+    fn global_stops(backend: &mut MemoryBackend) -> Vec<mimir::Stop> {
+        let mut stops: Vec<mimir::Stop> = backend
+            .get_all_objects_from_index("munin_global_stops")
+            .unwrap();
+        stops.sort_by(|a, b| a.id.cmp(&b.id));
+        stops
+    }
+
+    #[test]
+    fn test_global_stop_index() {
+        let mut backend = MemoryBackend::new();
+        index_stops(
+            vec![stop("stop:1"), stop("stop:2")],
+            &mut backend,
+            "fr",
+            index_settings(),
+            false,
+        )
+        .unwrap();
+        index_stops(
+            vec![stop("stop:2"), stop("stop:3")],
+            &mut backend,
+            "be",
+            index_settings(),
+            false,
+        )
+        .unwrap();
+
+        let stops = global_stops(&mut backend);
+        assert_eq!(
+            stops.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["stop:1", "stop:2", "stop:3"]
+        );
+        assert_eq!(stops[1].coverages, vec!["be", "fr"]);
+
+        // a new import of a dataset replaces its stops in the global index
+        index_stops(
+            vec![stop("stop:1")],
+            &mut backend,
+            "be",
+            index_settings(),
+            false,
+        )
+        .unwrap();
+        let stops = global_stops(&mut backend);
+        assert_eq!(
+            stops.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["stop:1", "stop:2"]
+        );
+        assert_eq!(stops[0].coverages, vec!["be", "fr"]);
+        assert_eq!(stops[1].coverages, vec!["fr"]);
+
+        // an incremental update also updates the global index, in place
+        let global_indexes = |backend: &MemoryBackend| -> Vec<String> {
+            backend
+                .indexes()
+                .into_iter()
+                .map(|(index, _)| index)
+                .filter(|index| index.starts_with("munin_global_stops_"))
+                .collect()
+        };
+        let global_index = global_indexes(&backend);
+        assert_eq!(global_index.len(), 1);
+        index_stops(
+            vec![stop("stop:1"), stop("stop:4")],
+            &mut backend,
+            "be",
+            index_settings(),
+            true,
+        )
+        .unwrap();
+        let stops = global_stops(&mut backend);
+        assert_eq!(
+            stops.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["stop:1", "stop:2", "stop:4"]
+        );
+        assert_eq!(stops[2].coverages, vec!["be"]);
+        assert_eq!(global_indexes(&backend), global_index);
+
+        // once a dataset is deleted, the global index is rebuilt without its stops
+        for (index, _) in backend.get_all_aliased_index("munin_stop_be").unwrap() {
+            backend.delete_index(&index).unwrap();
+        }
+        rebuild_global_stop_index(&mut backend, &index_settings()).unwrap();
+        let stops = global_stops(&mut backend);
+        assert_eq!(
+            stops.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["stop:1", "stop:2"]
+        );
+        assert_eq!(stops[0].coverages, vec!["fr"]);
+        assert_eq!(global_indexes(&backend).len(), 1);
+    }
+}
@@ -61,15 +61,27 @@ pub fn bragi_bano_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
 }
 
 fn status_test(bragi: &mut BragiHandler) {
+    let status = bragi.get_json("/status");
+    assert_eq!(status.pointer("/status"), Some(&json!("good")));
     assert_eq!(
-        bragi.get_json("/status").pointer("/status"),
-        Some(&json!("good"))
+        status.pointer("/template_version"),
+        Some(&json!(mimir::rubber::TEMPLATE_VERSION))
     );
+    // the indexes are built with the templates of this version
+    assert_eq!(status.pointer("/incompatible_indexes"), None);
 }
 
 fn simple_bano_autocomplete_test(bragi: &mut BragiHandler) {
+    let mut response = bragi.get_json("/autocomplete?q=15 Rue Hector Malot (Paris)");
+    // the Elasticsearch score depends on the index, we only check it is given
+    let score = response["features"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("score")
+        .unwrap();
+    assert!(score.as_f64().unwrap() > 0.);
     assert_eq!(
-        bragi.get_json("/autocomplete?q=15 Rue Hector Malot (Paris)"),
+        response,
         json!(
         {
             "features": [
@@ -96,6 +108,8 @@ fn simple_bano_autocomplete_test(bragi: &mut BragiHandler) {
                             "type": "house"
                         }
                     },
+                    "match_type": "prefix",
+                    "confidence": 1.0,
                     "type": "Feature"
                 }
             ],
@@ -30,7 +30,7 @@
 
 use super::BragiHandler;
 use super::{count_types, get_types, get_value, get_values};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::path::Path;
 
 pub fn bragi_filter_types_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
@@ -89,6 +89,8 @@ pub fn bragi_filter_types_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
     unvalid_type_test(&mut bragi);
     addr_by_id_test(&mut bragi);
     admin_by_id_test(&mut bragi);
+    admin_boundary_by_id_test(&mut bragi);
+    admin_tile_test(&mut bragi);
     street_by_id_test(&mut bragi);
     stop_by_id_test(&mut bragi);
     stop_area_that_does_not_exists(&mut bragi);
@@ -187,6 +189,66 @@ fn admin_by_id_test(bragi: &mut BragiHandler) {
     assert_eq!(get_values(&all_20, "id"), vec!["admin:fr:77288"]);
 }
 
+fn admin_boundary_by_id_test(bragi: &mut BragiHandler) {
+    let mut geometry = |q: &str| bragi.get_json(q)["features"][0]["geometry"].clone();
+    let nb_points = |geometry: &Value| -> usize {
+        geometry["coordinates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|polygon| polygon.as_array().unwrap())
+            .map(|ring| ring.as_array().unwrap().len())
+            .sum()
+    };
+
+    assert_eq!(geometry("/features/admin:fr:77288")["type"], "Point");
+
+    let boundary = geometry("/features/admin:fr:77288?geometry=boundary");
+    assert_eq!(boundary["type"], "MultiPolygon");
+
+    let simplified = geometry("/features/admin:fr:77288?geometry=boundary&tolerance=0.01");
+    assert_eq!(simplified["type"], "MultiPolygon");
+    assert!(nb_points(&simplified) < nb_points(&boundary));
+
+    // the places that are not admins keep their coordinates
+    assert_eq!(
+        geometry("/features/street:osm:way:161162362?geometry=boundary")["type"],
+        "Point"
+    );
+
+    for tolerance in &["-1", "NaN", "inf"] {
+        assert_eq!(
+            bragi.get_status(&format!(
+                "/features/admin:fr:77288?geometry=boundary&tolerance={}",
+                tolerance
+            )),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+}
+
+fn admin_tile_test(bragi: &mut BragiHandler) {
+    // tile of the admin's coordinates at zoom 12
+    let coord = bragi.get_json("/features/admin:fr:77288")["features"][0]["geometry"]
+        ["coordinates"]
+        .clone();
+    let (lon, lat) = (coord[0].as_f64().unwrap(), coord[1].as_f64().unwrap());
+    let n = f64::from(1 << 12);
+    let x = ((lon + 180.) / 360. * n) as u32;
+    let lat = lat.to_radians();
+    let y = ((1. - (lat.tan() + 1. / lat.cos()).ln() / std::f64::consts::PI) / 2. * n) as u32;
+
+    let (status, tile) = bragi.raw_get(&format!("/tiles/admin/12/{}/{}.mvt", x, y));
+    assert_eq!(status, actix_web::http::StatusCode::OK);
+    let id = b"admin:fr:77288";
+    assert!(tile.windows(id.len()).any(|w| w == id));
+
+    let (status, _) = bragi.raw_get("/tiles/admin/12/5000/1415.mvt");
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+    let (status, _) = bragi.raw_get(&format!("/tiles/street/12/{}/{}.mvt", x, y));
+    assert_eq!(status, actix_web::http::StatusCode::BAD_REQUEST);
+}
+
 fn street_by_id_test(bragi: &mut BragiHandler) {
     let all_20 = bragi.get("/features/street:osm:way:161162362");
     assert_eq!(all_20.len(), 1);
@@ -99,6 +99,61 @@ pub fn bragi_stops_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
     stop_all_data_test(&mut bragi);
     stop_order_by_weight_test(&mut bragi);
     distance_test(&mut bragi);
+    // without api keys, the export is not available
+    assert_eq!(
+        bragi.get_status("/export/stop/dataset2"),
+        actix_web::http::StatusCode::FORBIDDEN
+    );
+    export_test(es_wrapper.host());
+}
+
+fn export_test(url: String) {
+    let api_keys = bragi::api_key_middleware::ApiKeysConfig::new(
+        r#"
+        [[keys]]
+        key = "audit"
+        name = "audit"
+        pt_datasets = ["dataset2"]
+        export = true
+        "#,
+    )
+    .unwrap();
+    let mut bragi = BragiHandler::new_with_api_keys(url, Some(api_keys));
+
+    let (status, body) = bragi.raw_get("/export/stop/dataset2?api_key=audit");
+    assert!(status.is_success(), "invalid status: {}", status);
+    let mut ids: Vec<String> = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let stop: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(stop["type"], "public_transport:stop_area");
+            stop["id"].as_str().unwrap().to_owned()
+        })
+        .collect();
+    ids.sort();
+    assert_eq!(
+        ids,
+        vec![
+            "stop_area:SA:known_by_all_dataset",
+            "stop_area:SA:second_station:dataset2"
+        ]
+    );
+
+    // the key only gives access to dataset2
+    assert_eq!(
+        bragi.get_status("/export/stop/dataset1?api_key=audit"),
+        actix_web::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        bragi.get_status("/export/plop/dataset2?api_key=audit"),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
+    // no index pattern to read other indexes
+    assert_eq!(
+        bragi.get_status("/export/addr/fr,munin_poi_x?api_key=audit"),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
 }
 
 fn stop_attached_to_admin_test(bragi: &mut BragiHandler) {
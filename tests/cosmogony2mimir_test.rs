@@ -28,6 +28,7 @@
 // https://groups.google.com/d/forum/navitia
 // www.navitia.io
 #![allow(clippy::cognitive_complexity)]
+use super::BragiHandler;
 use cosmogony::ZoneType;
 use std::collections::BTreeMap;
 use std::f64;
@@ -184,4 +185,69 @@ pub fn cosmogony2mimir_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
         }
         _ => panic!("should be an admin"),
     }
+
+    admin_hierarchy_test(&mut BragiHandler::new(es_wrapper.host()));
+}
+
+fn admin_hierarchy_test(bragi: &mut BragiHandler) {
+    let mut admin_ids = |q: &str| -> Vec<String> {
+        bragi.get_json(q)["admins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["id"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    let france = "admin:osm:relation:424256272";
+    let seine_et_marne = "admin:osm:relation:424253843";
+    let livry_sur_seine = "admin:osm:relation:215390";
+
+    assert_eq!(admin_ids("/admins?zone_type=country"), vec![france]);
+
+    // the admin, then its ancestors up to the country
+    let hierarchy = admin_ids(&format!("/admins/{}", livry_sur_seine));
+    assert_eq!(hierarchy.first().unwrap(), livry_sur_seine);
+    assert!(hierarchy.iter().any(|id| id == seine_et_marne));
+    assert_eq!(hierarchy.last().unwrap(), france);
+
+    assert!(admin_ids(&format!("/admins/{}/children", france))
+        .iter()
+        .any(|id| id == seine_et_marne));
+    assert!(admin_ids(&format!(
+        "/admins/{}/children?zone_type=city",
+        seine_et_marne
+    ))
+    .iter()
+    .any(|id| id == livry_sur_seine));
+
+    assert_eq!(
+        bragi.get_status("/admins/admin:unknown"),
+        actix_web::http::StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        bragi.get_status("/admins"),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        bragi.get_status(&format!("/admins/{}/children?limit=10001", france)),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
+
+    // the first admins by name, not the first found sorted by name
+    let names = |q: &str, bragi: &mut BragiHandler| -> Vec<String> {
+        bragi.get_json(q)["admins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    let children = format!("/admins/{}/children", seine_et_marne);
+    let mut all_names = names(&children, bragi);
+    assert!(all_names.len() > 2);
+    let mut sorted = all_names.clone();
+    sorted.sort();
+    assert_eq!(all_names, sorted);
+    all_names.truncate(2);
+    assert_eq!(names(&format!("{}?limit=2", children), bragi), all_names);
 }
@@ -0,0 +1,83 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+use super::get_values;
+use super::BragiHandler;
+use mimir::rubber::{DocumentModel, Rubber};
+use mimir::Addr;
+use serde_json::json;
+use std::path::Path;
+
+/// Import and search with the `typeless` document model on Elasticsearch 7, where the
+/// mapping types are removed and `hits.total` is an object
+pub fn typeless_bano_test(es_wrapper: crate::ElasticSearchWrapper<'_>) {
+    let bano2mimir = Path::new(env!("OUT_DIR"))
+        .join("../../../bano2mimir")
+        .display()
+        .to_string();
+    crate::launch_and_assert(
+        &bano2mimir,
+        &[
+            "--input=./tests/fixtures/sample-bano.csv".into(),
+            format!("--connection-string={}", es_wrapper.host()),
+            "--document-model=typeless".into(),
+        ],
+        &es_wrapper,
+    );
+    assert_eq!(es_wrapper.count(None, "type:addr"), 35);
+
+    let mut bragi = BragiHandler::new_for(&es_wrapper);
+    let status = bragi.get_json("/status");
+    assert_eq!(status.pointer("/status"), Some(&json!("good")));
+    assert_eq!(status.pointer("/incompatible_indexes"), None);
+
+    let res = bragi.get("/autocomplete?q=15 Rue Hector Malot (Paris)");
+    assert_eq!(
+        get_values(&res, "label").first(),
+        Some(&"15 Rue Hector Malot (Paris)")
+    );
+    assert_eq!(get_values(&res, "type").first(), Some(&"house"));
+
+    let res = bragi.get("/features/addr:2.376379;48.846495:15");
+    assert_eq!(get_values(&res, "name"), vec!["15 Rue Hector Malot"]);
+
+    let res = bragi.get("/reverse?lon=2.37716&lat=48.8468");
+    assert_eq!(
+        get_values(&res, "label"),
+        vec!["20 Rue Hector Malot (Paris)"]
+    );
+
+    // the scrolls of the exports, the dumps and the migrations
+    let mut rubber = Rubber::new(&es_wrapper.host()).with_document_model(DocumentModel::Typeless);
+    let places = rubber.clone().scroll_places("munin_addr").unwrap();
+    assert_eq!(places.map(Result::unwrap).count(), 35);
+    let addrs: Vec<Addr> = rubber.get_all_objects_from_index("munin_addr").unwrap();
+    assert_eq!(addrs.len(), 35);
+}
@@ -30,6 +30,7 @@
 
 use cosmogony::ZoneType;
 use geo::prelude::BoundingRect;
+use mimir::backend::SearchBackend;
 use mimir::rubber::{self, IndexSettings, Rubber};
 use mimir::{Admin, Coord, MimirObject, Street};
 use serde_json::{json, Value};
@@ -48,6 +49,8 @@ fn check_has_bob(es: &crate::ElasticSearchWrapper<'_>) {
             "street"
         );
         let es_bob = es_elt.pointer("/_source").unwrap();
+        // the type is also written in the document, for the typeless indexes
+        assert_eq!(es_bob.pointer("/type"), Some(&json!("street")));
         assert_eq!(es_bob.pointer("/id"), Some(&json!("bob")));
         assert_eq!(es_bob.pointer("/name"), Some(&json!("bob's street")));
         assert_eq!(es_bob.pointer("/label"), Some(&json!("bob's name")));
@@ -90,6 +93,25 @@ pub fn rubber_zero_downtime_test(mut es: crate::ElasticSearchWrapper<'_>) {
 
     check_has_bob(&es);
 
+    // the index is loaded without replicas nor refresh, and gets its final settings
+    // when published
+    let res = reqwest::blocking::get(&format!(
+        "{}/munin_street_{}/_settings",
+        es.docker_wrapper.host(),
+        dataset
+    ))
+    .unwrap();
+    let settings: Value = res.json().unwrap();
+    let index_settings = settings.as_object().unwrap().values().next().unwrap();
+    assert_eq!(
+        index_settings.pointer("/settings/index/number_of_replicas"),
+        Some(&json!("1"))
+    );
+    assert_eq!(
+        index_settings.pointer("/settings/index/refresh_interval"),
+        None
+    );
+
     let coord = Coord::new(2.683_262_90f64, 48.511_072_2f64);
     let bobette = Street {
         id: "bobette".to_string(),
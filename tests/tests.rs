@@ -47,6 +47,7 @@ mod bragi_synonyms_test;
 mod bragi_three_cities_test;
 mod canonical_import_process_test;
 mod cosmogony2mimir_test;
+mod es7_test;
 mod openaddresses2mimir_test;
 mod osm2mimir_bano2mimir_test;
 mod osm2mimir_test;
@@ -170,3 +171,14 @@ fn all_tests() {
         &docker_wrapper,
     ));
 }
+
+/// The `typeless` document model on Elasticsearch 7, in its own docker.
+/// Run by the CI with `cargo test --test tests es7 -- --ignored`
+#[test]
+#[ignore]
+fn es7_tests() {
+    let _guard = mimir::logger_init();
+    let docker_wrapper = DockerWrapper::new_es7().unwrap();
+
+    es7_test::typeless_bano_test(ElasticSearchWrapper::new(&docker_wrapper));
+}